@@ -1,53 +1,44 @@
+extern crate aocutil;
+
+use aocutil::vm::{AsmError, Effect, InstructionSet, Machine, Operand, Registers};
+use std::cmp;
 use std::fs::File;
 use std::io::Read;
-use std::collections::HashMap;
-use std::cmp;
-
-fn process(input: &str) -> (HashMap<&str, i32>, i32) {
-    let mut max_value = 0;
-    let mut registers = HashMap::new();
-    for instr in input.lines() {
-        let default = 0;
-        let mut tokens = instr.split_whitespace();
-        let register = tokens.next().unwrap();
-        let cmd = tokens.next().unwrap();
-        let amount = tokens.next().unwrap().parse::<i32>().unwrap();
-        tokens.next().unwrap();
-        let target_register = tokens.next().unwrap();
-        let cmp = tokens.next().unwrap();
-        let bounds = tokens.next().unwrap().parse::<i32>().unwrap();
-
-        let &target_value = registers.get(target_register).unwrap_or(&default);
-        let cond = match cmp {
-            ">" => target_value > bounds,
-            ">=" => target_value >= bounds,
-            "<" => target_value < bounds,
-            "<=" => target_value <= bounds,
-            "==" => target_value == bounds,
-            "!=" => target_value != bounds,
-            _ => false,
-        };
 
-        if !cond {
-            continue;
-        }
+fn cpu() -> InstructionSet {
+    InstructionSet::new()
+        .op("inc", 2, 1, |r, a| {
+            if let Operand::Reg(dst) = &a[0] {
+                r.add(dst, r.value(&a[1]));
+            }
+            Effect::Next
+        })
+        .op("dec", 2, 1, |r, a| {
+            if let Operand::Reg(dst) = &a[0] {
+                r.add(dst, -r.value(&a[1]));
+            }
+            Effect::Next
+        })
+}
 
-        let &value = registers.get(register).unwrap_or(&default);
-        let new_value = match cmd {
-            "inc" => value + amount,
-            "dec" => value - amount,
-            _ => value,
-        };
+fn process(input: &str) -> Result<(Registers, i64), AsmError> {
+    let set = cpu();
+    let mut machine = Machine::new(&set, set.parse(input)?)?;
 
-        max_value = cmp::max(max_value, new_value);
-        registers.insert(register, new_value);
+    let mut max_value = 0;
+    while machine.step(&mut |_, _| ()) {
+        max_value = machine
+            .registers
+            .iter()
+            .map(|(_, v)| v)
+            .fold(max_value, cmp::max);
     }
 
-    (registers, max_value)
+    Ok((machine.registers, max_value))
 }
 
-fn answer_1(registers: &HashMap<&str, i32>) -> i32 {
-    *registers.values().max().unwrap_or(&0)
+fn answer_1(registers: &Registers) -> i64 {
+    registers.iter().map(|(_, v)| v).max().unwrap_or(0)
 }
 
 fn main() {
@@ -55,7 +46,7 @@ fn main() {
     let mut file = File::open("input.txt").unwrap();
     file.read_to_string(&mut input).unwrap();
 
-    let result = process(&input);
+    let result = process(&input).unwrap();
     println!("Part 1: {:?}", answer_1(&result.0));
     println!("Part 2: {:?}", result.1);
 }
@@ -67,14 +58,14 @@ mod test {
     #[test]
     fn examples_1() {
         let input = "b inc 5 if a > 1\na inc 1 if b < 5\nc dec -10 if a >= 1\nc inc -20 if c == 10";
-        let result = process(&input);
+        let result = process(input).unwrap();
         assert_eq!(answer_1(&result.0), 1);
     }
 
     #[test]
     fn examples_2() {
         let input = "b inc 5 if a > 1\na inc 1 if b < 5\nc dec -10 if a >= 1\nc inc -20 if c == 10";
-        let result = process(&input);
+        let result = process(input).unwrap();
         assert_eq!(result.1, 10);
     }
 }
@@ -0,0 +1,6 @@
+extern crate adventofcode201710;
+
+fn main() {
+    println!("Part 1: {:?}", adventofcode201710::answer_1(256, &[206, 63, 255, 131, 65, 80, 238, 157, 254, 24, 133, 2, 16, 0, 1, 3]));
+    println!("Part 2: {:?}", adventofcode201710::answer_2("206,63,255,131,65,80,238,157,254,24,133,2,16,0,1,3"));
+}
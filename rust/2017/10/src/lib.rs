@@ -0,0 +1,75 @@
+extern crate aocutil;
+#[cfg(test)]
+#[macro_use]
+extern crate quickcheck;
+
+mod stringcircle;
+use stringcircle::StringCircle;
+
+pub fn answer_1(size: usize, input: &[usize]) -> i32 {
+    let mut circle = StringCircle::with_size(size);
+    circle.hash(input)
+}
+
+pub fn answer_2(input: &str) -> String {
+    to_hex(&knot_hash(input))
+}
+
+/// The full Knot Hash of `input`: 64 rounds over its bytes followed by a fixed suffix, reduced
+/// to a dense hash of 16 bytes.
+pub fn knot_hash(input: &str) -> [u8; 16] {
+    let mut lengths: Vec<usize> = input.bytes().map(|x| x as usize).collect();
+    lengths.extend_from_slice(&[17, 31, 73, 47, 23]);
+
+    let mut circle = StringCircle::new();
+    for _ in 0..64 {
+        circle.hash(&lengths);
+    }
+
+    let mut hash = [0; 16];
+    for (h, x) in hash.iter_mut().zip(circle.dense_hash()) {
+        *h = x as u8;
+    }
+    hash
+}
+
+pub fn to_hex(hash: &[u8; 16]) -> String {
+    hash.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+/// The 128 bits of a hash, most significant bit of the first byte first.
+pub fn to_bits(hash: &[u8; 16]) -> Vec<bool> {
+    hash.iter()
+        .flat_map(|&x| (0..8).rev().map(move |i| x & (1 << i) != 0))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn example1() {
+        assert_eq!(answer_1(5, &[3, 4, 1, 5]), 12)
+    }
+
+    #[test]
+    fn bits() {
+        let hash = knot_hash("flqrgnkx-0");
+        assert_eq!(
+            "11010100",
+            to_bits(&hash)[..8]
+                .iter()
+                .map(|&b| if b { '1' } else { '0' })
+                .collect::<String>()
+        );
+    }
+
+    #[test]
+    fn example2() {
+        assert_eq!(answer_2(""), String::from("a2582a3a0e66e6e86e3812dcb672a272"));
+        assert_eq!(answer_2("AoC 2017"), String::from("33efeb34ea91902bb2f59c9920caa6cd"));
+        assert_eq!(answer_2("1,2,3"), String::from("3efbe78a8d82f29979031a4aa0b16a9d"));
+        assert_eq!(answer_2("1,2,4"), String::from("63960835bcdc130f0b66d7ff4f6a5a8e"));
+    }
+}
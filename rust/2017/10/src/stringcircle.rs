@@ -1,6 +1,10 @@
+use aocutil::Ring;
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct StringCircle {
-    knots: Vec<i32>,
+    knots: Ring,
+    /// The knot at the current position.
+    current: usize,
     pos: usize,
     skip_size: usize,
 }
@@ -12,7 +16,8 @@ impl StringCircle {
 
     pub fn with_size(size: usize) -> Self {
         StringCircle {
-            knots: (0..(size as i32)).collect(),
+            knots: Ring::from_order(0..size, size),
+            current: 0,
             pos: 0,
             skip_size: 0,
         }
@@ -23,36 +28,27 @@ impl StringCircle {
         debug_assert!(knotlen > 2);
 
         for length in input {
-            let pos = self.pos;
-            circular_reverse(&mut self.knots, pos, pos + *length - 1);
-            self.pos = position_looped(knotlen, self.pos + *length + self.skip_size);
+            self.current = self.knots.reverse(self.current, *length);
+            let step = *length + self.skip_size;
+            self.current = self.knots.nth(self.current, step);
+            self.pos = (self.pos + step) % knotlen;
             self.skip_size += 1;
         }
 
-        self.knots[0] * self.knots[1]
+        let knots = self.knots();
+        knots[0] * knots[1]
     }
 
-    pub fn dense_hash(&self) -> Vec<i32> {
-        let size = self.knots.len() / 16;
-        (0..size)
-            .map(|x| x * 16)
-            .map(|idx| xor_slice(&self.knots[idx..idx + 16]))
-            .collect()
+    /// The knots in order, starting at the first position.
+    pub fn knots(&self) -> Vec<i32> {
+        let first = self.knots.nth_back(self.current, self.pos);
+        self.knots.iter_from(first).map(|k| k as i32).collect()
     }
-}
 
-fn circular_reverse<T>(slice: &mut [T], start: usize, end: usize) -> &[T] {
-    let len = slice.len();
-    if len <= 1 {
-        return slice
-    }
-
-    for cur in start..middle(start, end) {
-        let end_cur = end - (cur - start);
-        slice.swap(position_looped(len, cur), position_looped(len, end_cur));
+    pub fn dense_hash(&self) -> Vec<i32> {
+        let knots = self.knots();
+        knots.chunks(16).map(xor_slice).collect()
     }
-
-    slice
 }
 
 #[inline]
@@ -60,20 +56,6 @@ fn xor_slice(slice: &[i32]) -> i32 {
     slice.iter().fold(0, |acc, &x| acc ^ x)
 }
 
-#[inline]
-fn middle(a: usize, b: usize) -> usize {
-    a + ((b as f64 - a as f64) / 2.0).ceil() as usize
-}
-
-#[inline]
-fn position_looped(len: usize, pos: usize) -> usize {
-    match len {
-        0 => 0,
-        1 => 0,
-        _ => pos % len,
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -86,46 +68,34 @@ mod test {
     }
 
     #[test]
-    fn circular_reverse_with_single_element_does_nothing() {
-        assert_eq!(circular_reverse(&mut [1], 0, 0), [1]);
-    }
-
-    #[test]
-    fn circular_reverse_extended() {
-        assert_eq!(circular_reverse(&mut [0, 1, 2, 3, 4], 0, 2), [2, 1, 0, 3, 4]);
-        assert_eq!(circular_reverse(&mut [2, 1, 0, 3, 4], 3, 6), [4, 3, 0, 1, 2]);
-        assert_eq!(circular_reverse(&mut [4, 3, 0, 1, 2], 1, 5), [3, 4, 2, 1, 0]);
-        assert_eq!(circular_reverse(&mut [0, 1, 2, 3, 4], 0, 2), [2, 1, 0, 3, 4]);
-
-        assert_eq!(circular_reverse(&mut [4, 0, 2, 1, 3, 5], 5, 10), [1, 2, 0, 4, 5, 3]);
-
+    fn hash_steps() {
+        let mut string = StringCircle::with_size(5);
+        string.hash(&[3]);
+        assert_eq!(string.knots(), [2, 1, 0, 3, 4]);
+        string.hash(&[4]);
+        assert_eq!(string.knots(), [4, 3, 0, 1, 2]);
+        string.hash(&[1]);
+        assert_eq!(string.knots(), [4, 3, 0, 1, 2]);
+        string.hash(&[5]);
+        assert_eq!(string.knots(), [3, 4, 2, 1, 0]);
     }
 
     #[test]
-    fn position_looped_loops() {
-        assert_eq!(position_looped(10, 9), 9);
-        assert_eq!(position_looped(10, 10), 0);
-        assert_eq!(position_looped(10, 11), 1);
+    fn hash_with_no_length_does_nothing() {
+        let mut string = StringCircle::with_size(5);
+        string.hash(&[0]);
+        assert_eq!(string.knots(), [0, 1, 2, 3, 4]);
     }
 
     quickcheck! {
-        fn circurlar_reverse_full(xs: Vec<i32>) -> TestResult {
-            let len = xs.len();
-            if len == 0 {
-                return TestResult::discard()
-            }
-            let mut rev = xs.clone();
-            let mut xs = xs;
-            rev.reverse();
-            TestResult::from_bool(rev == circular_reverse(xs.as_mut_slice(), 0, len - 1))
-        }
-
-        fn position_looped_never_oob(len: usize, idx: usize) -> TestResult {
-            if len == 0 {
+        fn hash_full_length_reverses(size: usize) -> TestResult {
+            if size <= 2 || size > 1000 {
                 return TestResult::discard()
             }
-
-            TestResult::from_bool(position_looped(len, idx) < len)
+            let mut string = StringCircle::with_size(size);
+            string.hash(&[size]);
+            let rev: Vec<i32> = (0..size as i32).rev().collect();
+            TestResult::from_bool(rev == string.knots())
         }
     }
 }
@@ -0,0 +1,49 @@
+extern crate adventofcode201710;
+extern crate aocutil;
+
+use adventofcode201710::{knot_hash, to_bits};
+use aocutil::{Grid, MASK_CROSSHAIR};
+use std::fs::File;
+use std::io::Read;
+
+/// The disk, with `true` for used squares. Each row is the knot hash of the key and the row
+/// number.
+fn disk(key: &str) -> Grid<bool> {
+    let rows = (0..128)
+        .map(|row| to_bits(&knot_hash(&format!("{}-{}", key, row))))
+        .collect();
+    Grid::from_vec2d(rows)
+}
+
+fn answer_1(key: &str) -> usize {
+    disk(key).iter().filter(|&(_, &used)| used).count()
+}
+
+fn answer_2(key: &str) -> usize {
+    disk(key).components(MASK_CROSSHAIR, |&used| used).len()
+}
+
+fn main() {
+    let mut input = String::new();
+    let mut file = File::open("input.txt").unwrap();
+    file.read_to_string(&mut input).unwrap();
+
+    let key = input.trim();
+    println!("Part 1: {}", answer_1(key));
+    println!("Part 2: {}", answer_2(key));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn example1() {
+        assert_eq!(answer_1("flqrgnkx"), 8108);
+    }
+
+    #[test]
+    fn example2() {
+        assert_eq!(answer_2("flqrgnkx"), 1242);
+    }
+}
@@ -1,15 +1,19 @@
+extern crate aocutil;
+
+use aocutil::Ring;
+
 pub fn answer_1(steps: usize) -> u32 {
-    let mut last_pos = 0;
-    let mut buf: Vec<u32> = Vec::with_capacity(2018);
-    buf.push(0);
+    let mut buf = Ring::with_capacity(2018);
+    let mut last = 0;
+    buf.insert_after(last, 0);
 
     for x in 1..2018 {
-        let pos = wrap_position(buf.len(), last_pos + steps) + 1;
-        buf.insert(pos, x as u32);
-        last_pos = pos;
+        let pos = buf.nth(last, steps);
+        buf.insert_after(pos, x);
+        last = x;
     }
 
-    buf[last_pos + 1]
+    buf.next(last) as u32
 }
 
 pub fn answer_2(steps: usize) -> u32 {
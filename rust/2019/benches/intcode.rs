@@ -67,7 +67,7 @@ fn run_test(program: &[i64], input: &[i64]) -> Result<Option<i64>> {
         cpu.input(i);
     }
 
-    cpu.run_complete()
+    Ok(cpu.run_complete()?)
 }
 
 macro_rules! test {
@@ -0,0 +1,374 @@
+use crate::intcode::ascii::AsciiMachine;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::collections::HashMap;
+
+pub const CHECKPOINT: &str = "Security Checkpoint";
+
+/// Items which end the game or leave the droid unable to move once taken.
+pub const UNSAFE_ITEMS: [&str; 5] = [
+    "escape pod",
+    "giant electromagnet",
+    "infinite loop",
+    "molten lava",
+    "photons",
+];
+
+const EJECTED: &str = "ejected back to the checkpoint";
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Room {
+    pub name: String,
+    pub description: String,
+    pub doors: Vec<String>,
+    pub items: Vec<String>,
+}
+
+enum Section {
+    Description,
+    Doors,
+    Items,
+    Other,
+}
+
+impl Room {
+    /// Parse the last room described in `text`. A single response may describe several rooms,
+    /// e.g. when the droid is ejected from the pressure-sensitive floor.
+    pub fn parse(text: &str) -> Option<Room> {
+        let mut room: Option<Room> = None;
+        let mut section = Section::Other;
+
+        for line in text.lines() {
+            if line.starts_with("== ") && line.ends_with(" ==") {
+                room = Some(Room {
+                    name: line.trim_matches(|c| c == '=' || c == ' ').to_owned(),
+                    ..Room::default()
+                });
+                section = Section::Description;
+                continue;
+            }
+
+            let room = match room.as_mut() {
+                Some(r) => r,
+                None => continue,
+            };
+
+            match (line, &section) {
+                ("Doors here lead:", _) => section = Section::Doors,
+                ("Items here:", _) => section = Section::Items,
+                ("", Section::Description) => (),
+                ("", _) => section = Section::Other,
+                (l, Section::Doors) => room.doors.push(l.trim_start_matches("- ").to_owned()),
+                (l, Section::Items) => room.items.push(l.trim_start_matches("- ").to_owned()),
+                (l, Section::Description) => {
+                    if !room.description.is_empty() {
+                        room.description.push('\n');
+                    }
+                    room.description.push_str(l);
+                }
+                (_, Section::Other) => (),
+            }
+        }
+
+        room
+    }
+}
+
+/// Something that accepts commands and responds with text.
+pub trait Console {
+    fn send(&mut self, command: &str) -> Result<String>;
+}
+
+impl Console for AsciiMachine {
+    fn send(&mut self, command: &str) -> Result<String> {
+        self.write_line(command);
+        Ok(self.read_until_prompt()?)
+    }
+}
+
+pub fn opposite(door: &str) -> Result<&'static str> {
+    match door {
+        "north" => Ok("south"),
+        "south" => Ok("north"),
+        "east" => Ok("west"),
+        "west" => Ok("east"),
+        _ => Err(anyhow!("unknown door {}", door)),
+    }
+}
+
+/// Result of exploring the ship.
+#[derive(Debug, Default)]
+pub struct Expedition {
+    pub rooms: HashMap<String, Room>,
+    pub inventory: Vec<String>,
+    /// Doors leading from the starting room to the security checkpoint.
+    pub checkpoint: Option<Vec<String>>,
+    /// Door leading from the checkpoint to the pressure-sensitive floor.
+    pub plate: Option<String>,
+}
+
+/// Map every room reachable from `start`, picking up all safe items along the way. The droid is
+/// returned to `start` afterwards.
+pub fn explore<C: Console>(console: &mut C, start: &Room) -> Result<Expedition> {
+    let mut expedition = Expedition::default();
+    visit(console, start, &mut Vec::new(), &mut expedition)?;
+    Ok(expedition)
+}
+
+fn visit<C: Console>(
+    console: &mut C,
+    room: &Room,
+    path: &mut Vec<String>,
+    expedition: &mut Expedition,
+) -> Result<()> {
+    expedition.rooms.insert(room.name.clone(), room.clone());
+
+    for item in &room.items {
+        if !UNSAFE_ITEMS.contains(&item.as_str()) {
+            console.send(&format!("take {}", item))?;
+            expedition.inventory.push(item.clone());
+        }
+    }
+
+    if room.name == CHECKPOINT {
+        expedition.checkpoint = Some(path.clone());
+    }
+
+    let back = match path.last() {
+        Some(d) => Some(opposite(d)?),
+        None => None,
+    };
+
+    for door in &room.doors {
+        if Some(door.as_str()) == back {
+            continue;
+        }
+
+        let text = console.send(door)?;
+        let next = Room::parse(&text).ok_or_else(|| anyhow!("no room behind {}", door))?;
+        if text.contains(EJECTED) {
+            expedition.plate = Some(door.clone());
+            continue;
+        }
+
+        if !expedition.rooms.contains_key(&next.name) {
+            path.push(door.clone());
+            visit(console, &next, path, expedition)?;
+            path.pop();
+        }
+
+        console.send(opposite(door)?)?;
+    }
+
+    Ok(())
+}
+
+/// Walk to the checkpoint and try every combination of the collected items on the
+/// pressure-sensitive floor until the droid is let through. Returns the airlock password.
+pub fn crack<C: Console>(console: &mut C, expedition: &Expedition) -> Result<i64> {
+    let path = expedition
+        .checkpoint
+        .as_ref()
+        .ok_or_else(|| anyhow!("security checkpoint not found"))?;
+    let plate = expedition
+        .plate
+        .as_ref()
+        .ok_or_else(|| anyhow!("pressure-sensitive floor not found"))?;
+    let items = &expedition.inventory;
+    let re = Regex::new(r"typing (\d+)").unwrap();
+
+    for door in path {
+        console.send(door)?;
+    }
+
+    for item in items {
+        console.send(&format!("drop {}", item))?;
+    }
+
+    // Visit the combinations in Gray code order so only one item changes between attempts
+    let mut held = 0usize;
+    for i in 0..(1usize << items.len()) {
+        let gray = i ^ (i >> 1);
+        let changed = gray ^ held;
+        if changed != 0 {
+            let idx = changed.trailing_zeros() as usize;
+            let verb = if gray & changed != 0 { "take" } else { "drop" };
+            console.send(&format!("{} {}", verb, items[idx]))?;
+            held = gray;
+        }
+
+        let text = console.send(plate)?;
+        if text.contains(EJECTED) {
+            continue;
+        }
+
+        return re
+            .captures(&text)
+            .and_then(|c| c[1].parse().ok())
+            .ok_or_else(|| anyhow!("password not found in: {}", text));
+    }
+
+    Err(anyhow!("no combination of items opens the airlock"))
+}
+
+/// Explore the ship and find the password for the main airlock.
+pub fn find_password(rom: &[i64]) -> Result<i64> {
+    let mut machine = AsciiMachine::new(rom);
+    let intro = machine.read_until_prompt()?;
+    let start = Room::parse(&intro).ok_or_else(|| anyhow!("no starting room"))?;
+
+    let expedition = explore(&mut machine, &start)?;
+    crack(&mut machine, &expedition)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn describe(name: &str, doors: &[&str], items: &[String]) -> String {
+        let mut text = format!("\n\n\n== {} ==\nA room.\n\nDoors here lead:\n", name);
+        for d in doors {
+            text.push_str(&format!("- {}\n", d));
+        }
+        if !items.is_empty() {
+            text.push_str("\nItems here:\n");
+            for i in items {
+                text.push_str(&format!("- {}\n", i));
+            }
+        }
+        text.push_str("\nCommand?\n");
+        text
+    }
+
+    // Hull Breach --north-- Kitchen --east-- Security Checkpoint --north-- plate
+    //      |
+    //    west
+    //      |
+    //   Stables
+    struct Ship {
+        room: &'static str,
+        items: HashMap<&'static str, Vec<String>>,
+        inventory: Vec<String>,
+        weights: HashMap<&'static str, i64>,
+        target: i64,
+    }
+
+    impl Ship {
+        fn new() -> Self {
+            let mut items = HashMap::new();
+            items.insert("Hull Breach", vec![]);
+            items.insert("Kitchen", vec!["mug".to_owned(), "photons".to_owned()]);
+            items.insert("Stables", vec!["hay".to_owned(), "saddle".to_owned()]);
+            items.insert(CHECKPOINT, vec!["coin".to_owned()]);
+
+            let mut weights = HashMap::new();
+            weights.insert("mug", 1);
+            weights.insert("hay", 2);
+            weights.insert("saddle", 4);
+            weights.insert("coin", 8);
+
+            Ship {
+                room: "Hull Breach",
+                items,
+                inventory: vec![],
+                weights,
+                target: 11,
+            }
+        }
+
+        fn doors(room: &str) -> &'static [(&'static str, &'static str)] {
+            match room {
+                "Hull Breach" => &[("north", "Kitchen"), ("west", "Stables")],
+                "Kitchen" => &[("south", "Hull Breach"), ("east", CHECKPOINT)],
+                "Stables" => &[("east", "Hull Breach")],
+                CHECKPOINT => &[("west", "Kitchen"), ("north", "Floor")],
+                _ => &[],
+            }
+        }
+
+        fn look(&self) -> String {
+            let doors: Vec<_> = Ship::doors(self.room).iter().map(|(d, _)| *d).collect();
+            describe(self.room, &doors, &self.items[self.room])
+        }
+    }
+
+    impl Console for Ship {
+        fn send(&mut self, command: &str) -> Result<String> {
+            if let Some(item) = command.strip_prefix("take ") {
+                let items = self.items.get_mut(self.room).unwrap();
+                let idx = items.iter().position(|i| i == item).unwrap();
+                self.inventory.push(items.remove(idx));
+                return Ok(format!("\nYou take the {}.\n\nCommand?\n", item));
+            }
+
+            if let Some(item) = command.strip_prefix("drop ") {
+                let idx = self.inventory.iter().position(|i| i == item).unwrap();
+                let item = self.inventory.remove(idx);
+                self.items.get_mut(self.room).unwrap().push(item.clone());
+                return Ok(format!("\nYou drop the {}.\n\nCommand?\n", item));
+            }
+
+            let (_, next) = Ship::doors(self.room)
+                .iter()
+                .find(|(d, _)| *d == command)
+                .ok_or_else(|| anyhow!("You can't go that way."))?;
+
+            if *next == "Floor" {
+                let weight: i64 = self
+                    .inventory
+                    .iter()
+                    .map(|i| self.weights[i.as_str()])
+                    .sum();
+                if weight == self.target {
+                    return Ok(format!(
+                        "{}Oh, hello! You should be able to get in by typing 1234 on the keypad.",
+                        describe("Pressure-Sensitive Floor", &["south"], &[])
+                    ));
+                }
+
+                return Ok(format!(
+                    "{}A loud voice says you are ejected back to the checkpoint.\n{}",
+                    describe("Pressure-Sensitive Floor", &["south"], &[]),
+                    self.look()
+                ));
+            }
+
+            self.room = next;
+            Ok(self.look())
+        }
+    }
+
+    #[test]
+    fn parse_room() {
+        let text = "\n\n\n== Hull Breach ==\nYou got in through a hole in the floor here.\n\nDoors here lead:\n- north\n- west\n\nItems here:\n- mug\n\nCommand?\n";
+        assert_eq!(
+            Some(Room {
+                name: "Hull Breach".to_owned(),
+                description: "You got in through a hole in the floor here.".to_owned(),
+                doors: vec!["north".to_owned(), "west".to_owned()],
+                items: vec!["mug".to_owned()],
+            }),
+            Room::parse(text)
+        );
+
+        assert_eq!(None, Room::parse("\nYou take the mug.\n\nCommand?\n"));
+    }
+
+    #[test]
+    fn explore_and_crack() {
+        let mut ship = Ship::new();
+        let start = Room::parse(&ship.look()).unwrap();
+
+        let expedition = explore(&mut ship, &start).unwrap();
+        assert_eq!(4, expedition.rooms.len());
+        assert_eq!(vec!["mug", "coin", "hay", "saddle"], expedition.inventory);
+        assert_eq!(
+            Some(vec!["north".to_owned(), "east".to_owned()]),
+            expedition.checkpoint
+        );
+        assert_eq!(Some("north".to_owned()), expedition.plate);
+        assert_eq!("Hull Breach", ship.room);
+
+        assert_eq!(1234, crack(&mut ship, &expedition).unwrap());
+    }
+}
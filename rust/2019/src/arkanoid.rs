@@ -2,6 +2,7 @@ use crate::intcode;
 use anyhow::{anyhow, Result};
 use aocutil::{Point, Vector};
 use std::convert::TryFrom;
+use std::io::{BufRead, Write};
 
 type Grid = aocutil::Grid<Tile>;
 
@@ -60,9 +61,11 @@ pub struct Game {
     ball: Point,
     prg: intcode::Interpretor,
     grid: Grid,
+    inputs: Vec<Input>,
 }
 
-enum Input {
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Input {
     JoystickLeft,
     JoystickNeutral,
     JoystickRight,
@@ -78,6 +81,82 @@ impl From<Input> for i64 {
     }
 }
 
+impl TryFrom<i64> for Input {
+    type Error = anyhow::Error;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match value {
+            -1 => Ok(Input::JoystickLeft),
+            0 => Ok(Input::JoystickNeutral),
+            1 => Ok(Input::JoystickRight),
+            _ => Err(anyhow!("invalid joystick position {}", value)),
+        }
+    }
+}
+
+/// State of the screen when the game asks for the joystick position.
+pub struct Frame<'a> {
+    pub grid: &'a Grid,
+    pub score: i64,
+    pub paddle: Point,
+    pub ball: Point,
+}
+
+pub trait Joystick {
+    fn tilt(&mut self, frame: &Frame) -> Input;
+}
+
+/// Keeps the paddle underneath the ball.
+pub struct Autopilot;
+
+impl Joystick for Autopilot {
+    fn tilt(&mut self, frame: &Frame) -> Input {
+        match frame.paddle.x.cmp(&frame.ball.x) {
+            std::cmp::Ordering::Less => Input::JoystickRight,
+            std::cmp::Ordering::Greater => Input::JoystickLeft,
+            _ => Input::JoystickNeutral,
+        }
+    }
+}
+
+/// Plays back recorded inputs, leaving the joystick in the neutral position once they run out.
+pub struct Replay {
+    inputs: std::vec::IntoIter<Input>,
+}
+
+impl Replay {
+    pub fn new(inputs: Vec<Input>) -> Self {
+        Replay {
+            inputs: inputs.into_iter(),
+        }
+    }
+
+    /// Read a recording written by `write_inputs`.
+    pub fn read<R: BufRead>(r: R) -> Result<Self> {
+        let inputs = r
+            .lines()
+            .map(|l| Input::try_from(l?.trim().parse::<i64>()?))
+            .collect::<Result<_>>()?;
+
+        Ok(Replay::new(inputs))
+    }
+}
+
+impl Joystick for Replay {
+    fn tilt(&mut self, _: &Frame) -> Input {
+        self.inputs.next().unwrap_or(Input::JoystickNeutral)
+    }
+}
+
+/// Write inputs as recorded by a game, one per line.
+pub fn write_inputs<W: Write>(mut w: W, inputs: &[Input]) -> Result<()> {
+    for &i in inputs {
+        writeln!(w, "{}", i64::from(i))?;
+    }
+
+    Ok(())
+}
+
 impl Game {
     pub fn new(rom: &[i64]) -> Self {
         Game {
@@ -88,45 +167,62 @@ impl Game {
             grid: Grid::new(),
             paddle: Point::default(),
             ball: Point::default(),
+            inputs: Vec::new(),
         }
     }
 
     pub fn update(&mut self) -> Result<()> {
-        match self.prg.run()? {
-            intcode::State::Terminated(_) => {
-                self.over = true;
-            }
-            intcode::State::Suspended(x) => {
-                let y = self.get_output()?;
-                let v = self.get_output()?;
-                if x == -1 && y == 0 {
-                    self.score = v;
-                } else {
-                    let point = Point::new(x, 0 - y);
-                    let tile = Tile::try_from(v)?;
-                    self.grid.insert(point, tile);
-                    match tile {
-                        Tile::Ball => self.ball = point,
-                        Tile::HorizontalPaddle => self.paddle = point,
-                        Tile::Empty => {
-                            self.grid.remove(&point);
-                        }
-                        _ => (),
+        self.update_with(&mut Autopilot)
+    }
+
+    /// Process the next output of the game, asking `joystick` for input if needed.
+    pub fn update_with<J: Joystick>(&mut self, joystick: &mut J) -> Result<()> {
+        let Game {
+            prg,
+            grid,
+            score,
+            paddle,
+            ball,
+            inputs,
+            ..
+        } = self;
+        let mut moved = false;
+        let output = prg.read_n_with(&mut || {
+            moved = true;
+            let frame = Frame {
+                grid,
+                score: *score,
+                paddle: *paddle,
+                ball: *ball,
+            };
+            let input = joystick.tilt(&frame);
+            inputs.push(input);
+
+            let input = input.into();
+            *paddle += Vector::new(input, 0);
+            Some(input)
+        })?;
+
+        if moved {
+            self.init = true;
+        }
+
+        match output {
+            None => self.over = true,
+            Some([-1, 0, score]) => self.score = score,
+            Some([x, y, v]) => {
+                let point = Point::new(x, 0 - y);
+                let tile = Tile::try_from(v)?;
+                self.grid.insert(point, tile);
+                match tile {
+                    Tile::Ball => self.ball = point,
+                    Tile::HorizontalPaddle => self.paddle = point,
+                    Tile::Empty => {
+                        self.grid.remove(&point);
                     }
+                    _ => (),
                 }
             }
-            intcode::State::AwaitingInput => {
-                self.init = true;
-                let input = match self.paddle.x.cmp(&self.ball.x) {
-                    std::cmp::Ordering::Less => Input::JoystickRight,
-                    std::cmp::Ordering::Greater => Input::JoystickLeft,
-                    _ => Input::JoystickNeutral,
-                }
-                .into();
-
-                self.paddle = self.paddle + Vector::new(input, 0);
-                self.prg.input(input);
-            }
         }
 
         Ok(())
@@ -148,15 +244,43 @@ impl Game {
         &self.grid
     }
 
+    /// Joystick positions in the order they were given to the game.
+    pub fn inputs(&self) -> &[Input] {
+        &self.inputs
+    }
+
     pub fn count_blocks(&self) -> usize {
         self.grid.iter().filter(|(_, &t)| t == Tile::Block).count()
     }
+}
 
-    fn get_output(&mut self) -> Result<i64> {
-        match self.prg.run()? {
-            intcode::State::Suspended(x) => Ok(x),
-            intcode::State::Terminated(_) => Err(anyhow!("expected output")),
-            intcode::State::AwaitingInput => Err(anyhow!("expected input")),
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn replay_roundtrip() {
+        let inputs = vec![
+            Input::JoystickLeft,
+            Input::JoystickNeutral,
+            Input::JoystickRight,
+        ];
+        let mut buf = Vec::new();
+        write_inputs(&mut buf, &inputs).unwrap();
+        assert_eq!("-1\n0\n1\n", String::from_utf8(buf.clone()).unwrap());
+
+        let mut replay = Replay::read(&buf[..]).unwrap();
+        let grid = Grid::new();
+        let frame = Frame {
+            grid: &grid,
+            score: 0,
+            paddle: Point::default(),
+            ball: Point::default(),
+        };
+        let played: Vec<_> = (0..4).map(|_| replay.tilt(&frame)).collect();
+        assert_eq!(&inputs[..], &played[..3]);
+        assert_eq!(Input::JoystickNeutral, played[3]);
+
+        assert!(Replay::read(&b"2\n"[..]).is_err());
     }
 }
@@ -0,0 +1,141 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use adventofcode2019::adventure::{self, Console};
+use adventofcode2019::intcode::ascii::AsciiMachine;
+use adventofcode2019::intcode::Interpretor;
+
+const HELP: &str = "\
+Commands are sent to the droid as typed. n, s, e and w are short for the directions.
+
+  !history      list the commands sent so far
+  !N            send command number N from the history again
+  !undo         go back to before the last command
+  !save NAME    remember the current state as NAME
+  !load NAME    go back to the state saved as NAME
+  !dump FILE    write the current state to FILE
+  !restore FILE continue from the state in FILE
+  !auto         explore the ship and find the password
+  !help         show this message
+  !quit         leave the game";
+
+fn get_rom(path: &str) -> Result<Vec<i64>> {
+    let buffer = std::fs::read_to_string(path)?;
+
+    Ok(buffer
+        .trim()
+        .split(',')
+        .map(|x| x.parse().unwrap())
+        .collect())
+}
+
+fn expand(command: &str) -> &str {
+    match command {
+        "n" => "north",
+        "s" => "south",
+        "e" => "east",
+        "w" => "west",
+        _ => command,
+    }
+}
+
+fn main() -> Result<()> {
+    let rom = get_rom("input/2019/day25.txt")?;
+
+    if std::env::args().any(|a| a == "--auto") {
+        println!("{}", adventure::find_password(&rom)?);
+        return Ok(());
+    }
+
+    let mut machine = AsciiMachine::new(&rom);
+    let mut history: Vec<String> = Vec::new();
+    let mut undo: Vec<AsciiMachine> = Vec::new();
+    let mut saved: HashMap<String, (AsciiMachine, usize)> = HashMap::new();
+
+    print!("{}", machine.read_until_prompt()?);
+    std::io::stdout().flush()?;
+
+    let stdin = std::io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let line = line.trim();
+
+        match line.split_once(' ') {
+            Some(("!save", name)) => {
+                saved.insert(name.to_owned(), (machine.clone(), history.len()));
+                println!("Saved {}.", name);
+            }
+            Some(("!load", name)) => match saved.get(name) {
+                Some((m, len)) => {
+                    machine = m.clone();
+                    history.truncate(*len);
+                    undo.clear();
+                    println!("Loaded {}.", name);
+                }
+                None => println!("Nothing saved as {}.", name),
+            },
+            Some(("!dump", path)) => match machine.interpretor().save_file(path) {
+                Ok(()) => println!("Wrote {}.", path),
+                Err(e) => println!("Unable to write {}: {}", path, e),
+            },
+            Some(("!restore", path)) => match Interpretor::load_file(path) {
+                Ok(prg) => {
+                    machine = AsciiMachine::from(prg);
+                    history.clear();
+                    undo.clear();
+                    println!("Restored {}.", path);
+                }
+                Err(e) => println!("Unable to read {}: {}", path, e),
+            },
+            _ => match line {
+                "" => (),
+                "!help" => println!("{}", HELP),
+                "!quit" => break,
+                "!history" => {
+                    for (i, c) in history.iter().enumerate() {
+                        println!("{:>4}  {}", i, c);
+                    }
+                }
+                "!undo" => match undo.pop() {
+                    Some(m) => {
+                        machine = m;
+                        history.pop();
+                        println!("Undone.");
+                    }
+                    None => println!("Nothing to undo."),
+                },
+                "!auto" => println!("{}", adventure::find_password(&rom)?),
+                _ => {
+                    let command = match line.strip_prefix('!').map(|n| n.parse::<usize>()) {
+                        Some(Ok(n)) => match history.get(n) {
+                            Some(c) => c.clone(),
+                            None => {
+                                println!("No command number {}.", n);
+                                continue;
+                            }
+                        },
+                        Some(Err(_)) => {
+                            println!("Unknown command. Type !help for help.");
+                            continue;
+                        }
+                        None => expand(line).to_owned(),
+                    };
+
+                    undo.push(machine.clone());
+                    history.push(command.clone());
+                    print!("{}", machine.send(&command)?);
+
+                    if machine.is_terminated() {
+                        println!();
+                        break;
+                    }
+                }
+            },
+        }
+
+        std::io::stdout().flush()?;
+    }
+
+    Ok(())
+}
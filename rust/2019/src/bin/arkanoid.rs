@@ -1,7 +1,24 @@
-use anyhow::Result;
-use std::time::Duration;
+use anyhow::{anyhow, Result};
+use std::io::{BufReader, Write};
+use std::time::{Duration, Instant};
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
 
-use adventofcode2019::arkanoid;
+use adventofcode2019::arkanoid::{self, Autopilot, Frame, Game, Input, Joystick, Replay};
+
+const USAGE: &str = "\
+usage: arkanoid [--auto | --replay FILE] [--record FILE] [--headless]
+
+Without --auto or --replay the paddle is moved with the arrow keys. Press q to quit.
+
+  --auto          let the paddle follow the ball
+  --replay FILE   play back the inputs recorded in FILE
+  --record FILE   write the inputs of the game to FILE
+  --headless      run without drawing or delays and report the timing";
+
+const FRAME_DELAY: Duration = Duration::from_millis(50);
 
 fn get_rom(path: &str) -> Result<Vec<i64>> {
     let buffer = std::fs::read_to_string(path)?;
@@ -13,23 +30,156 @@ fn get_rom(path: &str) -> Result<Vec<i64>> {
         .collect())
 }
 
+/// Redraws the screen in place before passing control to another joystick.
+struct Display<J> {
+    out: Box<dyn Write>,
+    joystick: J,
+    error: Option<std::io::Error>,
+}
+
+impl<J> Display<J> {
+    fn draw(&mut self, frame: &Frame) -> std::io::Result<()> {
+        // Raw mode does not return the carriage on a new line
+        let grid = frame.grid.to_string().replace('\n', "\r\n");
+        write!(
+            self.out,
+            "{}Score: {}\r\n{}",
+            termion::cursor::Goto(1, 1),
+            frame.score,
+            grid
+        )?;
+        self.out.flush()
+    }
+}
+
+impl<J: Joystick> Joystick for Display<J> {
+    fn tilt(&mut self, frame: &Frame) -> Input {
+        if let Err(e) = self.draw(frame) {
+            self.error.get_or_insert(e);
+        }
+        std::thread::sleep(FRAME_DELAY);
+
+        self.joystick.tilt(frame)
+    }
+}
+
+/// Reads the arrow keys pressed since the last frame.
+struct Keyboard {
+    keys: termion::input::Keys<termion::AsyncReader>,
+    quit: bool,
+}
+
+impl Joystick for Keyboard {
+    fn tilt(&mut self, _: &Frame) -> Input {
+        let mut input = Input::JoystickNeutral;
+        for key in self.keys.by_ref() {
+            match key {
+                Ok(Key::Left) => input = Input::JoystickLeft,
+                Ok(Key::Right) => input = Input::JoystickRight,
+                Ok(Key::Char('q')) | Ok(Key::Ctrl('c')) | Err(_) => self.quit = true,
+                _ => (),
+            }
+        }
+
+        input
+    }
+}
+
+fn play<J: Joystick>(game: &mut Game, joystick: &mut J, stop: impl Fn(&J) -> bool) -> Result<()> {
+    while !game.is_over() && !stop(joystick) {
+        game.update_with(joystick)?;
+    }
+
+    Ok(())
+}
+
+/// Play while drawing each frame to `out`.
+fn play_on<J: Joystick>(
+    out: Box<dyn Write>,
+    game: &mut Game,
+    joystick: J,
+    stop: impl Fn(&J) -> bool,
+) -> Result<()> {
+    let mut display = Display {
+        out,
+        joystick,
+        error: None,
+    };
+    play(game, &mut display, |d| {
+        d.error.is_some() || stop(&d.joystick)
+    })?;
+
+    match display.error {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
 fn main() -> Result<()> {
+    let mut auto = false;
+    let mut headless = false;
+    let mut replay = None;
+    let mut record = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--auto" => auto = true,
+            "--headless" => headless = true,
+            "--replay" => replay = Some(args.next().ok_or_else(|| anyhow!(USAGE))?),
+            "--record" => record = Some(args.next().ok_or_else(|| anyhow!(USAGE))?),
+            _ => return Err(anyhow!(USAGE)),
+        }
+    }
+
     let mut rom = get_rom("input/2019/day13.txt")?;
     rom[0] = 2;
+    let mut game = Game::new(&rom);
 
-    let mut game = arkanoid::Game::new(&rom);
+    let replay = match replay {
+        Some(path) => Some(Replay::read(BufReader::new(std::fs::File::open(path)?))?),
+        None => None,
+    };
 
-    while !game.is_initialized() {
-        game.update()?;
-    }
+    if headless {
+        let start = Instant::now();
+        match replay {
+            Some(mut r) => play(&mut game, &mut r, |_| false)?,
+            None if auto => play(&mut game, &mut Autopilot, |_| false)?,
+            None => return Err(anyhow!("--headless requires --auto or --replay")),
+        }
+        println!(
+            "Score {} with {} blocks left after {} frames in {:?}",
+            game.get_score(),
+            game.count_blocks(),
+            game.inputs().len(),
+            start.elapsed()
+        );
+    } else {
+        let out = AlternateScreen::from(std::io::stdout().into_raw_mode()?);
+        let mut out: Box<dyn Write> = Box::new(out);
+        write!(out, "{}{}", termion::clear::All, termion::cursor::Hide)?;
 
-    while !game.is_over() {
-        game.update()?;
+        let result = match replay {
+            Some(r) => play_on(out, &mut game, r, |_| false),
+            None if auto => play_on(out, &mut game, Autopilot, |_| false),
+            None => {
+                let keyboard = Keyboard {
+                    keys: termion::async_stdin().keys(),
+                    quit: false,
+                };
+                play_on(out, &mut game, keyboard, |k| k.quit)
+            }
+        };
 
-        let g = game.get_grid();
-        println!("{}", g);
+        // The alternate screen has been left, so show the result on the normal one
+        print!("{}", termion::cursor::Show);
+        result?;
+        println!("Score: {}", game.get_score());
+    }
 
-        std::thread::sleep(Duration::from_millis(50));
+    if let Some(path) = record {
+        arkanoid::write_inputs(std::fs::File::create(path)?, game.inputs())?;
     }
 
     Ok(())
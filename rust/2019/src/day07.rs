@@ -1,6 +1,7 @@
-use crate::intcode::{Interpretor, State};
+use crate::intcode::Interpretor;
 use anyhow::{anyhow, Result};
 use permutohedron::Heap;
+use std::sync::mpsc::channel;
 
 fn get_signal(phases: &[i64], memory: &[i64]) -> i64 {
     let amplifiers = phases.iter().map(|&phase| {
@@ -16,33 +17,31 @@ fn get_signal(phases: &[i64], memory: &[i64]) -> i64 {
 }
 
 fn get_chained_signal(phases: &[i64], memory: &[i64]) -> Result<i64> {
-    let mut amplifiers: Vec<_> = phases
-        .iter()
-        .map(|&phase| {
-            let mut i = Interpretor::new(&memory);
-            i.input(phase);
-            i
+    let (senders, receivers): (Vec<_>, Vec<_>) = phases.iter().map(|_| channel()).unzip();
+
+    for (tx, &phase) in senders.iter().zip(phases) {
+        tx.send(phase)?;
+    }
+    senders[0].send(0)?;
+
+    // Amplifier i reads from channel i and writes to the next amplifier, with the last one
+    // feeding back into the first
+    let amplifiers: Vec<_> = receivers
+        .into_iter()
+        .enumerate()
+        .map(|(i, rx)| {
+            let tx = senders[(i + 1) % senders.len()].clone();
+            Interpretor::new(memory).spawn(rx, tx)
         })
         .collect();
+    drop(senders);
 
-    let last_amplifier = amplifiers.len() - 1;
-    let mut signal = 0;
-    loop {
-        for (i, a) in amplifiers.iter_mut().enumerate() {
-            a.input(signal);
-            match a.run()? {
-                State::Suspended(x) => signal = x,
-                State::Terminated(x) => {
-                    if i == last_amplifier {
-                        return Ok(x.unwrap());
-                    } else {
-                        continue;
-                    }
-                }
-                State::AwaitingInput => return Err(anyhow!("expected input")),
-            }
-        }
+    let mut signal = None;
+    for a in amplifiers {
+        signal = a.join().map_err(|_| anyhow!("amplifier panicked"))??;
     }
+
+    signal.ok_or_else(|| anyhow!("expected signal"))
 }
 
 #[aoc_generator(day7)]
@@ -1,108 +1,42 @@
+use crate::sif::{Color, Image};
 use anyhow::{anyhow, Result};
 
 const HEIGHT: usize = 6;
 const WIDTH: usize = 25;
 
-type Grid = aocutil::Grid<Color>;
-type Point = aocutil::Point;
-
-#[derive(Debug, PartialEq, Clone, Copy)]
-pub enum Color {
-    Black,
-    White,
-    Transparent,
-}
-
-impl Default for Color {
-    fn default() -> Self {
-        Color::Transparent
-    }
-}
-
-impl std::fmt::Display for Color {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Color::White => '█',
-                Color::Black => '░',
-                Color::Transparent => ' ',
-            }
-        )
-    }
-}
-
-impl std::str::FromStr for Color {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.chars().nth(0) {
-            Some('0') => Ok(Color::Black),
-            Some('1') => Ok(Color::White),
-            Some('2') => Ok(Color::Transparent),
-            _ => Err(anyhow!("invalid color")),
-        }
-    }
-}
-
-pub fn decode(input: &[Color], width: usize, height: usize) -> Result<Grid> {
-    let mut g = Grid::default();
-    let layers: Vec<&[Color]> = input.chunks(width * height).collect();
-
-    for y in 0..height {
-        for x in 0..width {
-            let i = y * width + x;
-            let p = layers
-                .iter()
-                .find_map(|l| {
-                    if l[i] != Color::Transparent {
-                        Some(l[i])
-                    } else {
-                        None
-                    }
-                })
-                .unwrap_or(Color::Transparent);
-            g.insert(Point::new(x as i64, (height - 1 - y) as i64), p);
-        }
-    }
-
-    Ok(g)
-}
-
 #[aoc_generator(day8)]
-pub fn input_generator(input: &str) -> Vec<Color> {
-    input
-        .lines()
-        .map(|l| {
-            l.chars()
-                .map(|x| format!("{}", x).parse().expect("want color"))
-        })
-        .flatten()
-        .collect()
-}
-
-pub fn count_value(xs: &[Color], x: Color) -> usize {
-    xs.iter().filter(|&y| *y == x).count()
+pub fn input_generator(input: &str) -> String {
+    input.trim().to_string()
 }
 
 #[aoc(day8, part1)]
-fn answer_1(input: &[Color]) -> Result<usize> {
-    input
-        .chunks(WIDTH * HEIGHT)
-        .min_by(|&a, &b| count_value(a, Color::Black).cmp(&count_value(b, Color::Black)))
-        .map(|l| count_value(l, Color::White) * count_value(l, Color::Transparent))
+fn answer_1(input: &str) -> Result<usize> {
+    let count = |h: &std::collections::HashMap<Color, usize>, c| h.get(&c).copied().unwrap_or(0);
+
+    Image::parse(input, WIDTH, HEIGHT)?
+        .histograms()
+        .iter()
+        .min_by_key(|h| count(h, Color::Black))
+        .map(|h| count(h, Color::White) * count(h, Color::Transparent))
         .ok_or_else(|| anyhow!("unable to find layer"))
 }
 
 #[aoc(day8, part2)]
-fn answer_2(input: &[Color]) -> Result<String> {
-    Ok(format!("\n{}", decode(input, WIDTH, HEIGHT)?))
+fn answer_2(input: &str) -> Result<String> {
+    let image = Image::parse(input, WIDTH, HEIGHT)?;
+    let text = image.ocr()?;
+    if text.contains('?') {
+        return Ok(format!("\n{}", image.render()));
+    }
+
+    Ok(text)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::sif::Grid;
+    use aocutil::Point;
 
     #[test]
     fn examples_2() {
@@ -114,7 +48,9 @@ mod test {
 
         assert_eq!(
             g,
-            decode(&input_generator("0222112222120000"), 2, 2).unwrap()
+            Image::parse(&input_generator("0222112222120000"), 2, 2)
+                .unwrap()
+                .composite()
         );
     }
 }
@@ -1,70 +1,31 @@
-use euclid;
-
-type Point = euclid::Point2D<f64, euclid::UnknownUnit>;
-
-fn is_in_line_of_sight(asteroids: &[Point], a: &Point, b: &Point) -> bool {
-    asteroids
-        .iter()
-        .filter(|&x| x != a && x != b)
-        .find(|&x| {
-            let len_ab = a.distance_to(*b);
-            let len_ax = a.distance_to(*x);
-            let len_bx = b.distance_to(*x);
-
-            (len_ax + len_bx) - len_ab <= 0.000_1
-        })
-        .is_none()
-}
-
-fn find_best_place(asteroids: &[Point]) -> (&Point, usize) {
-    let mut max = (&asteroids[0], 0);
-
-    for a in asteroids {
-        let mut visible = 0;
-        for b in asteroids {
-            if a == b {
-                continue;
-            }
-
-            if is_in_line_of_sight(asteroids, a, b) {
-                visible += 1;
-                if visible > max.1 {
-                    max = (&a, visible);
-                }
-            }
-        }
-    }
-
-    max
-}
+use aocutil::{best_visibility, Point, Ray};
 
+/// Asteroid positions, with y pointing up as in `Grid::from_vec2d`. Rows of the map are at
+/// negative y.
 #[aoc_generator(day10)]
 pub fn input_generator(input: &str) -> Vec<Point> {
     input
         .lines()
         .enumerate()
-        .map(|(y, l)| {
-            l.chars().enumerate().filter_map(move |(x, p)| {
-                if p == '#' {
-                    Some(Point::new(x as f64, y as f64))
-                } else {
-                    None
-                }
-            })
+        .flat_map(|(y, l)| {
+            l.chars()
+                .enumerate()
+                .filter(|&(_, p)| p == '#')
+                .map(move |(x, _)| Point::new(x as i64, -(y as i64)))
         })
-        .flatten()
         .collect()
 }
 
 #[aoc(day10, part1)]
 fn answer_1(input: &[Point]) -> usize {
-    let (_, visible) = find_best_place(input);
-    visible
+    best_visibility(input).map_or(0, |v| v.visible())
 }
 
 #[aoc(day10, part2)]
-fn answer_2(_input: &[Point]) -> usize {
-    0
+fn answer_2(input: &[Point]) -> Option<i64> {
+    let p = *best_visibility(input)?.sweep(&Ray::UP).get(199)?;
+
+    Some(p.x * 100 - p.y)
 }
 
 #[cfg(test)]
@@ -172,7 +133,7 @@ mod test {
     }
 
     #[test]
-    fn test_los() {
+    fn test_visibility() {
         let asteroids = input_generator(
             r#".#..#
 .....
@@ -181,11 +142,48 @@ mod test {
 ...##"#,
         );
 
-        let best = &asteroids[8];
-        assert!(!is_in_line_of_sight(&asteroids, best, &asteroids[0]),);
-        assert!(is_in_line_of_sight(&asteroids, best, &asteroids[1]),);
-        assert!(is_in_line_of_sight(&asteroids, best, &asteroids[2]),);
-        assert!(is_in_line_of_sight(&asteroids, best, &asteroids[3]),);
-        assert!(is_in_line_of_sight(&asteroids, best, &asteroids[4]),);
+        let best = best_visibility(&asteroids).unwrap();
+        assert_eq!(Point::new(3, -4), best.origin());
+        assert_eq!(8, best.visible());
+        assert_eq!(
+            &[Point::new(2, -2), Point::new(1, 0)],
+            best.along(&Ray::new(-1, 2))
+        );
+    }
+
+    #[test]
+    fn examples_2() {
+        let asteroids = input_generator(
+            r#".#..##.###...#######
+##.############..##.
+.#.######.########.#
+.###.#######.####.#.
+#####.##.#.##.###.##
+..#####..#.#########
+####################
+#.####....###.#.#.##
+##.#################
+#####.##.###..####..
+..######..##.#######
+####.##.####...##..#
+.#####..#.######.###
+##...#.##########...
+#.##########.#######
+.####.#.###.###.#.##
+....##.##.###..#####
+.#.#.###########.###
+#.#.#.#####.####.###
+###.##.####.##.#..##"#,
+        );
+
+        let order = best_visibility(&asteroids).unwrap().sweep(&Ray::UP);
+        assert_eq!(Point::new(11, -12), order[0]);
+        assert_eq!(Point::new(12, -1), order[1]);
+        assert_eq!(Point::new(12, -8), order[9]);
+        assert_eq!(Point::new(16, 0), order[19]);
+        assert_eq!(Point::new(10, -16), order[99]);
+        assert_eq!(Point::new(9, -6), order[198]);
+        assert_eq!(Point::new(11, -1), order[298]);
+        assert_eq!(Some(802), answer_2(&asteroids));
     }
 }
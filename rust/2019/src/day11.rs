@@ -1,6 +1,6 @@
-use crate::intcode::{Interpretor, State};
+use crate::robot::{Agent, Codec, Pose};
 use anyhow::{anyhow, Result};
-use aocutil::{Point, Direction, Vector};
+use aocutil::{Direction, Point};
 use std::collections::HashMap;
 
 const BLACK: i64 = 0;
@@ -9,61 +9,45 @@ const WHITE: i64 = 1;
 const LEFT: i64 = 0;
 const RIGHT: i64 = 1;
 
-struct Robot {
-    brain: Interpretor,
-    direction: Direction,
-    position: Point,
+/// The robot is sent the color of its panel and responds with the color to paint and the
+/// direction to turn.
+struct Painter;
+
+impl Codec for Painter {
+    type Command = i64;
+    type Response = (i64, i64);
+    const OUTPUTS: usize = 2;
+
+    fn encode(&self, color: &i64) -> Vec<i64> {
+        vec![*color]
+    }
+
+    fn decode(&self, outputs: &[i64]) -> Result<(i64, i64)> {
+        Ok((outputs[0], outputs[1]))
+    }
 }
 
 fn paint_hull(input: &[i64], starting_color: i64) -> Result<HashMap<Point, i64>> {
     let mut panels: HashMap<Point, i64> = HashMap::new();
-    let mut painted: HashMap<Point, usize> = HashMap::new();
-    let mut rbt = Robot {
-        brain: Interpretor::new(input),
-        direction: Direction::Up,
-        position: Point::new(0, 0),
-    };
+    let mut robot = Agent::new(input, Painter);
+    let mut pose = Pose::new(Point::new(0, 0), Direction::Up);
 
-    panels.insert(rbt.position, starting_color);
+    panels.insert(pose.position, starting_color);
 
     loop {
-        let pos = rbt.position;
-        let color = panels.get(&pos).unwrap_or(&BLACK);
-        rbt.brain.input(*color);
-
-        match rbt.brain.run()? {
-            State::Suspended(color) => {
-                panels.insert(pos, color);
-                *painted.entry(pos).or_insert(0) += 1;
-            }
-            State::Terminated(_) => break,
-            State::AwaitingInput => return Err(anyhow!("expected input")),
-        }
+        let current = *panels.get(&pose.position).unwrap_or(&BLACK);
+        let (color, direction) = match robot.send(&current)? {
+            Some(response) => response,
+            None => break,
+        };
 
-        match rbt.brain.run()? {
-            State::Suspended(direction) => {
-                rbt.direction = match direction {
-                    LEFT => match rbt.direction {
-                        Direction::Up => Direction::Left,
-                        Direction::Left => Direction::Down,
-                        Direction::Down => Direction::Right,
-                        Direction::Right => Direction::Up,
-                    },
-                    RIGHT => match rbt.direction {
-                        Direction::Up => Direction::Right,
-                        Direction::Right => Direction::Down,
-                        Direction::Down => Direction::Left,
-                        Direction::Left => Direction::Up,
-                    },
-                    _ => return Err(anyhow!("invalid direction {}", direction)),
-                }
-            }
-            State::Terminated(_) => return Err(anyhow!("expected second input")),
-            State::AwaitingInput => return Err(anyhow!("expected input")),
+        panels.insert(pose.position, color);
+        match direction {
+            LEFT => pose.turn_left(),
+            RIGHT => pose.turn_right(),
+            _ => return Err(anyhow!("invalid direction {}", direction)),
         }
-
-        let vec: Vector = rbt.direction.into();
-        rbt.position = pos + vec;
+        pose.advance();
     }
 
     Ok(panels)
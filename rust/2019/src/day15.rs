@@ -1,19 +1,13 @@
-use crate::intcode;
+use crate::robot::{self, Agent, Codec, Step};
 use anyhow::{anyhow, Result};
-use std::collections::{HashMap, VecDeque};
-use std::convert::{Into, TryFrom};
-use aocutil::Point;
-use euclid;
-
-type Map = HashMap<Point, Tile>;
-type Vector2D = euclid::Vector2D<i64, euclid::UnknownUnit>;
+use aocutil::{Direction, Grid, Point};
+use std::convert::TryFrom;
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 enum Tile {
     Empty,
     Wall,
     Oxygen,
-    //Robot,
 }
 
 impl std::fmt::Display for Tile {
@@ -25,42 +19,11 @@ impl std::fmt::Display for Tile {
                 Tile::Empty => "░",
                 Tile::Wall => "█",
                 Tile::Oxygen => "X",
-                //Tile::Robot => "O",
             }
         )
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
-enum Movement {
-    North,
-    East,
-    South,
-    West,
-}
-
-impl Into<i64> for Movement {
-    fn into(self) -> i64 {
-        match self {
-            Movement::North => 1,
-            Movement::East => 4,
-            Movement::South => 2,
-            Movement::West => 3,
-        }
-    }
-}
-
-impl Into<Vector2D> for Movement {
-    fn into(self) -> Vector2D {
-        match self {
-            Movement::North => Vector2D::new(0, 1),
-            Movement::East => Vector2D::new(1, 0),
-            Movement::South => Vector2D::new(0, -1),
-            Movement::West => Vector2D::new(-1, 0),
-        }
-    }
-}
-
 #[derive(Copy, Clone, PartialEq, Debug)]
 enum Status {
     HitWall,
@@ -81,117 +44,51 @@ impl TryFrom<i64> for Status {
     }
 }
 
+/// The droid is sent a movement command and responds with its status.
 #[derive(Clone)]
-struct Robot {
-    prg: intcode::Interpretor,
-    pos: Point,
-    steps: usize,
-}
-
-impl Robot {
-    pub fn new(prg: &intcode::Interpretor) -> Self {
-        Robot {
-            prg: prg.clone(),
-            pos: Point::new(0, 0),
-            steps: 0,
-        }
+struct Droid;
+
+impl Codec for Droid {
+    type Command = Direction;
+    type Response = Status;
+    const OUTPUTS: usize = 1;
+
+    fn encode(&self, d: &Direction) -> Vec<i64> {
+        vec![match d {
+            Direction::Up => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+            Direction::Right => 4,
+        }]
     }
-}
 
-fn build_map(prg: &intcode::Interpretor) -> Result<(Map, Option<Robot>)> {
-    let directions = [
-        Movement::North,
-        Movement::East,
-        Movement::West,
-        Movement::South,
-    ];
-    let mut map = HashMap::new();
-    let mut robots = VecDeque::new();
-    let mut found = None;
-
-    {
-        let robot = Robot::new(prg);
-        map.insert(robot.pos, Tile::Empty);
-        robots.push_front(robot);
+    fn decode(&self, outputs: &[i64]) -> Result<Status> {
+        Status::try_from(outputs[0])
     }
+}
 
-    while let Some(robot) = robots.pop_front() {
-        for &m in &directions {
-            let vec: Vector2D = m.into();
-            let new_pos = robot.pos + vec;
-            if map.contains_key(&new_pos) {
-                // visited already
-                continue;
-            }
+fn build_map(input: &[i64]) -> Result<(Grid<Tile>, Point)> {
+    let map = robot::explore_bfs(&Agent::new(input, Droid), Tile::Empty, |s| match s {
+        Status::HitWall => Step::Blocked(Tile::Wall),
+        Status::Moved => Step::Moved(Tile::Empty),
+        Status::AtDestination => Step::Moved(Tile::Oxygen),
+    })?;
 
-            let mut new_robot = robot.clone();
-            new_robot.prg.input(m.into());
-            match new_robot.prg.run()? {
-                intcode::State::Terminated(_) => break,
-                intcode::State::Suspended(out) => {
-                    match Status::try_from(out)? {
-                        Status::HitWall => {
-                            map.insert(new_pos, Tile::Wall);
-                        }
-                        Status::Moved => {
-                            map.insert(new_pos, Tile::Empty);
-                            new_robot.pos = new_pos;
-                            new_robot.steps += 1;
-                            robots.push_front(new_robot);
-                        }
-                        Status::AtDestination => {
-                            map.insert(new_pos, Tile::Oxygen);
-                            new_robot.pos = new_pos;
-                            new_robot.steps += 1;
-                            found = Some(new_robot.clone());
-                            robots.push_front(new_robot);
-                        }
-                    };
-                }
-                intcode::State::AwaitingInput => panic!("Input should have already been provided"),
-            }
-            //println!("\n\n{}", aocutil::Grid::from(&map));
-        }
-    }
+    let oxygen = map
+        .iter()
+        .find(|(_, &t)| t == Tile::Oxygen)
+        .map(|(&p, _)| p)
+        .ok_or_else(|| anyhow!("oxygen system not found"))?;
 
-    Ok((map, found))
+    Ok((map, oxygen))
 }
 
-fn fill(map: &Map, origin: &Point) -> Result<usize> {
-    let mut map = map.clone();
-    let directions = [
-        Movement::North,
-        Movement::East,
-        Movement::West,
-        Movement::South,
-    ];
-    let mut oxygen = Vec::new();
-    let mut to_fill = VecDeque::new();
-    to_fill.push_front((*origin, 0));
-
-    while let Some((p, minutes)) = to_fill.pop_front() {
-        oxygen.push((p, minutes));
-        map.insert(p, Tile::Oxygen);
-
-        for &m in &directions {
-            let vec: Vector2D = m.into();
-            let new_pos = p + vec;
-            match map.get(&new_pos) {
-                Some(Tile::Wall) => continue,
-                Some(Tile::Oxygen) => continue,
-                Some(Tile::Empty) => {
-                    to_fill.push_back((new_pos, minutes + 1));
-                }
-                None => return Err(anyhow!("something went wrong")),
-            }
-        }
-    }
-
-    Ok(oxygen
-        .iter()
-        .max_by(|(_, a), (_, b)| a.cmp(b))
-        .map(|(_, m)| *m)
-        .unwrap())
+fn fill(map: &Grid<Tile>, origin: Point) -> usize {
+    robot::flood(map, origin, |&t| t != Tile::Wall)
+        .values()
+        .copied()
+        .max()
+        .unwrap_or(0)
 }
 
 #[aoc_generator(day15)]
@@ -205,17 +102,14 @@ pub fn input_generator(input: &str) -> Vec<i64> {
 
 #[aoc(day15, part1)]
 fn answer_1(input: &[i64]) -> Result<usize> {
-    let prg = intcode::Interpretor::new(input);
-    let (_, robot) = build_map(&prg)?;
+    let (map, oxygen) = build_map(input)?;
 
-    Ok(robot.unwrap().steps)
+    Ok(robot::flood(&map, Point::new(0, 0), |&t| t != Tile::Wall)[&oxygen])
 }
 
 #[aoc(day15, part2)]
 fn answer_2(input: &[i64]) -> Result<usize> {
-    let prg = intcode::Interpretor::new(input);
-    let (map, robot) = build_map(&prg)?;
+    let (map, oxygen) = build_map(input)?;
 
-    let minutes = fill(&map, &robot.unwrap().pos)?;
-    Ok(minutes)
+    Ok(fill(&map, oxygen))
 }
@@ -1,98 +1,148 @@
 use anyhow::{anyhow, Result};
-use std::char;
-use std::iter;
+use std::fmt;
+
+/// A signal from some offset onwards. Each digit of a phase only depends on the digits at and
+/// after its own position, so the digits before the offset are never needed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signal {
+    digits: Vec<i64>,
+    offset: usize,
+}
 
-const PATTERN: [i64; 4] = [0, 1, 0, -1];
+impl Signal {
+    /// `base` repeated `repeat` times, keeping the digits from `offset` onwards. The digits before
+    /// the offset are not materialised. Fails if the offset is past the end of the signal.
+    pub fn new(base: &[i64], repeat: usize, offset: usize) -> Result<Self> {
+        let len = base.len() * repeat;
+        if offset > len {
+            return Err(anyhow!(
+                "offset {} is past the end of a signal of {} digits",
+                offset,
+                len
+            ));
+        }
+        let digits = (offset..len).map(|i| base[i % base.len()]).collect();
 
-pub struct FFTIterator {
-    signal: String,
-}
+        Ok(Signal { digits, offset })
+    }
+
+    /// Length of the whole signal, including the digits before the offset.
+    pub fn len(&self) -> usize {
+        self.offset + self.digits.len()
+    }
 
-impl FFTIterator {
-    pub fn new(signal: &str) -> Self {
-        FFTIterator {
-            signal: signal.to_owned(),
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Digits from the offset onwards.
+    pub fn digits(&self) -> &[i64] {
+        &self.digits
+    }
+
+    /// Apply one phase of the repeating `0, 1, 0, -1` pattern.
+    pub fn phase(&mut self) {
+        // Past the middle the pattern is zero before each position and one after it
+        if 2 * self.offset + 1 >= self.len() {
+            self.suffix_phase();
+        } else {
+            self.prefix_phase();
         }
     }
+
+    pub fn run(&mut self, phases: usize) {
+        for _ in 0..phases {
+            self.phase();
+        }
+    }
+
+    fn suffix_phase(&mut self) {
+        let mut sum = 0;
+        for d in self.digits.iter_mut().rev() {
+            sum += *d;
+            *d = sum % 10;
+        }
+    }
+
+    /// The digit at position `p` (0-based) sums alternating runs of `p + 1` digits. With prefix
+    /// sums each run takes constant time, and there are `len / (p + 1)` runs, so a phase is
+    /// O(n log n).
+    fn prefix_phase(&mut self) {
+        let mut sums = Vec::with_capacity(self.digits.len() + 1);
+        sums.push(0);
+        for &d in &self.digits {
+            sums.push(sums[sums.len() - 1] + d);
+        }
+
+        let len = self.len();
+        let offset = self.offset;
+        // Sum of the digits at positions `start..end`, clipped to the signal
+        let sum = |start: usize, end: usize| sums[end.min(len) - offset] - sums[start - offset];
+
+        for (i, d) in self.digits.iter_mut().enumerate() {
+            let run = offset + i + 1;
+            let mut total = 0;
+            // Ones start at position `run - 1`, and minus ones two runs later
+            let mut start = run - 1;
+            while start < len {
+                total += sum(start, start + run);
+                if start + 2 * run < len {
+                    total -= sum(start + 2 * run, start + 3 * run);
+                }
+                start += 4 * run;
+            }
+            *d = total.abs() % 10;
+        }
+    }
+
+    /// The first `count` digits from the offset.
+    pub fn message(&self, count: usize) -> String {
+        self.digits
+            .iter()
+            .take(count)
+            .map(|d| d.to_string())
+            .collect()
+    }
 }
 
-impl Iterator for FFTIterator {
-    type Item = String;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let digits: Vec<u32> = self
-            .signal
-            .chars()
-            .map(|c| c.to_digit(10).unwrap())
-            .collect();
-
-        let signal: String = (1..=digits.len())
-            .map(|i| {
-                let pattern = PATTERN
-                    .iter()
-                    .map(|m| iter::repeat(m).take(i))
-                    .flatten()
-                    .cycle()
-                    .skip(1);
-
-                let sum: i64 = digits
-                    .iter()
-                    .zip(pattern)
-                    .map(|(&x, &m)| x as i64 * m)
-                    .sum();
-                char::from_digit((sum % 10).abs() as u32, 10).unwrap()
-            })
-            .collect();
-
-        self.signal = signal.to_owned();
-
-        Some(signal)
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message(self.digits.len()))
     }
 }
 
 #[aoc_generator(day16)]
-pub fn input_generator(input: &str) -> String {
-    input.trim().to_owned()
+pub fn input_generator(input: &str) -> Vec<i64> {
+    input
+        .trim()
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .map(i64::from)
+        .collect()
 }
 
 #[aoc(day16, part1)]
-fn answer_1(input: &str) -> Result<String> {
-    let mut signal = FFTIterator::new(input)
-        .nth(99)
-        .ok_or_else(|| anyhow!("oops"))?;
-    signal.truncate(8);
+fn answer_1(input: &[i64]) -> Result<String> {
+    let mut signal = Signal::new(input, 1, 0)?;
+    signal.run(100);
 
-    Ok(signal)
+    Ok(signal.message(8))
 }
 
 #[aoc(day16, part2)]
-fn answer_2(input: &str) -> Result<String> {
-    let offset = input[..7]
-        .parse()
-        .map_err(|e| anyhow!("unable to parse offset: {}", e))?;
-
-    let mut digits: Vec<i64> = input
-        .chars()
-        .filter_map(|c| c.to_digit(10))
-        .cycle()
-        .take(10_000 * input.len())
-        .skip(offset)
-        .map(|x| x as i64)
-        .collect();
+fn answer_2(input: &[i64]) -> Result<String> {
+    if input.len() < 7 {
+        return Err(anyhow!("signal is too short for an offset"));
+    }
+    let offset = input[..7].iter().fold(0, |acc, &d| acc * 10 + d as usize);
 
-    for _ in 0..100 {
-        let mut sum = 0;
-        for i in (0..digits.len()).rev() {
-            sum += digits[i];
-            digits[i] = sum.abs() % 10;
-        }
+    let mut signal = Signal::new(input, 10_000, offset)?;
+    if signal.digits().len() < 8 {
+        return Err(anyhow!("offset {} leaves no room for the message", offset));
     }
+    signal.run(100);
 
-    Ok(digits
-        .iter()
-        .take(8)
-        .filter_map(|&d| char::from_digit(d as u32, 10))
-        .collect())
+    Ok(signal.message(8))
 }
 
 #[cfg(test)]
@@ -100,12 +150,28 @@ mod test {
     use super::*;
 
     #[test]
-    fn test_fft_iterator() {
-        let mut iter = FFTIterator::new("12345678");
-        assert_eq!("48226158".to_string(), iter.next().unwrap());
-        assert_eq!("34040438".to_string(), iter.next().unwrap());
-        assert_eq!("03415518".to_string(), iter.next().unwrap());
-        assert_eq!("01029498".to_string(), iter.next().unwrap());
+    fn test_phases() {
+        let mut signal = Signal::new(&input_generator("12345678"), 1, 0).unwrap();
+        let mut phases = vec![];
+        for _ in 0..4 {
+            signal.phase();
+            phases.push(signal.to_string());
+        }
+        assert_eq!(vec!["48226158", "34040438", "03415518", "01029498"], phases);
+    }
+
+    #[test]
+    fn test_offsets() {
+        // Digits after an offset do not depend on the ones before it, in either half
+        let base = input_generator("80871224585914546619083218645595");
+        let mut full = Signal::new(&base, 3, 0).unwrap();
+        full.run(10);
+        for &offset in &[5, 40, 48, 95] {
+            let mut signal = Signal::new(&base, 3, offset).unwrap();
+            signal.run(10);
+            assert_eq!(96, signal.len());
+            assert_eq!(&full.digits()[offset..], signal.digits());
+        }
     }
 
     #[test]
@@ -139,4 +205,25 @@ mod test {
             answer_2(&input_generator("03081770884921959731165446850517")).unwrap()
         );
     }
+
+    #[test]
+    fn test_offset_errors() {
+        assert!(Signal::new(&[1, 2], 1, 3).is_err());
+        assert_eq!(0, Signal::new(&[1, 2], 1, 2).unwrap().digits().len());
+
+        assert_eq!(
+            "offset 1234567 is past the end of a signal of 70000 digits",
+            answer_2(&input_generator("1234567"))
+                .unwrap_err()
+                .to_string()
+        );
+        // 80000 digits, with the offset four digits before the end
+        assert_eq!(
+            "offset 79996 leaves no room for the message",
+            answer_2(&input_generator("00799961"))
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(answer_2(&input_generator("123456")).is_err());
+    }
 }
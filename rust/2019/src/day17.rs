@@ -1,14 +1,12 @@
-use crate::intcode;
+use crate::intcode::ascii::AsciiMachine;
+use crate::robot::{self, Pose};
 use anyhow::{anyhow, Result};
-use aocutil::Direction;
+use aocutil::{compress, Direction, Limits};
 use aocutil::{Grid, Point};
 use std::collections::VecDeque;
 
 const SCAFFOLD: Tile = Tile(b'#' as i64);
 const OPEN_SPACE: Tile = Tile(b'.' as i64);
-const NL: i64 = b'\n' as i64;
-
-type Vector2D = euclid::Vector2D<i64, euclid::UnknownUnit>;
 
 #[derive(PartialEq, Clone, Copy, Debug)]
 struct Tile(i64);
@@ -36,75 +34,8 @@ impl std::fmt::Display for Tile {
     }
 }
 
-#[derive(Debug)]
-struct Turn {
-    relative: char,
-    absolute: Direction,
-}
-
-impl Turn {
-    pub fn new(abs: Direction, rel: char) -> Self {
-        Turn {
-            absolute: abs,
-            relative: rel,
-        }
-    }
-
-    pub fn try_from_points(dir: Direction, a: Point, b: Point) -> Option<Turn> {
-        use Direction::*;
-
-        let (dx, dy) = (b.x - a.x, b.y - a.y);
-
-        match (dir, dx, dy) {
-            (Up, 1, 0) => Some(Turn::new(Right, 'R')),
-            (Up, -1, 0) => Some(Turn::new(Left, 'L')),
-            (Right, 0, 1) => Some(Turn::new(Up, 'L')),
-            (Right, 0, -1) => Some(Turn::new(Down, 'R')),
-            (Down, 1, 0) => Some(Turn::new(Right, 'L')),
-            (Down, -1, 0) => Some(Turn::new(Left, 'R')),
-            (Left, 0, 1) => Some(Turn::new(Up, 'R')),
-            (Left, 0, -1) => Some(Turn::new(Down, 'L')),
-            _ => None,
-        }
-    }
-}
-
-fn build_map(input: &[i64]) -> Result<Vec<Vec<Tile>>> {
-    let mut prg = intcode::Interpretor::new(input);
-    let mut map = Vec::new();
-    let mut current = Vec::new();
-
-    loop {
-        match prg.run()? {
-            intcode::State::Suspended(o) => match o {
-                NL => {
-                    let len = current.len();
-                    map.push(current);
-                    current = Vec::with_capacity(len);
-                }
-                _ => {
-                    current.push(Tile::new(o));
-                }
-            },
-            intcode::State::AwaitingInput => return Err(anyhow!("unexpectedly awaiting input")),
-            intcode::State::Terminated(_) => break,
-        }
-    }
-
-    Ok(map)
-}
-
 fn build_grid(input: &[i64]) -> Result<Grid<Tile>> {
-    let map = build_map(input)?;
-    let height = map.len();
-    let mut grid = Grid::new();
-    for (y, r) in map.iter().enumerate() {
-        for (x, &t) in r.iter().enumerate() {
-            grid.insert(Point::new(x as i64, (height - y) as i64), t);
-        }
-    }
-
-    Ok(grid)
+    robot::camera(&mut AsciiMachine::new(input), |c| Ok(Tile::new(c as i64)))
 }
 
 fn get_neighbours(grid: &Grid<Tile>, p: Point) -> Vec<(Point, Tile)> {
@@ -130,16 +61,14 @@ fn get_neighbour_scaffolds(grid: &Grid<Tile>, p: Point) -> Vec<(Point, Tile)> {
 #[derive(Clone, Debug)]
 struct Robot {
     path: Vec<String>,
-    direction: Direction,
-    position: Point,
+    pose: Pose,
 }
 
 impl Robot {
     pub fn new(p: Point, d: Direction) -> Self {
         Robot {
             path: Vec::default(),
-            direction: d,
-            position: p,
+            pose: Pose::new(p, d),
         }
     }
 }
@@ -166,58 +95,37 @@ fn find_robots(grid: &Grid<Tile>) -> Vec<Robot> {
     vec![]
 }
 
-fn find_paths(grid: &Grid<Tile>) -> Vec<String> {
+fn find_paths(grid: &Grid<Tile>) -> Vec<Vec<String>> {
     let mut paths = Vec::new();
     let mut robots: VecDeque<Robot> = find_robots(grid).into();
+    let walkable = |p: Point| matches!(grid.get(&p), Some(t) if t.is_walkable());
 
     while let Some(robot) = robots.pop_front() {
-        let tiles = get_neighbour_scaffolds(grid, robot.position);
-        if tiles.is_empty() {
-            panic!("orphaned?");
-        }
-
-        if tiles.len() == 1 {
-            let back: Vector2D = match robot.direction {
-                Direction::Up => Direction::Down,
-                Direction::Right => Direction::Left,
-                Direction::Down => Direction::Up,
-                Direction::Left => Direction::Left,
-            }
-            .into();
-            // Can't go back so this path is complete
-            if robot.position + back == tiles[0].0 {
-                paths.push(robot.path.join(","));
-                continue;
-            }
-        }
-
-        // Queue other paths
-        for (next_pos, _) in tiles {
-            let mut new_robot = robot.clone();
-            let turn = Turn::try_from_points(new_robot.direction, new_robot.position, next_pos);
-            // Can only go left or right
-            if turn.is_none() {
-                continue;
-            }
-            let turn = turn.unwrap();
-
+        let mut stuck = true;
+
+        // Queue a path for each way the robot can turn, going as far as possible
+        for &(relative, heading) in &[
+            ('L', robot.pose.heading.turn_left()),
+            ('R', robot.pose.heading.turn_right()),
+        ] {
+            let mut pose = Pose::new(robot.pose.position, heading);
             let mut steps = 0;
-            let vec: Vector2D = turn.absolute.into();
-            let mut pos = new_robot.position + vec;
-            while let Some(t) = grid.get(&pos) {
-                if !t.is_walkable() {
-                    break;
-                }
-
-                pos = pos + vec;
+            while walkable(pose.ahead()) {
+                pose.advance();
                 steps += 1;
             }
 
-            assert!(steps > 0, "appending path when no steps were taken");
-            new_robot.position = pos - vec;
-            new_robot.direction = turn.absolute;
-            new_robot.path.push(format!("{},{}", turn.relative, steps));
-            robots.push_back(new_robot);
+            if steps > 0 {
+                let mut new_robot = robot.clone();
+                new_robot.pose = pose;
+                new_robot.path.push(format!("{},{}", relative, steps));
+                robots.push_back(new_robot);
+                stuck = false;
+            }
+        }
+
+        if stuck {
+            paths.push(robot.path);
         }
     }
 
@@ -244,7 +152,7 @@ fn answer_1(input: &[i64]) -> Result<usize> {
         }
 
         if get_neighbour_scaffolds(&grid, p).len() == 4 {
-            parameters += p.x * p.y;
+            parameters += p.x * -p.y;
         }
     }
     Ok(parameters as usize)
@@ -255,37 +163,27 @@ fn answer_2(input: &[i64]) -> Result<usize> {
     let is_continuous = std::env::var("DEBUG").map(|x| x != "").unwrap_or(false);
     let grid = build_grid(input)?;
 
-    let paths = find_paths(&grid);
-    for path in paths {
-        println!("{}", path);
-    }
+    // Each movement function and the main routine may be at most 20 characters
+    let limits = Limits::new(3, 20);
+    let routines = find_paths(&grid)
+        .iter()
+        .find_map(|path| compress(path, limits).into_iter().next())
+        .ok_or_else(|| anyhow!("no path can be split into movement functions"))?;
 
     let mut input = input.to_owned();
     input[0] = 2;
 
-    let mut prg = intcode::Interpretor::new(&input);
-    // R,8,L,4,R,4,R,10,R,8,R,8,L,4,R,4,R,10,R,8,L,12,L,12,R,8,R,8,R,10,R,4,R,4,L,12,L,12,R,8,R,8,R,10,R,4,R,4,L,12,L,12,R,8,R,8,R,10,R,4,R,4,R,10,R,4,R,4,R,8,L,4,R,4,R,10,R,8
-    //
-    // A: R,8,L,4,R,4,R,10,R,8
-    // B: L,12,L,12,R,8,R,8
-    // C: R,10,R,4,R,4
-    prg.input_str("A,A,B,C,B,C,B,C,C,A\n");
-    prg.input_str("R,8,L,4,R,4,R,10,R,8\n");
-    prg.input_str("L,12,L,12,R,8,R,8\n");
-    prg.input_str("R,10,R,4,R,4\n");
-    prg.input_str(if is_continuous { "y\n" } else { "n\n" });
-
-    loop {
-        match prg.run()? {
-            intcode::State::Suspended(o) => print!("{}", (o as u8) as char),
-            intcode::State::AwaitingInput => return Err(anyhow!("unexpectedly awaiting input")),
-            intcode::State::Terminated(dust) => {
-                println!("{:?}", dust);
-                break;
-            }
-        }
+    let mut prg = AsciiMachine::new(&input);
+    let mut lines = routines.lines();
+    lines.resize(limits.routines + 1, String::new());
+    for line in lines {
+        prg.write_line(&line);
     }
+    prg.write_line(if is_continuous { "y" } else { "n" });
+
+    print!("{}", prg.read_all_text()?);
 
-    println!();
-    Ok(0)
+    prg.answer()
+        .map(|dust| dust as usize)
+        .ok_or_else(|| anyhow!("no dust collected"))
 }
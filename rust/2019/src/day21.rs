@@ -1,20 +1,24 @@
+use crate::springscript::{self, Mode};
 use anyhow::Result;
 
 #[aoc_generator(day21)]
 pub fn input_generator(input: &str) -> Vec<i64> {
     input
         .lines()
-        .map(|l| l.split(',').map(|x| x.parse().unwrap()))
-        .flatten()
+        .flat_map(|l| l.split(',').map(|x| x.parse().unwrap()))
         .collect()
 }
 
+fn survey(input: &[i64], mode: Mode) -> Result<i64> {
+    springscript::survey(mode, |script| springscript::run(input, script, mode))
+}
+
 #[aoc(day21, part1)]
-fn answer_1(input: &[i64]) -> Result<usize> {
-    Ok(0)
+fn answer_1(input: &[i64]) -> Result<i64> {
+    survey(input, Mode::Walk)
 }
 
 #[aoc(day21, part2)]
-fn answer_2(input: &[i64]) -> Result<usize> {
-    Ok(0)
+fn answer_2(input: &[i64]) -> Result<i64> {
+    survey(input, Mode::Run)
 }
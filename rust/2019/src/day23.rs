@@ -1,20 +1,39 @@
+use crate::intcode::network::{Action, Monitor, Nat, Network, Packet, NAT_ADDRESS};
 use anyhow::Result;
 
+const NETWORK_SIZE: usize = 50;
+
+/// Halts with the `y` of the first packet sent to the NAT.
+struct FirstNatPacket;
+
+impl Monitor for FirstNatPacket {
+    fn receive(&mut self, packet: Packet) -> Action {
+        if packet.dest == NAT_ADDRESS {
+            Action::Halt(packet.y)
+        } else {
+            Action::Continue
+        }
+    }
+
+    fn idle(&mut self) -> Action {
+        Action::Continue
+    }
+}
+
 #[aoc_generator(day23)]
 pub fn input_generator(input: &str) -> Vec<i64> {
     input
         .lines()
-        .map(|l| l.split(',').map(|x| x.parse().unwrap()))
-        .flatten()
+        .flat_map(|l| l.split(',').map(|x| x.parse().unwrap()))
         .collect()
 }
 
 #[aoc(day23, part1)]
-fn answer_1(input: &[i64]) -> Result<usize> {
-    Ok(0)
+fn answer_1(input: &[i64]) -> Result<i64> {
+    Network::new(input, NETWORK_SIZE, FirstNatPacket).run()
 }
 
 #[aoc(day23, part2)]
-fn answer_2(input: &[i64]) -> Result<usize> {
-    Ok(0)
+fn answer_2(input: &[i64]) -> Result<i64> {
+    Network::new(input, NETWORK_SIZE, Nat::default()).run()
 }
@@ -1,6 +1,12 @@
-use anyhow::{anyhow, Context, Result};
 use std::collections::VecDeque;
 
+pub mod analysis;
+pub mod ascii;
+pub mod compiler;
+pub mod driver;
+pub mod network;
+pub mod snapshot;
+
 const OP_ADDI: i64 = 1;
 const OP_MULT: i64 = 2;
 const OP_INPU: i64 = 3;
@@ -15,6 +21,78 @@ const MODE_POSITION: i64 = 0;
 const MODE_IMMEDIATE: i64 = 1;
 const MODE_RELATIVE: i64 = 2;
 
+/// Default cap on the number of memory cells a program may address.
+pub const DEFAULT_MEMORY_LIMIT: usize = 1 << 24;
+
+pub type Result<T> = std::result::Result<T, IntcodeError>;
+
+/// Errors raised while interpreting a program. Each variant carries the instruction pointer of
+/// the faulting instruction.
+#[derive(Debug, PartialEq, Clone)]
+pub enum IntcodeError {
+    InvalidOpcode {
+        ip: usize,
+        opcode: i64,
+    },
+    InvalidMode {
+        ip: usize,
+        mode: i64,
+    },
+    NegativeAddress {
+        ip: usize,
+        address: i64,
+    },
+    WriteToImmediate {
+        ip: usize,
+    },
+    InputExhausted {
+        ip: usize,
+    },
+    UnexpectedTermination {
+        ip: usize,
+    },
+    MemoryLimitExceeded {
+        ip: usize,
+        address: usize,
+        limit: usize,
+    },
+    /// An arithmetic result or relative address does not fit in an `i64`.
+    Overflow {
+        ip: usize,
+    },
+}
+
+impl std::fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntcodeError::InvalidOpcode { ip, opcode } => {
+                write!(f, "invalid opcode {} at ip={}", opcode, ip)
+            }
+            IntcodeError::InvalidMode { ip, mode } => {
+                write!(f, "invalid parameter mode {} at ip={}", mode, ip)
+            }
+            IntcodeError::NegativeAddress { ip, address } => {
+                write!(f, "negative address {} at ip={}", address, ip)
+            }
+            IntcodeError::WriteToImmediate { ip } => {
+                write!(f, "destination can not be an immediate value at ip={}", ip)
+            }
+            IntcodeError::InputExhausted { ip } => write!(f, "expected input at ip={}", ip),
+            IntcodeError::UnexpectedTermination { ip } => {
+                write!(f, "expected output but terminated at ip={}", ip)
+            }
+            IntcodeError::MemoryLimitExceeded { ip, address, limit } => write!(
+                f,
+                "address {} exceeds memory limit of {} at ip={}",
+                address, limit, ip
+            ),
+            IntcodeError::Overflow { ip } => write!(f, "arithmetic overflow at ip={}", ip),
+        }
+    }
+}
+
+impl std::error::Error for IntcodeError {}
+
 #[derive(Debug)]
 enum Op {
     Add(Param, Param, Param),
@@ -63,7 +141,7 @@ enum InstrResult {
 #[derive(Debug, Copy, Clone)]
 enum Param {
     Immediate(i64),
-    Pointer(usize),
+    Pointer(i64),
     Relative(i64),
 }
 
@@ -77,15 +155,31 @@ impl std::fmt::Display for Param {
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct Interpretor {
     ip: usize,
-    rb: usize,
+    op_ip: usize,
+    rb: i64,
     memory: Vec<i64>,
+    memory_limit: usize,
     inputs: VecDeque<i64>,
     output: Option<i64>,
 }
 
+impl Default for Interpretor {
+    fn default() -> Self {
+        Interpretor {
+            ip: 0,
+            op_ip: 0,
+            rb: 0,
+            memory: Vec::new(),
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            inputs: VecDeque::new(),
+            output: None,
+        }
+    }
+}
+
 impl Interpretor {
     pub fn new(memory: &[i64]) -> Self {
         Interpretor {
@@ -94,6 +188,13 @@ impl Interpretor {
         }
     }
 
+    /// Limit the number of memory cells the program may write to. Writing beyond the limit fails
+    /// with `IntcodeError::MemoryLimitExceeded` instead of growing memory without bound.
+    pub fn with_memory_limit(mut self, limit: usize) -> Self {
+        self.memory_limit = limit;
+        self
+    }
+
     pub fn run(&mut self) -> Result<State> {
         loop {
             let op = self.parse_op()?;
@@ -111,7 +212,7 @@ impl Interpretor {
         loop {
             match self.run()? {
                 State::Terminated(x) => return Ok(x),
-                State::AwaitingInput => return Err(anyhow!("expected input")),
+                State::AwaitingInput => return Err(IntcodeError::InputExhausted { ip: self.ip }),
                 State::Suspended(_) => continue,
             }
         }
@@ -119,19 +220,28 @@ impl Interpretor {
 
     fn interpret(&mut self, op: Op) -> Result<InstrResult> {
         match op {
-            Op::Add(a, b, c) => self.set(c, self.get_value(a) + self.get_value(b))?,
-            Op::Multiply(a, b, c) => self.set(c, self.get_value(a) * self.get_value(b))?,
-            Op::Less(a, b, c) => self.set(c, (self.get_value(a) < self.get_value(b)) as i64)?,
-            Op::Equal(a, b, c) => self.set(c, (self.get_value(a) == self.get_value(b)) as i64)?,
-            Op::AdjustRelBase(a) => self.rb = (self.rb as i64 + self.get_value(a)) as usize,
+            Op::Add(a, b, c) => {
+                let v = self.get_value(a)?.checked_add(self.get_value(b)?);
+                self.set(c, self.checked(v)?)?
+            }
+            Op::Multiply(a, b, c) => {
+                let v = self.get_value(a)?.checked_mul(self.get_value(b)?);
+                self.set(c, self.checked(v)?)?
+            }
+            Op::Less(a, b, c) => self.set(c, (self.get_value(a)? < self.get_value(b)?) as i64)?,
+            Op::Equal(a, b, c) => self.set(c, (self.get_value(a)? == self.get_value(b)?) as i64)?,
+            Op::AdjustRelBase(a) => {
+                let rb = self.rb.checked_add(self.get_value(a)?);
+                self.rb = self.checked(rb)?
+            }
             Op::JumpTrue(a, b) => {
-                if self.get_value(a) != 0 {
-                    self.ip = self.get_value(b) as usize;
+                if self.get_value(a)? != 0 {
+                    self.ip = self.address(self.get_value(b)?)?;
                 }
             }
             Op::JumpFalse(a, b) => {
-                if self.get_value(a) == 0 {
-                    self.ip = self.get_value(b) as usize;
+                if self.get_value(a)? == 0 {
+                    self.ip = self.address(self.get_value(b)?)?;
                 }
             }
             Op::Input(a) => {
@@ -140,22 +250,27 @@ impl Interpretor {
                 } else {
                     // Push Input operator back so that it gets processed again when the
                     // interpretor is resumed
-                    self.ip = self.ip - 2;
+                    self.ip = self.op_ip;
                     return Ok(InstrResult::AwaitInput);
                 }
             }
             Op::Output(out) => {
-                let v = self.get_value(out);
+                let v = self.get_value(out)?;
                 self.output = Some(v);
                 return Ok(InstrResult::Suspend(v));
             }
-            Op::Terminate => return Ok(InstrResult::Terminate),
+            Op::Terminate => {
+                // Stay on the terminate instruction so that resuming terminates again
+                self.ip = self.op_ip;
+                return Ok(InstrResult::Terminate);
+            }
         };
         Ok(InstrResult::Continue)
     }
 
     fn parse_op(&mut self) -> Result<Op> {
-        let mut instr = self.get_token().context("expecting instruction")?;
+        self.op_ip = self.ip;
+        let mut instr = self.get_token();
         let opcode = instr % 100;
         instr /= 100;
 
@@ -170,37 +285,63 @@ impl Interpretor {
             OP_OUTP => self.parse_instr_outp(&mut instr),
             OP_ADRB => self.parse_instr_adrb(&mut instr),
             OP_TERM => Ok(Op::Terminate),
-            _ => Err(anyhow!("invalid operation: {}", opcode)),
+            _ => Err(IntcodeError::InvalidOpcode {
+                ip: self.op_ip,
+                opcode,
+            }),
         }
-        .with_context(|| format!("opcode={},modes={}", opcode, instr))
     }
 
-    fn get_token(&mut self) -> Result<i64> {
-        let v = self
-            .memory
-            .get(self.ip)
-            .ok_or_else(|| anyhow!("expected token"))?;
+    fn get_token(&mut self) -> i64 {
+        let v = self.get(self.ip);
         self.ip += 1;
-        Ok(*v)
+        v
     }
 
-    fn get_value(&self, param: Param) -> i64 {
-        match param {
-            Param::Immediate(v) => v,
-            Param::Pointer(i) => self.get(i),
-            Param::Relative(d) => self.get((self.rb as i64 + d) as usize),
+    fn checked(&self, v: Option<i64>) -> Result<i64> {
+        v.ok_or(IntcodeError::Overflow { ip: self.op_ip })
+    }
+
+    /// The address `d` away from the relative base.
+    fn relative(&self, d: i64) -> Result<usize> {
+        let addr = self.rb.checked_add(d);
+        self.address(self.checked(addr)?)
+    }
+
+    fn address(&self, addr: i64) -> Result<usize> {
+        if addr < 0 {
+            return Err(IntcodeError::NegativeAddress {
+                ip: self.op_ip,
+                address: addr,
+            });
         }
+
+        Ok(addr as usize)
+    }
+
+    fn get_value(&self, param: Param) -> Result<i64> {
+        Ok(match param {
+            Param::Immediate(v) => v,
+            Param::Pointer(i) => self.get(self.address(i)?),
+            Param::Relative(d) => self.get(self.relative(d)?),
+        })
     }
 
     fn set(&mut self, addr: Param, x: i64) -> Result<()> {
         let dst = match addr {
-            Param::Pointer(x) => x,
-            Param::Relative(x) => (self.rb as i64 + x) as usize,
-            Param::Immediate(_) => {
-                return Err(anyhow!("destination can not be an immediate value"))
-            }
+            Param::Pointer(x) => self.address(x)?,
+            Param::Relative(x) => self.relative(x)?,
+            Param::Immediate(_) => return Err(IntcodeError::WriteToImmediate { ip: self.op_ip }),
         };
 
+        if dst >= self.memory_limit {
+            return Err(IntcodeError::MemoryLimitExceeded {
+                ip: self.op_ip,
+                address: dst,
+                limit: self.memory_limit,
+            });
+        }
+
         if self.memory.len() <= dst {
             self.memory.resize(dst + 1, 0);
         }
@@ -287,13 +428,111 @@ impl Interpretor {
         let mode = *modes % 10;
         *modes /= 10;
 
-        let p = self.get_token()?;
+        let p = self.get_token();
 
         Ok(match mode {
             MODE_IMMEDIATE => Param::Immediate(p),
-            MODE_POSITION => Param::Pointer(p as usize),
+            MODE_POSITION => Param::Pointer(p),
             MODE_RELATIVE => Param::Relative(p),
-            _ => return Err(anyhow!("inavlid mode: {}", mode)),
+            _ => {
+                return Err(IntcodeError::InvalidMode {
+                    ip: self.op_ip,
+                    mode,
+                })
+            }
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn invalid_opcode() {
+        let mut prg = Interpretor::new(&[1101, 1, 1, 5, 42, 0]);
+        assert_eq!(
+            Err(IntcodeError::InvalidOpcode { ip: 4, opcode: 42 }),
+            prg.run()
+        );
+    }
+
+    #[test]
+    fn invalid_mode() {
+        let mut prg = Interpretor::new(&[304, 0, 99]);
+        assert_eq!(Err(IntcodeError::InvalidMode { ip: 0, mode: 3 }), prg.run());
+    }
+
+    #[test]
+    fn negative_address() {
+        let mut prg = Interpretor::new(&[4, -1, 99]);
+        assert_eq!(
+            Err(IntcodeError::NegativeAddress { ip: 0, address: -1 }),
+            prg.run()
+        );
+
+        let mut prg = Interpretor::new(&[109, -5, 204, 1, 99]);
+        assert_eq!(
+            Err(IntcodeError::NegativeAddress { ip: 2, address: -4 }),
+            prg.run()
+        );
+
+        let mut prg = Interpretor::new(&[1105, 1, -3]);
+        assert_eq!(
+            Err(IntcodeError::NegativeAddress { ip: 0, address: -3 }),
+            prg.run()
+        );
+    }
+
+    #[test]
+    fn write_to_immediate() {
+        let mut prg = Interpretor::new(&[11101, 1, 1, 0, 99]);
+        assert_eq!(Err(IntcodeError::WriteToImmediate { ip: 0 }), prg.run());
+    }
+
+    #[test]
+    fn input_exhausted() {
+        let mut prg = Interpretor::new(&[3, 5, 3, 5, 99, 0]);
+        prg.input(1);
+        assert_eq!(
+            Err(IntcodeError::InputExhausted { ip: 2 }),
+            prg.run_complete()
+        );
+
+        prg.input(2);
+        assert_eq!(Ok(None), prg.run_complete());
+        assert_eq!(2, prg.get(5));
+    }
+
+    #[test]
+    fn memory_limit() {
+        let mut prg = Interpretor::new(&[1101, 1, 1, 100, 99]).with_memory_limit(100);
+        assert_eq!(
+            Err(IntcodeError::MemoryLimitExceeded {
+                ip: 0,
+                address: 100,
+                limit: 100
+            }),
+            prg.run()
+        );
+
+        let mut prg = Interpretor::new(&[1101, 1, 1, 99, 99]).with_memory_limit(100);
+        assert_eq!(Ok(State::Terminated(None)), prg.run());
+        assert_eq!(2, prg.get(99));
+    }
+
+    #[test]
+    fn overflow() {
+        let mut prg = Interpretor::new(&[1101, i64::MAX, 1, 5, 99, 0]);
+        assert_eq!(Err(IntcodeError::Overflow { ip: 0 }), prg.run());
+
+        let mut prg = Interpretor::new(&[1102, i64::MIN, -1, 5, 99, 0]);
+        assert_eq!(Err(IntcodeError::Overflow { ip: 0 }), prg.run());
+
+        let mut prg = Interpretor::new(&[109, i64::MAX, 109, 1, 99]);
+        assert_eq!(Err(IntcodeError::Overflow { ip: 2 }), prg.run());
+
+        let mut prg = Interpretor::new(&[109, i64::MAX, 204, 1, 99]);
+        assert_eq!(Err(IntcodeError::Overflow { ip: 2 }), prg.run());
+    }
+}
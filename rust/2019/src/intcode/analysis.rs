@@ -0,0 +1,551 @@
+use super::{
+    Op, Param, MODE_IMMEDIATE, MODE_POSITION, MODE_RELATIVE, OP_ADDI, OP_ADRB, OP_EQUA, OP_INPU,
+    OP_JMPF, OP_JMPT, OP_LESS, OP_MULT, OP_OUTP, OP_TERM,
+};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write;
+use std::ops::Range;
+
+/// Number of preceding instructions searched for the return address of a call.
+const CALL_WINDOW: usize = 4;
+
+#[derive(Debug)]
+pub struct Instruction {
+    pub addr: usize,
+    pub len: usize,
+    op: Op,
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:>5}: {}", self.addr, self.op)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum EdgeKind {
+    Fallthrough,
+    Jump,
+    Call,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Edge {
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// How control leaves a basic block, other than through its edges.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Exit {
+    Edges,
+    Terminate,
+    /// Jump to an address stored relative to the relative base, as done to return from a call.
+    Return,
+    /// Jump to an address that can not be determined statically.
+    Indirect,
+    /// The next instruction could not be decoded.
+    Invalid(usize),
+}
+
+#[derive(Debug)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub end: usize,
+    pub instructions: Vec<Instruction>,
+    pub edges: Vec<Edge>,
+    pub exit: Exit,
+}
+
+/// A call following the convention of writing the return address relative to the relative
+/// base before jumping to the function.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Call {
+    pub site: usize,
+    pub target: usize,
+    pub ret: usize,
+}
+
+/// A write by the instruction at `at` into the instruction occupying `target`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct CodeWrite {
+    pub at: usize,
+    pub target: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Cfg {
+    pub blocks: BTreeMap<usize, BasicBlock>,
+    pub calls: Vec<Call>,
+    pub code_writes: Vec<CodeWrite>,
+    /// Memory never reached from the entry point, which is either data or dead code.
+    pub unreachable: Vec<Range<usize>>,
+}
+
+struct Decoded {
+    op: Op,
+    len: usize,
+    edges: Vec<Edge>,
+    exit: Exit,
+}
+
+/// Statically analyse a memory image, starting execution at address 0.
+pub fn analyse(memory: &[i64]) -> Cfg {
+    // Jump targets read through pointers are only trusted if nothing writes to them. Repeat the
+    // traversal until no trusted pointer turns out to be written to.
+    let mut clobbered = HashSet::new();
+    loop {
+        let (code, leaders, calls) = traverse(memory, &clobbered);
+        let writes = pointer_writes(&code);
+        let before = clobbered.len();
+        clobbered.extend(writes.iter().map(|&(_, target)| target));
+        if clobbered.len() == before {
+            return build(memory, code, leaders, calls, &writes);
+        }
+    }
+}
+
+fn decode_param(memory: &[i64], addr: usize, mode: i64) -> Option<Param> {
+    let p = *memory.get(addr)?;
+    match mode {
+        MODE_POSITION => Some(Param::Pointer(p)),
+        MODE_IMMEDIATE => Some(Param::Immediate(p)),
+        MODE_RELATIVE => Some(Param::Relative(p)),
+        _ => None,
+    }
+}
+
+fn decode(memory: &[i64], addr: usize) -> Option<(Op, usize)> {
+    let instr = *memory.get(addr)?;
+    if instr < 0 {
+        return None;
+    }
+    let p = |n: usize| {
+        let mode = instr / 10i64.pow(n as u32 + 1) % 10;
+        decode_param(memory, addr + n, mode)
+    };
+
+    Some(match instr % 100 {
+        OP_ADDI => (Op::Add(p(1)?, p(2)?, p(3)?), 4),
+        OP_MULT => (Op::Multiply(p(1)?, p(2)?, p(3)?), 4),
+        OP_LESS => (Op::Less(p(1)?, p(2)?, p(3)?), 4),
+        OP_EQUA => (Op::Equal(p(1)?, p(2)?, p(3)?), 4),
+        OP_JMPT => (Op::JumpTrue(p(1)?, p(2)?), 3),
+        OP_JMPF => (Op::JumpFalse(p(1)?, p(2)?), 3),
+        OP_INPU => (Op::Input(p(1)?), 2),
+        OP_OUTP => (Op::Output(p(1)?), 2),
+        OP_ADRB => (Op::AdjustRelBase(p(1)?), 2),
+        OP_TERM => (Op::Terminate, 1),
+        _ => return None,
+    })
+}
+
+fn destination(op: &Op) -> Option<Param> {
+    match *op {
+        Op::Add(_, _, c) | Op::Multiply(_, _, c) | Op::Less(_, _, c) | Op::Equal(_, _, c) => {
+            Some(c)
+        }
+        Op::Input(a) => Some(a),
+        _ => None,
+    }
+}
+
+/// Value written by `op` if it only depends on immediate parameters. A result which overflows
+/// is not constant, since the interpreter faults instead of writing it.
+fn constant_result(op: &Op) -> Option<i64> {
+    match *op {
+        Op::Add(Param::Immediate(a), Param::Immediate(b), _) => a.checked_add(b),
+        Op::Multiply(Param::Immediate(a), Param::Immediate(b), _) => a.checked_mul(b),
+        _ => None,
+    }
+}
+
+/// Jump target given by `param`, read from the operand cell at `operand`. The target is unknown if
+/// the operand, or the cell it points to, may be overwritten by the program.
+fn resolve(
+    memory: &[i64],
+    clobbered: &HashSet<usize>,
+    operand: usize,
+    param: Param,
+) -> Option<usize> {
+    if clobbered.contains(&operand) {
+        return None;
+    }
+
+    let target = match param {
+        Param::Immediate(v) => v,
+        Param::Pointer(p) if p >= 0 && !clobbered.contains(&(p as usize)) => {
+            *memory.get(p as usize).unwrap_or(&0)
+        }
+        _ => return None,
+    };
+
+    if target < 0 {
+        return None;
+    }
+
+    Some(target as usize)
+}
+
+fn traverse(
+    memory: &[i64],
+    clobbered: &HashSet<usize>,
+) -> (BTreeMap<usize, Decoded>, BTreeSet<usize>, Vec<Call>) {
+    let mut code: BTreeMap<usize, Decoded> = BTreeMap::new();
+    let mut leaders = BTreeSet::new();
+    let mut calls = Vec::new();
+    let mut queue = vec![0];
+    leaders.insert(0);
+
+    while let Some(start) = queue.pop() {
+        // Follow a straight run of instructions, queueing any other successors
+        let mut run: Vec<usize> = Vec::new();
+        let mut addr = start;
+        while !code.contains_key(&addr) {
+            let (op, len) = match decode(memory, addr) {
+                Some(d) => d,
+                None => {
+                    // Attribute the invalid instruction to the end of the run
+                    if let Some(prev) = run.last().and_then(|a| code.get_mut(a)) {
+                        prev.exit = Exit::Invalid(addr);
+                        prev.edges.clear();
+                    }
+                    break;
+                }
+            };
+
+            let next = addr + len;
+            let mut edges = Vec::new();
+            let mut exit = Exit::Edges;
+            let mut fallthrough = true;
+
+            match op {
+                Op::Terminate => {
+                    exit = Exit::Terminate;
+                    fallthrough = false;
+                }
+                Op::JumpTrue(cond, target) | Op::JumpFalse(cond, target) => {
+                    let jump_if = matches!(op, Op::JumpTrue(_, _));
+                    let (may_jump, may_fall) = match cond {
+                        Param::Immediate(c) if !clobbered.contains(&(addr + 1)) => {
+                            ((c != 0) == jump_if, (c != 0) != jump_if)
+                        }
+                        _ => (true, true),
+                    };
+                    fallthrough = may_fall;
+
+                    if may_jump {
+                        match resolve(memory, clobbered, addr + 2, target) {
+                            Some(t) if !may_fall && is_call(&code, &run, next) => {
+                                calls.push(Call {
+                                    site: addr,
+                                    target: t,
+                                    ret: next,
+                                });
+                                edges.push(Edge {
+                                    to: t,
+                                    kind: EdgeKind::Call,
+                                });
+                                // Execution continues at the return address
+                                fallthrough = true;
+                                leaders.insert(t);
+                                queue.push(t);
+                            }
+                            Some(t) => {
+                                edges.push(Edge {
+                                    to: t,
+                                    kind: EdgeKind::Jump,
+                                });
+                                leaders.insert(t);
+                                queue.push(t);
+                            }
+                            None => {
+                                exit = match target {
+                                    Param::Relative(_) => Exit::Return,
+                                    _ => Exit::Indirect,
+                                }
+                            }
+                        }
+                    }
+
+                    if fallthrough {
+                        leaders.insert(next);
+                    }
+                }
+                _ => (),
+            }
+
+            if fallthrough {
+                edges.push(Edge {
+                    to: next,
+                    kind: EdgeKind::Fallthrough,
+                });
+            }
+
+            code.insert(
+                addr,
+                Decoded {
+                    op,
+                    len,
+                    edges,
+                    exit,
+                },
+            );
+            run.push(addr);
+
+            if !fallthrough {
+                break;
+            }
+            addr = next;
+        }
+
+        // Joining an already decoded run makes its instruction a leader
+        if code.contains_key(&addr) && !run.contains(&addr) && addr != start {
+            leaders.insert(addr);
+        }
+    }
+
+    (code, leaders, calls)
+}
+
+/// Whether one of the last few instructions of `run` stores `ret` relative to the relative base.
+fn is_call(code: &BTreeMap<usize, Decoded>, run: &[usize], ret: usize) -> bool {
+    run.iter().rev().take(CALL_WINDOW).any(|a| {
+        let op = &code[a].op;
+        matches!(destination(op), Some(Param::Relative(_)))
+            && constant_result(op) == Some(ret as i64)
+    })
+}
+
+fn pointer_writes(code: &BTreeMap<usize, Decoded>) -> Vec<(usize, usize)> {
+    code.iter()
+        .filter_map(|(&addr, d)| match destination(&d.op) {
+            Some(Param::Pointer(p)) if p >= 0 => Some((addr, p as usize)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn build(
+    memory: &[i64],
+    code: BTreeMap<usize, Decoded>,
+    leaders: BTreeSet<usize>,
+    calls: Vec<Call>,
+    writes: &[(usize, usize)],
+) -> Cfg {
+    let mut cfg = Cfg {
+        calls,
+        ..Cfg::default()
+    };
+
+    let owner = |target: usize| {
+        code.range(..=target)
+            .next_back()
+            .filter(|(&a, d)| target < a + d.len)
+            .map(|(&a, _)| a)
+    };
+    cfg.code_writes = writes
+        .iter()
+        .filter(|&&(_, target)| owner(target).is_some())
+        .map(|&(at, target)| CodeWrite { at, target })
+        .collect();
+
+    let mut covered = 0;
+    let mut current: Option<BasicBlock> = None;
+    for (addr, d) in code {
+        if addr > covered {
+            cfg.unreachable.push(covered..addr);
+        }
+        covered = covered.max(addr + d.len);
+
+        let block = match current.take() {
+            Some(b) if b.end == addr && !leaders.contains(&addr) => b,
+            Some(b) => {
+                cfg.blocks.insert(b.start, b);
+                BasicBlock {
+                    start: addr,
+                    end: addr,
+                    instructions: vec![],
+                    edges: vec![],
+                    exit: Exit::Edges,
+                }
+            }
+            None => BasicBlock {
+                start: addr,
+                end: addr,
+                instructions: vec![],
+                edges: vec![],
+                exit: Exit::Edges,
+            },
+        };
+
+        let mut block = block;
+        block.end = addr + d.len;
+        block.edges = d.edges;
+        block.exit = d.exit;
+        block.instructions.push(Instruction {
+            addr,
+            len: d.len,
+            op: d.op,
+        });
+
+        let ends_block = block.exit != Exit::Edges
+            || block.edges.iter().any(|e| e.kind != EdgeKind::Fallthrough);
+        if ends_block {
+            cfg.blocks.insert(block.start, block);
+        } else {
+            current = Some(block);
+        }
+    }
+    if let Some(b) = current {
+        cfg.blocks.insert(b.start, b);
+    }
+    if covered < memory.len() {
+        cfg.unreachable.push(covered..memory.len());
+    }
+
+    cfg
+}
+
+impl Cfg {
+    /// Render the graph in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph intcode {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=monospace];").unwrap();
+
+        for block in self.blocks.values() {
+            let mut label: String = block
+                .instructions
+                .iter()
+                .map(|i| format!("{}\\l", i))
+                .collect();
+            match block.exit {
+                Exit::Edges => (),
+                Exit::Terminate => label.push_str("(terminate)\\l"),
+                Exit::Return => label.push_str("(return)\\l"),
+                Exit::Indirect => label.push_str("(indirect jump)\\l"),
+                Exit::Invalid(a) => label.push_str(&format!("(invalid instruction at {})\\l", a)),
+            }
+            writeln!(dot, "    b{} [label=\"{}\"];", block.start, label).unwrap();
+
+            for edge in &block.edges {
+                let style = match edge.kind {
+                    EdgeKind::Fallthrough => "",
+                    EdgeKind::Jump => " [label=\"jump\"]",
+                    EdgeKind::Call => " [label=\"call\", style=dashed]",
+                };
+                writeln!(dot, "    b{} -> b{}{};", block.start, edge.to, style).unwrap();
+            }
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Ackermann function from the benchmarks
+    static ACKERMANN: [i64; 70] = [
+        109, 99, 21101, 0, 13, 0, 203, 1, 203, 2, 1105, 1, 16, 204, 1, 99, 1205, 1, 26, 22101, 1,
+        2, 1, 2105, 1, 0, 1205, 2, 40, 22101, -1, 1, 1, 21101, 0, 1, 2, 1105, 1, 16, 21101, 0, 57,
+        3, 22101, 0, 1, 4, 22101, -1, 2, 5, 109, 3, 1105, 1, 16, 109, -3, 22101, 0, 4, 2, 22101,
+        -1, 1, 1, 1105, 1, 16,
+    ];
+
+    #[test]
+    fn calls_and_returns() {
+        let cfg = analyse(&ACKERMANN);
+
+        assert_eq!(
+            vec![
+                Call {
+                    site: 10,
+                    target: 16,
+                    ret: 13
+                },
+                Call {
+                    site: 54,
+                    target: 16,
+                    ret: 57
+                }
+            ],
+            {
+                let mut calls = cfg.calls.clone();
+                calls.sort_by_key(|c| c.site);
+                calls
+            }
+        );
+
+        let main = &cfg.blocks[&0];
+        assert_eq!(13, main.end);
+        assert_eq!(
+            vec![
+                Edge {
+                    to: 16,
+                    kind: EdgeKind::Call
+                },
+                Edge {
+                    to: 13,
+                    kind: EdgeKind::Fallthrough
+                }
+            ],
+            main.edges
+        );
+        assert_eq!(Exit::Terminate, cfg.blocks[&13].exit);
+        assert_eq!(Exit::Return, cfg.blocks[&19].exit);
+        assert_eq!(
+            vec![16, 19, 26, 29, 40, 57],
+            cfg.blocks
+                .keys()
+                .copied()
+                .filter(|&a| a >= 16)
+                .collect::<Vec<_>>()
+        );
+        assert!(cfg.unreachable.is_empty());
+        assert!(cfg.code_writes.is_empty());
+    }
+
+    #[test]
+    fn self_modifying_and_unreachable() {
+        let memory = [
+            1101, 0, 99, 6, // ADD 0, 99, &6
+            1105, 1, 0, // JNZ 1, 0 (overwritten to 99 before it is reached)
+            42, 42,
+        ];
+        let cfg = analyse(&memory);
+
+        assert_eq!(vec![CodeWrite { at: 0, target: 6 }], cfg.code_writes);
+        assert_eq!(vec![7..9], cfg.unreachable);
+        assert_eq!(Exit::Indirect, cfg.blocks[&0].exit);
+        assert!(cfg.blocks[&0].edges.is_empty());
+        assert!(!cfg.to_dot().contains("b0 -> b0"));
+    }
+
+    #[test]
+    fn overflowing_constants() {
+        let memory = [
+            21101,
+            i64::MAX,
+            1,
+            0, // ADD MAX, 1, &rb+0 (faults instead of storing a return address)
+            1105,
+            1,
+            7, // JNZ 1, 7
+            99,
+        ];
+        let cfg = analyse(&memory);
+
+        assert!(cfg.calls.is_empty());
+        assert_eq!(
+            vec![Edge {
+                to: 7,
+                kind: EdgeKind::Jump
+            }],
+            cfg.blocks[&0].edges
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use super::{IntcodeError, Interpretor, Result, State};
+
+const NL: i64 = b'\n' as i64;
+
+/// Adapter for programs that communicate in ASCII. Outputs outside of the ASCII range are not
+/// text but the program's answer, which is made available through `answer`.
+#[derive(Clone)]
+pub struct AsciiMachine {
+    prg: Interpretor,
+    answer: Option<i64>,
+    terminated: bool,
+}
+
+impl AsciiMachine {
+    pub fn new(rom: &[i64]) -> Self {
+        Self::from(Interpretor::new(rom))
+    }
+
+    pub fn write(&mut self, text: &str) {
+        self.prg.input_str(text);
+    }
+
+    pub fn write_line(&mut self, line: &str) {
+        self.prg.input_str(line);
+        self.prg.input(NL);
+    }
+
+    /// Read a single line of text, without the trailing newline. Returns `None` if the program
+    /// terminated before producing any more text.
+    pub fn read_line(&mut self) -> Result<Option<String>> {
+        let mut line = String::new();
+        loop {
+            match self.next_char()? {
+                Some('\n') => return Ok(Some(line)),
+                Some(c) => line.push(c),
+                None if self.terminated && line.is_empty() => return Ok(None),
+                None if self.terminated => return Ok(Some(line)),
+                None => return Err(self.input_exhausted()),
+            }
+        }
+    }
+
+    /// Read text until the program either asks for input or terminates.
+    pub fn read_until_prompt(&mut self) -> Result<String> {
+        let mut text = String::new();
+        while let Some(c) = self.next_char()? {
+            text.push(c);
+        }
+
+        Ok(text)
+    }
+
+    /// Read text until the program terminates. Fails if the program asks for more input than
+    /// has been written.
+    pub fn read_all_text(&mut self) -> Result<String> {
+        let text = self.read_until_prompt()?;
+        if !self.terminated {
+            return Err(self.input_exhausted());
+        }
+
+        Ok(text)
+    }
+
+    pub fn answer(&self) -> Option<i64> {
+        self.answer
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+
+    pub fn interpretor(&self) -> &Interpretor {
+        &self.prg
+    }
+
+    /// Return the next character of output, or `None` if the program is awaiting input or has
+    /// terminated.
+    fn next_char(&mut self) -> Result<Option<char>> {
+        if self.terminated {
+            return Ok(None);
+        }
+
+        loop {
+            match self.prg.run()? {
+                State::Suspended(v) if (0..=127).contains(&v) => return Ok(Some(v as u8 as char)),
+                State::Suspended(v) => self.answer = Some(v),
+                State::AwaitingInput => return Ok(None),
+                State::Terminated(_) => {
+                    self.terminated = true;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    fn input_exhausted(&self) -> IntcodeError {
+        IntcodeError::InputExhausted { ip: self.prg.ip }
+    }
+}
+
+impl From<Interpretor> for AsciiMachine {
+    fn from(prg: Interpretor) -> Self {
+        AsciiMachine {
+            prg,
+            answer: None,
+            terminated: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Prints "ok?\n", echoes one line of input and finally outputs 1000.
+    static ECHO: [i64; 22] = [
+        104, 111, 104, 107, 104, 63, 104, 10, 3, 100, 4, 100, 1008, 100, 10, 101, 1006, 101, 8,
+        104, 1000, 99,
+    ];
+
+    #[test]
+    fn read_write_lines() {
+        let mut m = AsciiMachine::new(&ECHO);
+        assert_eq!(Some("ok?".to_owned()), m.read_line().unwrap());
+        assert_eq!("", m.read_until_prompt().unwrap());
+
+        m.write_line("hello");
+        assert_eq!(Some("hello".to_owned()), m.read_line().unwrap());
+        assert_eq!(None, m.read_line().unwrap());
+        assert_eq!(Some(1000), m.answer());
+        assert!(m.is_terminated());
+    }
+
+    #[test]
+    fn read_all_text() {
+        let mut m = AsciiMachine::new(&ECHO);
+        m.write_line("hi");
+        assert_eq!("ok?\nhi\n", m.read_all_text().unwrap());
+        assert_eq!(Some(1000), m.answer());
+    }
+
+    #[test]
+    fn missing_input() {
+        let mut m = AsciiMachine::new(&ECHO);
+        m.write("hi");
+        assert_eq!(
+            Err(IntcodeError::InputExhausted { ip: 8 }),
+            m.read_all_text()
+        );
+
+        let mut m = AsciiMachine::new(&ECHO);
+        m.read_line().unwrap();
+        assert_eq!(Err(IntcodeError::InputExhausted { ip: 8 }), m.read_line());
+    }
+}
@@ -0,0 +1,980 @@
+//! Compiler for a small imperative language targeting Intcode.
+//!
+//! ```text
+//! fn ack(m, n) {
+//!     if m == 0 { return n + 1; }
+//!     if n == 0 { return ack(m - 1, 1); }
+//!     return ack(m - 1, ack(m, n - 1));
+//! }
+//!
+//! output(ack(input(), input()));
+//! ```
+//!
+//! Values are integers. Top-level statements form the main program and functions may be
+//! declared anywhere at the top level. Variables are declared with `let` and are scoped to their
+//! block; functions only see their own parameters and locals. The builtins are `input()`,
+//! `output(x)` and `abort()`, which stops the program with an invalid opcode.
+//!
+//! Every function call gets a frame on a stack addressed through the relative base. Slot 0 of a
+//! frame holds the return address, followed by the arguments, locals and temporaries. The
+//! return value is left in slot 1.
+use super::{MODE_IMMEDIATE, MODE_RELATIVE};
+use super::{
+    OP_ADDI, OP_ADRB, OP_EQUA, OP_INPU, OP_JMPF, OP_JMPT, OP_LESS, OP_MULT, OP_OUTP, OP_TERM,
+};
+use std::collections::HashMap;
+
+/// Division and remainder are not native to Intcode and are provided by these functions when
+/// used. Both truncate towards zero.
+const PRELUDE: &str = "
+fn __div(a, b) {
+    if b == 0 { abort(); }
+    let negative = (a < 0) != (b < 0);
+    if a < 0 { a = -a; }
+    if b < 0 { b = -b; }
+    let q = 0;
+    while a >= b {
+        let d = b;
+        let m = 1;
+        while a >= d + d {
+            d = d + d;
+            m = m + m;
+        }
+        a = a - d;
+        q = q + m;
+    }
+    if negative { return -q; }
+    return q;
+}
+
+fn __mod(a, b) {
+    return a - a / b * b;
+}
+";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompileError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+pub type Result<T> = std::result::Result<T, CompileError>;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct Pos {
+    line: usize,
+    column: usize,
+}
+
+impl Pos {
+    fn error<T, S: Into<String>>(self, message: S) -> Result<T> {
+        Err(CompileError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Num(i64),
+    Ident(String),
+    Fn,
+    Let,
+    If,
+    Else,
+    While,
+    Return,
+    Break,
+    Continue,
+    Punct(&'static str),
+    Eof,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Num(n) => write!(f, "{}", n),
+            Token::Ident(s) => write!(f, "{}", s),
+            Token::Fn => write!(f, "fn"),
+            Token::Let => write!(f, "let"),
+            Token::If => write!(f, "if"),
+            Token::Else => write!(f, "else"),
+            Token::While => write!(f, "while"),
+            Token::Return => write!(f, "return"),
+            Token::Break => write!(f, "break"),
+            Token::Continue => write!(f, "continue"),
+            Token::Punct(p) => write!(f, "{}", p),
+            Token::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+// Longer punctuation first so that e.g. `<=` is not read as `<`
+const PUNCTUATION: [&str; 21] = [
+    "==", "!=", "<=", ">=", "&&", "||", "(", ")", "{", "}", ",", ";", "=", "+", "-", "*", "/", "%",
+    "<", ">", "!",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, Pos)>> {
+    let mut tokens = Vec::new();
+    let mut pos = Pos { line: 1, column: 1 };
+    let mut rest = source;
+
+    while let Some(c) = rest.chars().next() {
+        let start = pos;
+        let len = if c == '\n' {
+            pos.line += 1;
+            pos.column = 0;
+            1
+        } else if c.is_whitespace() {
+            c.len_utf8()
+        } else if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            match rest[..len].parse() {
+                Ok(n) => tokens.push((Token::Num(n), start)),
+                Err(_) => return start.error(format!("integer {} is too large", &rest[..len])),
+            }
+            len
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            let token = match &rest[..len] {
+                "fn" => Token::Fn,
+                "let" => Token::Let,
+                "if" => Token::If,
+                "else" => Token::Else,
+                "while" => Token::While,
+                "return" => Token::Return,
+                "break" => Token::Break,
+                "continue" => Token::Continue,
+                ident => Token::Ident(ident.to_owned()),
+            };
+            tokens.push((token, start));
+            len
+        } else {
+            match PUNCTUATION.iter().find(|p| rest.starts_with(*p)) {
+                Some(p) => {
+                    tokens.push((Token::Punct(p), start));
+                    p.len()
+                }
+                None => return start.error(format!("unexpected character {:?}", c)),
+            }
+        };
+
+        pos.column += rest[..len].chars().count();
+        rest = &rest[len..];
+    }
+
+    tokens.push((Token::Eof, pos));
+    Ok(tokens)
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum UnOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Num(i64),
+    Var(String, Pos),
+    Call(String, Vec<Expr>, Pos),
+    Unary(UnOp, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+enum Stmt {
+    Let(String, Expr),
+    Assign(String, Expr, Pos),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    Return(Option<Expr>, Pos),
+    Break(Pos),
+    Continue(Pos),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone)]
+struct Function {
+    name: String,
+    params: Vec<String>,
+    body: Vec<Stmt>,
+    pos: Pos,
+}
+
+#[derive(Debug, Default)]
+struct Program {
+    main: Vec<Stmt>,
+    functions: Vec<Function>,
+    uses_division: bool,
+}
+
+// Binary operators by increasing precedence
+const PRECEDENCE: [&[(&str, BinOp)]; 5] = [
+    &[("||", BinOp::Or)],
+    &[("&&", BinOp::And)],
+    &[
+        ("==", BinOp::Equal),
+        ("!=", BinOp::NotEqual),
+        ("<", BinOp::Less),
+        ("<=", BinOp::LessEqual),
+        (">", BinOp::Greater),
+        (">=", BinOp::GreaterEqual),
+    ],
+    &[("+", BinOp::Add), ("-", BinOp::Sub)],
+    &[("*", BinOp::Mul), ("/", BinOp::Div), ("%", BinOp::Mod)],
+];
+
+struct Parser {
+    tokens: Vec<(Token, Pos)>,
+    idx: usize,
+    uses_division: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.idx].0
+    }
+
+    fn pos(&self) -> Pos {
+        self.tokens[self.idx].1
+    }
+
+    fn next(&mut self) -> (Token, Pos) {
+        let t = self.tokens[self.idx].clone();
+        if t.0 != Token::Eof {
+            self.idx += 1;
+        }
+        t
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.next();
+            return true;
+        }
+
+        false
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<()> {
+        let (t, pos) = self.next();
+        if &t != token {
+            return pos.error(format!("expected {} but found {}", token, t));
+        }
+
+        Ok(())
+    }
+
+    fn ident(&mut self) -> Result<(String, Pos)> {
+        match self.next() {
+            (Token::Ident(name), pos) => Ok((name, pos)),
+            (t, pos) => pos.error(format!("expected identifier but found {}", t)),
+        }
+    }
+
+    fn program(&mut self) -> Result<Program> {
+        let mut program = Program::default();
+        while *self.peek() != Token::Eof {
+            if *self.peek() == Token::Fn {
+                program.functions.push(self.function()?);
+            } else {
+                program.main.push(self.stmt()?);
+            }
+        }
+        program.uses_division = self.uses_division;
+
+        Ok(program)
+    }
+
+    fn function(&mut self) -> Result<Function> {
+        self.expect(&Token::Fn)?;
+        let (name, pos) = self.ident()?;
+        self.expect(&Token::Punct("("))?;
+        let mut params = Vec::new();
+        while !self.eat(&Token::Punct(")")) {
+            if !params.is_empty() {
+                self.expect(&Token::Punct(","))?;
+            }
+            params.push(self.ident()?.0);
+        }
+        let body = self.block()?;
+
+        Ok(Function {
+            name,
+            params,
+            body,
+            pos,
+        })
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>> {
+        self.expect(&Token::Punct("{"))?;
+        let mut stmts = Vec::new();
+        while !self.eat(&Token::Punct("}")) {
+            stmts.push(self.stmt()?);
+        }
+
+        Ok(stmts)
+    }
+
+    fn stmt(&mut self) -> Result<Stmt> {
+        let pos = self.pos();
+        let stmt = match self.peek().clone() {
+            Token::Let => {
+                self.next();
+                let (name, _) = self.ident()?;
+                self.expect(&Token::Punct("="))?;
+                Stmt::Let(name, self.expr()?)
+            }
+            Token::Ident(name) if self.tokens[self.idx + 1].0 == Token::Punct("=") => {
+                self.idx += 2;
+                Stmt::Assign(name, self.expr()?, pos)
+            }
+            Token::If => return self.if_stmt(),
+            Token::While => {
+                self.next();
+                let cond = self.expr()?;
+                return Ok(Stmt::While(cond, self.block()?));
+            }
+            Token::Return => {
+                self.next();
+                if *self.peek() == Token::Punct(";") {
+                    Stmt::Return(None, pos)
+                } else {
+                    Stmt::Return(Some(self.expr()?), pos)
+                }
+            }
+            Token::Break => {
+                self.next();
+                Stmt::Break(pos)
+            }
+            Token::Continue => {
+                self.next();
+                Stmt::Continue(pos)
+            }
+            _ => Stmt::Expr(self.expr()?),
+        };
+        self.expect(&Token::Punct(";"))?;
+
+        Ok(stmt)
+    }
+
+    fn if_stmt(&mut self) -> Result<Stmt> {
+        self.expect(&Token::If)?;
+        let cond = self.expr()?;
+        let then = self.block()?;
+        let otherwise = if !self.eat(&Token::Else) {
+            vec![]
+        } else if *self.peek() == Token::If {
+            vec![self.if_stmt()?]
+        } else {
+            self.block()?
+        };
+
+        Ok(Stmt::If(cond, then, otherwise))
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level + 1)?;
+        loop {
+            let op = match self.peek() {
+                Token::Punct(p) => PRECEDENCE[level].iter().find(|(s, _)| s == p),
+                _ => None,
+            };
+            let op = match op {
+                Some(&(_, op)) => op,
+                None => return Ok(lhs),
+            };
+            self.next();
+            if op == BinOp::Div || op == BinOp::Mod {
+                self.uses_division = true;
+            }
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat(&Token::Punct("-")) {
+            return Ok(Expr::Unary(UnOp::Neg, Box::new(self.unary()?)));
+        }
+        if self.eat(&Token::Punct("!")) {
+            return Ok(Expr::Unary(UnOp::Not, Box::new(self.unary()?)));
+        }
+
+        match self.next() {
+            (Token::Num(n), _) => Ok(Expr::Num(n)),
+            (Token::Ident(name), pos) => {
+                if !self.eat(&Token::Punct("(")) {
+                    return Ok(Expr::Var(name, pos));
+                }
+                let mut args = Vec::new();
+                while !self.eat(&Token::Punct(")")) {
+                    if !args.is_empty() {
+                        self.expect(&Token::Punct(","))?;
+                    }
+                    args.push(self.expr()?);
+                }
+                Ok(Expr::Call(name, args, pos))
+            }
+            (Token::Punct("("), _) => {
+                let e = self.expr()?;
+                self.expect(&Token::Punct(")"))?;
+                Ok(e)
+            }
+            (t, pos) => pos.error(format!("expected expression but found {}", t)),
+        }
+    }
+}
+
+fn parse(source: &str) -> Result<Program> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        idx: 0,
+        uses_division: false,
+    };
+
+    parser.program()
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Operand {
+    Immediate(i64),
+    Relative(i64),
+    /// Immediate address of a label, resolved once all code has been generated.
+    Label(usize),
+}
+
+struct Loop {
+    start: usize,
+    end: usize,
+}
+
+#[derive(Default)]
+struct Codegen<'a> {
+    code: Vec<i64>,
+    labels: Vec<Option<usize>>,
+    fixups: Vec<(usize, usize)>,
+    functions: HashMap<&'a str, (usize, usize)>,
+    scopes: Vec<HashMap<&'a str, i64>>,
+    loops: Vec<Loop>,
+    /// Next free slot in the current frame.
+    depth: i64,
+    in_function: bool,
+}
+
+impl<'a> Codegen<'a> {
+    fn label(&mut self) -> usize {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn bind(&mut self, label: usize) {
+        self.labels[label] = Some(self.code.len());
+    }
+
+    fn emit(&mut self, opcode: i64, params: &[Operand]) {
+        let mut instr = opcode;
+        let mut scale = 100;
+        for p in params {
+            let mode = match p {
+                Operand::Immediate(_) | Operand::Label(_) => MODE_IMMEDIATE,
+                Operand::Relative(_) => MODE_RELATIVE,
+            };
+            instr += mode * scale;
+            scale *= 10;
+        }
+
+        self.code.push(instr);
+        for p in params {
+            match *p {
+                Operand::Immediate(v) | Operand::Relative(v) => self.code.push(v),
+                Operand::Label(l) => {
+                    self.fixups.push((self.code.len(), l));
+                    self.code.push(0);
+                }
+            }
+        }
+    }
+
+    fn jump(&mut self, label: usize) {
+        self.emit(OP_JMPT, &[Operand::Immediate(1), Operand::Label(label)]);
+    }
+
+    fn alloc(&mut self) -> i64 {
+        self.depth += 1;
+        self.depth - 1
+    }
+
+    fn lookup(&self, name: &str, pos: Pos) -> Result<i64> {
+        match self.scopes.iter().rev().find_map(|s| s.get(name)) {
+            Some(&slot) => Ok(slot),
+            None => pos.error(format!("undefined variable {}", name)),
+        }
+    }
+
+    fn program(&mut self, program: &'a Program) -> Result<Vec<i64>> {
+        for (i, f) in program.functions.iter().enumerate() {
+            if ["input", "output", "abort"].contains(&f.name.as_str())
+                || self.functions.contains_key(f.name.as_str())
+            {
+                return f
+                    .pos
+                    .error(format!("function {} is already defined", f.name));
+            }
+            self.functions.insert(&f.name, (i, f.params.len()));
+            self.label();
+        }
+
+        // The stack starts after the program
+        let stack = self.label();
+        self.emit(OP_ADRB, &[Operand::Label(stack)]);
+        self.depth = 1;
+        self.block(&program.main)?;
+        self.emit(OP_TERM, &[]);
+
+        self.in_function = true;
+        for (i, f) in program.functions.iter().enumerate() {
+            self.bind(i);
+            self.depth = 1;
+            self.scopes.push(HashMap::new());
+            for p in &f.params {
+                let slot = self.alloc();
+                self.scopes.last_mut().unwrap().insert(p, slot);
+            }
+            self.block(&f.body)?;
+            self.scopes.pop();
+            self.ret(Operand::Immediate(0));
+        }
+        self.bind(stack);
+
+        for &(at, label) in &self.fixups {
+            self.code[at] = self.labels[label].expect("unbound label") as i64;
+        }
+
+        Ok(std::mem::take(&mut self.code))
+    }
+
+    fn ret(&mut self, value: Operand) {
+        self.emit(
+            OP_ADDI,
+            &[value, Operand::Immediate(0), Operand::Relative(1)],
+        );
+        self.emit(OP_JMPT, &[Operand::Immediate(1), Operand::Relative(0)]);
+    }
+
+    fn block(&mut self, stmts: &'a [Stmt]) -> Result<()> {
+        let depth = self.depth;
+        self.scopes.push(HashMap::new());
+        for s in stmts {
+            self.stmt(s)?;
+        }
+        self.scopes.pop();
+        self.depth = depth;
+
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt) -> Result<()> {
+        let depth = self.depth;
+        match stmt {
+            Stmt::Let(name, e) => {
+                let slot = self.alloc();
+                self.eval_into(e, slot)?;
+                self.scopes.last_mut().unwrap().insert(name, slot);
+                // Keep the variable's slot allocated
+                return Ok(());
+            }
+            Stmt::Assign(name, e, pos) => {
+                let slot = self.lookup(name, *pos)?;
+                self.eval_into(e, slot)?;
+            }
+            Stmt::If(cond, then, otherwise) => {
+                let else_label = self.label();
+                let end = self.label();
+                let c = self.operand(cond)?;
+                self.depth = depth;
+                self.emit(OP_JMPF, &[c, Operand::Label(else_label)]);
+                self.block(then)?;
+                if !otherwise.is_empty() {
+                    self.jump(end);
+                }
+                self.bind(else_label);
+                self.block(otherwise)?;
+                self.bind(end);
+            }
+            Stmt::While(cond, body) => {
+                let start = self.label();
+                let end = self.label();
+                self.bind(start);
+                let c = self.operand(cond)?;
+                self.depth = depth;
+                self.emit(OP_JMPF, &[c, Operand::Label(end)]);
+                self.loops.push(Loop { start, end });
+                self.block(body)?;
+                self.loops.pop();
+                self.jump(start);
+                self.bind(end);
+            }
+            Stmt::Return(value, pos) => match (value, self.in_function) {
+                (Some(e), true) => {
+                    let v = self.operand(e)?;
+                    self.ret(v);
+                }
+                (None, true) => self.ret(Operand::Immediate(0)),
+                (None, false) => self.emit(OP_TERM, &[]),
+                (Some(_), false) => return pos.error("the main program can not return a value"),
+            },
+            Stmt::Break(pos) | Stmt::Continue(pos) => {
+                let target = match (self.loops.last(), stmt) {
+                    (Some(l), Stmt::Break(_)) => l.end,
+                    (Some(l), _) => l.start,
+                    (None, _) => return pos.error("break or continue outside of a loop"),
+                };
+                self.jump(target);
+            }
+            Stmt::Expr(Expr::Call(name, args, pos)) if name == "output" => {
+                if args.len() != 1 {
+                    return pos.error("output expects 1 argument");
+                }
+                let v = self.operand(&args[0])?;
+                self.emit(OP_OUTP, &[v]);
+            }
+            Stmt::Expr(Expr::Call(name, args, pos)) if name == "abort" => {
+                if !args.is_empty() {
+                    return pos.error("abort expects no arguments");
+                }
+                self.code.push(0);
+            }
+            Stmt::Expr(e) => {
+                self.operand(e)?;
+            }
+        }
+        self.depth = depth;
+
+        Ok(())
+    }
+
+    /// Evaluate `e` into an operand, using a temporary slot if it is not a constant or variable.
+    fn operand(&mut self, e: &'a Expr) -> Result<Operand> {
+        match e {
+            Expr::Num(n) => Ok(Operand::Immediate(*n)),
+            Expr::Var(name, pos) => Ok(Operand::Relative(self.lookup(name, *pos)?)),
+            _ => {
+                let slot = self.alloc();
+                self.eval_into(e, slot)?;
+                Ok(Operand::Relative(slot))
+            }
+        }
+    }
+
+    /// Evaluate `e` into the slot `dest`. Temporaries are allocated above the current depth and
+    /// released afterwards.
+    fn eval_into(&mut self, e: &'a Expr, dest: i64) -> Result<()> {
+        let depth = self.depth;
+        let dest_op = Operand::Relative(dest);
+        match e {
+            Expr::Num(_) | Expr::Var(_, _) => {
+                let v = self.operand(e)?;
+                self.emit(OP_ADDI, &[v, Operand::Immediate(0), dest_op]);
+            }
+            Expr::Unary(op, a) => {
+                let a = self.operand(a)?;
+                match op {
+                    UnOp::Neg => self.emit(OP_MULT, &[a, Operand::Immediate(-1), dest_op]),
+                    UnOp::Not => self.emit(OP_EQUA, &[a, Operand::Immediate(0), dest_op]),
+                }
+            }
+            Expr::Binary(op @ BinOp::And, a, b) | Expr::Binary(op @ BinOp::Or, a, b) => {
+                let short = self.label();
+                let end = self.label();
+                let a = self.operand(a)?;
+                let (test, value) = match op {
+                    BinOp::And => (OP_JMPF, 0),
+                    _ => (OP_JMPT, 1),
+                };
+                self.emit(test, &[a, Operand::Label(short)]);
+                let b = self.operand(b)?;
+                self.emit(OP_EQUA, &[b, Operand::Immediate(0), dest_op]);
+                self.emit(OP_EQUA, &[dest_op, Operand::Immediate(0), dest_op]);
+                self.jump(end);
+                self.bind(short);
+                self.emit(
+                    OP_ADDI,
+                    &[Operand::Immediate(value), Operand::Immediate(0), dest_op],
+                );
+                self.bind(end);
+            }
+            Expr::Binary(op @ BinOp::Div, a, b) | Expr::Binary(op @ BinOp::Mod, a, b) => {
+                let name = if *op == BinOp::Div { "__div" } else { "__mod" };
+                let (label, _) = self.functions[name];
+                self.call(label, &[a, b], dest)?;
+            }
+            Expr::Binary(op, a, b) => {
+                let a = self.operand(a)?;
+                let b = self.operand(b)?;
+                match op {
+                    BinOp::Add => self.emit(OP_ADDI, &[a, b, dest_op]),
+                    BinOp::Sub => {
+                        let neg_b = match b {
+                            Operand::Immediate(v) => Operand::Immediate(-v),
+                            _ => {
+                                let slot = self.alloc();
+                                let t = Operand::Relative(slot);
+                                self.emit(OP_MULT, &[b, Operand::Immediate(-1), t]);
+                                t
+                            }
+                        };
+                        self.emit(OP_ADDI, &[a, neg_b, dest_op]);
+                    }
+                    BinOp::Mul => self.emit(OP_MULT, &[a, b, dest_op]),
+                    BinOp::Less => self.emit(OP_LESS, &[a, b, dest_op]),
+                    BinOp::Greater => self.emit(OP_LESS, &[b, a, dest_op]),
+                    BinOp::Equal => self.emit(OP_EQUA, &[a, b, dest_op]),
+                    BinOp::LessEqual | BinOp::GreaterEqual | BinOp::NotEqual => {
+                        // Negate the complementary comparison
+                        match op {
+                            BinOp::LessEqual => self.emit(OP_LESS, &[b, a, dest_op]),
+                            BinOp::GreaterEqual => self.emit(OP_LESS, &[a, b, dest_op]),
+                            _ => self.emit(OP_EQUA, &[a, b, dest_op]),
+                        }
+                        self.emit(OP_EQUA, &[dest_op, Operand::Immediate(0), dest_op]);
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            Expr::Call(name, args, pos) => match name.as_str() {
+                "input" if args.is_empty() => self.emit(OP_INPU, &[dest_op]),
+                "input" => return pos.error("input expects no arguments"),
+                "output" | "abort" => {
+                    return pos.error(format!("{} can only be used as a statement", name))
+                }
+                _ => {
+                    let &(label, arity) = match self.functions.get(name.as_str()) {
+                        Some(f) => f,
+                        None => return pos.error(format!("undefined function {}", name)),
+                    };
+                    if args.len() != arity {
+                        return pos.error(format!(
+                            "{} expects {} arguments but was given {}",
+                            name,
+                            arity,
+                            args.len()
+                        ));
+                    }
+                    let args: Vec<&Expr> = args.iter().collect();
+                    self.call(label, &args, dest)?;
+                }
+            },
+        }
+        self.depth = depth;
+
+        Ok(())
+    }
+
+    fn call(&mut self, label: usize, args: &[&'a Expr], dest: i64) -> Result<()> {
+        // The callee's frame starts at the first free slot and its return value ends up in the
+        // slot after the return address, even if it takes no arguments.
+        let frame = self.depth;
+        self.depth += 1 + args.len().max(1) as i64;
+        for (i, a) in args.iter().enumerate() {
+            self.eval_into(a, frame + 1 + i as i64)?;
+        }
+
+        let ret = self.label();
+        self.emit(
+            OP_ADDI,
+            &[
+                Operand::Immediate(0),
+                Operand::Label(ret),
+                Operand::Relative(frame),
+            ],
+        );
+        self.emit(OP_ADRB, &[Operand::Immediate(frame)]);
+        self.jump(label);
+        self.bind(ret);
+        self.emit(OP_ADRB, &[Operand::Immediate(-frame)]);
+        self.emit(
+            OP_ADDI,
+            &[
+                Operand::Relative(frame + 1),
+                Operand::Immediate(0),
+                Operand::Relative(dest),
+            ],
+        );
+
+        Ok(())
+    }
+}
+
+/// Compile a program to an Intcode memory image.
+pub fn compile(source: &str) -> Result<Vec<i64>> {
+    let mut program = parse(source)?;
+    if program.uses_division {
+        program.functions.extend(parse(PRELUDE)?.functions);
+    }
+
+    Codegen::default().program(&program)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::analysis::analyse;
+    use super::super::Interpretor;
+    use super::*;
+
+    fn run(source: &str, inputs: &[i64]) -> Vec<i64> {
+        let rom = compile(source).unwrap();
+        Interpretor::new(&rom).run_with(inputs).unwrap()
+    }
+
+    static SUM_OF_PRIMES: &str = "
+        // Sum of the primes below the input
+        fn is_prime(n) {
+            let d = 2;
+            while d * d <= n {
+                if n % d == 0 {
+                    return 0;
+                }
+                d = d + 1;
+            }
+            return 1;
+        }
+
+        let n = input();
+        let sum = 0;
+        let i = 2;
+        while i < n {
+            if is_prime(i) {
+                sum = sum + i;
+            }
+            i = i + 1;
+        }
+        output(sum);
+    ";
+
+    static ACKERMANN: &str = "
+        fn ack(m, n) {
+            if m == 0 {
+                return n + 1;
+            }
+            if n == 0 {
+                return ack(m - 1, 1);
+            }
+            return ack(m - 1, ack(m, n - 1));
+        }
+
+        output(ack(input(), input()));
+    ";
+
+    #[test]
+    fn benchmarks() {
+        assert_eq!(vec![1060], run(SUM_OF_PRIMES, &[100]));
+        assert_eq!(vec![76127], run(SUM_OF_PRIMES, &[1000]));
+        assert_eq!(vec![9], run(ACKERMANN, &[2, 3]));
+        assert_eq!(vec![61], run(ACKERMANN, &[3, 3]));
+    }
+
+    #[test]
+    fn expressions() {
+        let source = "
+            let a = input();
+            let b = input();
+            output(a + b * 2 - (a - b));
+            output(-a / b);
+            output(a % b);
+            output(a < b);
+            output(a >= b);
+            output(a != b && !(a == b));
+            output(0 || b - b);
+        ";
+        assert_eq!(vec![9, -2, 1, 0, 1, 1, 0], run(source, &[7, 3]));
+    }
+
+    #[test]
+    fn control_flow() {
+        let source = "
+            fn fib(n) {
+                if n < 2 { return n; }
+                return fib(n - 1) + fib(n - 2);
+            }
+
+            let i = 0;
+            while 1 {
+                i = i + 1;
+                if i % 2 == 0 { continue; } else if i > 9 { break; }
+                output(fib(i));
+            }
+        ";
+        assert_eq!(vec![1, 2, 5, 13, 34], run(source, &[]));
+    }
+
+    #[test]
+    fn calling_convention() {
+        let rom = compile(ACKERMANN).unwrap();
+        let cfg = analyse(&rom);
+        assert_eq!(4, cfg.calls.len());
+        assert!(cfg.code_writes.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let error = |source: &str| compile(source).unwrap_err().to_string();
+        assert_eq!("1:9: undefined variable b", error("let a = b;"));
+        assert_eq!("2:5: undefined function f", error("\n    f();"));
+        assert_eq!(
+            "1:23: f expects 1 arguments but was given 2",
+            error("fn f(x) { return x; } f(1, 2);")
+        );
+        assert_eq!(
+            "1:11: expected ; but found let",
+            error("let a = 1 let b = 2;")
+        );
+        assert_eq!("1:1: break or continue outside of a loop", error("break;"));
+        assert_eq!("1:1: unexpected character '$'", error("$"));
+    }
+}
@@ -0,0 +1,203 @@
+use super::{IntcodeError, Interpretor, Result, State};
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread::JoinHandle;
+
+/// Iterator over the outputs of a program, see `Interpretor::outputs`.
+pub struct Outputs<'a, F> {
+    prg: &'a mut Interpretor,
+    input: F,
+    done: bool,
+}
+
+impl<'a, F> Iterator for Outputs<'a, F>
+where
+    F: FnMut() -> Option<i64>,
+{
+    type Item = Result<i64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.prg.next_output_with(&mut self.input) {
+            Ok(Some(v)) => Some(Ok(v)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+fn no_input() -> Option<i64> {
+    None
+}
+
+impl Interpretor {
+    /// Run until the next output, asking `input` for a value whenever the program awaits input.
+    /// Returns `None` once the program terminates.
+    pub fn next_output_with<F>(&mut self, input: &mut F) -> Result<Option<i64>>
+    where
+        F: FnMut() -> Option<i64>,
+    {
+        loop {
+            match self.run()? {
+                State::Suspended(v) => return Ok(Some(v)),
+                State::Terminated(_) => return Ok(None),
+                State::AwaitingInput => match input() {
+                    Some(v) => self.input(v),
+                    None => return Err(IntcodeError::InputExhausted { ip: self.ip }),
+                },
+            }
+        }
+    }
+
+    /// Run until the next output using only the inputs already provided.
+    pub fn next_output(&mut self) -> Result<Option<i64>> {
+        self.next_output_with(&mut no_input)
+    }
+
+    /// Iterate over the outputs of the program until it terminates.
+    pub fn outputs(&mut self) -> Outputs<'_, fn() -> Option<i64>> {
+        self.outputs_with(no_input)
+    }
+
+    /// Iterate over the outputs of the program, asking `input` for values as they are needed.
+    pub fn outputs_with<F>(&mut self, input: F) -> Outputs<'_, F>
+    where
+        F: FnMut() -> Option<i64>,
+    {
+        Outputs {
+            prg: self,
+            input,
+            done: false,
+        }
+    }
+
+    /// Provide `inputs` and collect every output until the program terminates.
+    pub fn run_with(&mut self, inputs: &[i64]) -> Result<Vec<i64>> {
+        for &v in inputs {
+            self.input(v);
+        }
+
+        self.outputs().collect()
+    }
+
+    /// Read the next `N` outputs. Returns `None` if the program terminates before producing any
+    /// of them.
+    pub fn read_n<const N: usize>(&mut self) -> Result<Option<[i64; N]>> {
+        self.read_n_with(&mut no_input)
+    }
+
+    pub fn read_n_with<F, const N: usize>(&mut self, input: &mut F) -> Result<Option<[i64; N]>>
+    where
+        F: FnMut() -> Option<i64>,
+    {
+        let mut values = [0; N];
+        for (i, value) in values.iter_mut().enumerate() {
+            match self.next_output_with(input)? {
+                Some(v) => *value = v,
+                None if i == 0 => return Ok(None),
+                None => return Err(IntcodeError::UnexpectedTermination { ip: self.op_ip }),
+            }
+        }
+
+        Ok(Some(values))
+    }
+
+    /// Run the program on its own thread, reading inputs from `input` and sending outputs to
+    /// `output`. The thread finishes with the last output once the program terminates.
+    ///
+    /// Outputs which can not be delivered because the receiver hung up are dropped, so the
+    /// last machine of a feedback loop can outlive the first.
+    pub fn spawn(
+        mut self,
+        input: Receiver<i64>,
+        output: Sender<i64>,
+    ) -> JoinHandle<Result<Option<i64>>> {
+        std::thread::spawn(move || {
+            let mut last = None;
+            while let Some(v) = self.next_output_with(&mut || input.recv().ok())? {
+                last = Some(v);
+                output.send(v).ok();
+            }
+
+            Ok(last)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    // Doubles each input until it receives 0
+    static DOUBLE: [i64; 15] = [3, 20, 1006, 20, 14, 1002, 20, 2, 21, 4, 21, 1105, 1, 0, 99];
+
+    #[test]
+    fn iterate_outputs() {
+        let mut prg = Interpretor::new(&DOUBLE);
+        assert_eq!(vec![2, 4, 6], prg.run_with(&[1, 2, 3, 0]).unwrap());
+
+        let mut prg = Interpretor::new(&DOUBLE);
+        prg.input(1);
+        let outputs: Vec<_> = prg.outputs().collect();
+        assert_eq!(
+            vec![Ok(2), Err(IntcodeError::InputExhausted { ip: 0 })],
+            outputs
+        );
+    }
+
+    #[test]
+    fn input_provider() {
+        let mut inputs = vec![0, 5, 4];
+        let mut prg = Interpretor::new(&DOUBLE);
+        let outputs: Result<Vec<_>> = prg.outputs_with(|| inputs.pop()).collect();
+        assert_eq!(Ok(vec![8, 10]), outputs);
+    }
+
+    #[test]
+    fn read_tuples() {
+        let mut prg = Interpretor::new(&DOUBLE);
+        for &v in &[1, 2, 3, 0] {
+            prg.input(v);
+        }
+        assert_eq!(Ok(Some([2, 4])), prg.read_n::<2>());
+        assert_eq!(
+            Err(IntcodeError::UnexpectedTermination { ip: 14 }),
+            prg.read_n::<2>()
+        );
+        assert_eq!(Ok(None), prg.read_n::<2>());
+    }
+
+    #[test]
+    fn channels() {
+        let (tx_a, rx_a) = channel();
+        let (tx_b, rx_b) = channel();
+        let (tx_c, rx_c) = channel();
+
+        let a = Interpretor::new(&DOUBLE).spawn(rx_a, tx_b);
+        let b = Interpretor::new(&DOUBLE).spawn(rx_b, tx_c);
+
+        for v in 1..=3 {
+            tx_a.send(v).unwrap();
+            assert_eq!(Ok(v * 4), rx_c.recv());
+        }
+        drop(tx_a);
+
+        assert_eq!(
+            Err(IntcodeError::InputExhausted { ip: 0 }),
+            a.join().unwrap()
+        );
+        assert_eq!(
+            Err(IntcodeError::InputExhausted { ip: 0 }),
+            b.join().unwrap()
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use super::{Interpretor, State};
+use anyhow::{anyhow, Result};
+use std::collections::VecDeque;
+
+/// Address of the NAT in the 2019 day 23 network.
+pub const NAT_ADDRESS: i64 = 255;
+
+const NO_PACKET: i64 = -1;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Packet {
+    pub src: i64,
+    pub dest: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+impl std::fmt::Display for Packet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:>3} -> {:>3}: ({}, {})",
+            self.src, self.dest, self.x, self.y
+        )
+    }
+}
+
+/// What the network should do after a monitor has been notified.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Action {
+    Continue,
+    Send(Packet),
+    Halt(i64),
+}
+
+/// Observes packets leaving the network and reacts to the network becoming idle.
+pub trait Monitor {
+    /// Called with every packet addressed outside of the network.
+    fn receive(&mut self, packet: Packet) -> Action;
+
+    /// Called when every queue is empty and every machine is awaiting input.
+    fn idle(&mut self) -> Action;
+}
+
+/// Monitor implementing the NAT. It remembers the last packet it received and sends it to
+/// machine 0 whenever the network is idle, halting once it sends the same `y` twice in a row.
+#[derive(Default)]
+pub struct Nat {
+    last: Option<Packet>,
+    last_sent_y: Option<i64>,
+}
+
+impl Monitor for Nat {
+    fn receive(&mut self, packet: Packet) -> Action {
+        self.last = Some(packet);
+        Action::Continue
+    }
+
+    fn idle(&mut self) -> Action {
+        let packet = match self.last {
+            Some(p) => p,
+            None => return Action::Continue,
+        };
+
+        if self.last_sent_y == Some(packet.y) {
+            return Action::Halt(packet.y);
+        }
+        self.last_sent_y = Some(packet.y);
+
+        Action::Send(Packet {
+            src: NAT_ADDRESS,
+            dest: 0,
+            x: packet.x,
+            y: packet.y,
+        })
+    }
+}
+
+struct Node {
+    prg: Interpretor,
+    queue: VecDeque<(i64, i64)>,
+    output: Vec<i64>,
+    terminated: bool,
+}
+
+/// Round-robin scheduler for machines exchanging `(dest, x, y)` packets. Each machine is booted
+/// with its address as the first input and receives `-1` whenever its queue is empty.
+pub struct Network<M> {
+    nodes: Vec<Node>,
+    monitor: M,
+    traffic: Option<Vec<Packet>>,
+}
+
+impl<M: Monitor> Network<M> {
+    pub fn new(rom: &[i64], size: usize, monitor: M) -> Self {
+        let nodes = (0..size)
+            .map(|addr| {
+                let mut prg = Interpretor::new(rom);
+                prg.input(addr as i64);
+                Node {
+                    prg,
+                    queue: VecDeque::new(),
+                    output: Vec::with_capacity(3),
+                    terminated: false,
+                }
+            })
+            .collect();
+
+        Network {
+            nodes,
+            monitor,
+            traffic: None,
+        }
+    }
+
+    /// Record every packet routed through the network, see `traffic`.
+    pub fn with_traffic_log(mut self) -> Self {
+        self.traffic = Some(Vec::new());
+        self
+    }
+
+    pub fn traffic(&self) -> &[Packet] {
+        self.traffic.as_deref().unwrap_or(&[])
+    }
+
+    pub fn monitor(&self) -> &M {
+        &self.monitor
+    }
+
+    /// Run the network until the monitor halts it, returning the value it halted with.
+    pub fn run(&mut self) -> Result<i64> {
+        loop {
+            if let Some(v) = self.tick()? {
+                return Ok(v);
+            }
+        }
+    }
+
+    /// Give every machine one turn. Returns `Some` if the monitor halted the network.
+    pub fn tick(&mut self) -> Result<Option<i64>> {
+        let mut active = false;
+
+        for addr in 0..self.nodes.len() {
+            let node = &mut self.nodes[addr];
+            if node.terminated {
+                continue;
+            }
+
+            match node.queue.pop_front() {
+                Some((x, y)) => {
+                    active = true;
+                    node.prg.input(x);
+                    node.prg.input(y);
+                }
+                None => node.prg.input(NO_PACKET),
+            }
+
+            let mut sent = Vec::new();
+            loop {
+                match node.prg.run()? {
+                    State::Suspended(v) => {
+                        node.output.push(v);
+                        if node.output.len() == 3 {
+                            sent.push(Packet {
+                                src: addr as i64,
+                                dest: node.output[0],
+                                x: node.output[1],
+                                y: node.output[2],
+                            });
+                            node.output.clear();
+                        }
+                    }
+                    State::AwaitingInput => break,
+                    State::Terminated(_) => {
+                        node.terminated = true;
+                        break;
+                    }
+                }
+            }
+
+            for packet in sent {
+                active = true;
+                if let Some(v) = self.route(packet)? {
+                    return Ok(Some(v));
+                }
+            }
+        }
+
+        if self.nodes.iter().all(|n| n.terminated) {
+            return Err(anyhow!("all machines terminated"));
+        }
+
+        if !active {
+            let action = self.monitor.idle();
+            return self.act(action);
+        }
+
+        Ok(None)
+    }
+
+    fn route(&mut self, packet: Packet) -> Result<Option<i64>> {
+        if let Some(traffic) = self.traffic.as_mut() {
+            traffic.push(packet);
+        }
+
+        match self.nodes.get_mut(packet.dest as usize) {
+            Some(node) if packet.dest >= 0 => {
+                node.queue.push_back((packet.x, packet.y));
+                Ok(None)
+            }
+            _ => {
+                let action = self.monitor.receive(packet);
+                self.act(action)
+            }
+        }
+    }
+
+    fn act(&mut self, action: Action) -> Result<Option<i64>> {
+        match action {
+            Action::Continue => Ok(None),
+            Action::Halt(v) => Ok(Some(v)),
+            Action::Send(packet) => match self.nodes.get(packet.dest as usize) {
+                Some(_) if packet.dest >= 0 => self.route(packet),
+                _ => Err(anyhow!(
+                    "monitor sent packet to unknown address {}",
+                    packet.dest
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Machine 0 boots by sending (1, 1, 1). Every machine that receives (x, y) sends
+    // (x + 1, x + 1, y * 2).
+    static RELAY: [i64; 39] = [
+        3, 100, 1005, 100, 11, 104, 1, 104, 1, 104, 1, 3, 101, 1008, 101, -1, 103, 1005, 103, 11,
+        3, 102, 1001, 101, 1, 104, 4, 104, 4, 104, 1002, 102, 2, 105, 4, 105, 1105, 1, 11,
+    ];
+
+    struct FirstPacket;
+
+    impl Monitor for FirstPacket {
+        fn receive(&mut self, packet: Packet) -> Action {
+            Action::Halt(packet.dest * 1000 + packet.y)
+        }
+
+        fn idle(&mut self) -> Action {
+            Action::Continue
+        }
+    }
+
+    #[derive(Default)]
+    struct CountIdle {
+        idle: usize,
+    }
+
+    impl Monitor for CountIdle {
+        fn receive(&mut self, _: Packet) -> Action {
+            Action::Continue
+        }
+
+        fn idle(&mut self) -> Action {
+            self.idle += 1;
+            if self.idle == 3 {
+                return Action::Halt(0);
+            }
+
+            Action::Send(Packet {
+                src: NAT_ADDRESS,
+                dest: 0,
+                x: 0,
+                y: self.idle as i64,
+            })
+        }
+    }
+
+    #[test]
+    fn routes_packets() {
+        let mut network = Network::new(&RELAY, 4, FirstPacket).with_traffic_log();
+        assert_eq!(4008, network.run().unwrap());
+        assert_eq!(
+            vec![
+                Packet {
+                    src: 0,
+                    dest: 1,
+                    x: 1,
+                    y: 1
+                },
+                Packet {
+                    src: 1,
+                    dest: 2,
+                    x: 2,
+                    y: 2
+                },
+                Packet {
+                    src: 2,
+                    dest: 3,
+                    x: 3,
+                    y: 4
+                },
+                Packet {
+                    src: 3,
+                    dest: 4,
+                    x: 4,
+                    y: 8
+                },
+            ],
+            network.traffic()
+        );
+    }
+
+    #[test]
+    fn detects_idle() {
+        let mut network = Network::new(&RELAY, 2, CountIdle::default()).with_traffic_log();
+        assert_eq!(0, network.run().unwrap());
+        assert_eq!(
+            vec![
+                Packet {
+                    src: 0,
+                    dest: 1,
+                    x: 1,
+                    y: 1
+                },
+                Packet {
+                    src: 1,
+                    dest: 2,
+                    x: 2,
+                    y: 2
+                },
+                Packet {
+                    src: 255,
+                    dest: 0,
+                    x: 0,
+                    y: 1
+                },
+                Packet {
+                    src: 0,
+                    dest: 1,
+                    x: 1,
+                    y: 2
+                },
+                Packet {
+                    src: 1,
+                    dest: 2,
+                    x: 2,
+                    y: 4
+                },
+                Packet {
+                    src: 255,
+                    dest: 0,
+                    x: 0,
+                    y: 2
+                },
+                Packet {
+                    src: 0,
+                    dest: 1,
+                    x: 1,
+                    y: 4
+                },
+                Packet {
+                    src: 1,
+                    dest: 2,
+                    x: 2,
+                    y: 8
+                },
+            ],
+            network.traffic()
+        );
+        assert_eq!(3, network.monitor().idle);
+    }
+
+    #[test]
+    fn nat_resends_until_repeated() {
+        let mut nat = Nat::default();
+        assert_eq!(Action::Continue, nat.idle());
+
+        let packet = Packet {
+            src: 1,
+            dest: NAT_ADDRESS,
+            x: 3,
+            y: 4,
+        };
+        assert_eq!(Action::Continue, nat.receive(packet));
+        assert_eq!(
+            Action::Send(Packet {
+                src: NAT_ADDRESS,
+                dest: 0,
+                x: 3,
+                y: 4
+            }),
+            nat.idle()
+        );
+        assert_eq!(Action::Halt(4), nat.idle());
+    }
+}
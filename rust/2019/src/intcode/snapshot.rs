@@ -0,0 +1,199 @@
+//! Compact binary snapshots of an `Interpretor`.
+//!
+//! A snapshot starts with the magic bytes `ICS1`, followed by the instruction pointer, relative
+//! base, memory limit, memory, pending inputs and last output. Integers are zigzag encoded
+//! LEB128 varints and sequences are prefixed with their length, so the mostly small values of
+//! an Intcode memory image take a byte or two each.
+use super::Interpretor;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"ICS1";
+
+impl Interpretor {
+    pub fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        write_int(w, self.ip as i64)?;
+        write_int(w, self.rb)?;
+        write_int(w, self.memory_limit as i64)?;
+        write_seq(w, self.memory.iter())?;
+        write_seq(w, self.inputs.iter())?;
+        match self.output {
+            Some(v) => {
+                write_int(w, 1)?;
+                write_int(w, v)
+            }
+            None => write_int(w, 0),
+        }
+    }
+
+    pub fn load<R: Read>(r: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not an intcode snapshot"));
+        }
+
+        let ip = read_usize(r)?;
+        let rb = read_int(r)?;
+        let memory_limit = read_usize(r)?;
+        let memory = read_seq(r)?;
+        if memory.len() > memory_limit {
+            return Err(invalid("memory exceeds limit"));
+        }
+        let inputs = read_seq(r)?.into_iter().collect::<VecDeque<_>>();
+        let output = match read_int(r)? {
+            0 => None,
+            1 => Some(read_int(r)?),
+            _ => return Err(invalid("invalid output flag")),
+        };
+
+        Ok(Interpretor {
+            ip,
+            op_ip: ip,
+            rb,
+            memory,
+            memory_limit,
+            inputs,
+            output,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.save(&mut buf).expect("writing to a Vec can not fail");
+        buf
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> io::Result<Self> {
+        let prg = Self::load(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(invalid("trailing bytes after snapshot"));
+        }
+
+        Ok(prg)
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_int<W: Write>(w: &mut W, v: i64) -> io::Result<()> {
+    let mut v = ((v << 1) ^ (v >> 63)) as u64;
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_int<R: Read>(r: &mut R) -> io::Result<i64> {
+    let mut v = 0u64;
+    let mut byte = [0];
+    for shift in (0..64).step_by(7) {
+        r.read_exact(&mut byte)?;
+        v |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok((v >> 1) as i64 ^ -((v & 1) as i64));
+        }
+    }
+
+    Err(invalid("varint too long"))
+}
+
+fn read_usize<R: Read>(r: &mut R) -> io::Result<usize> {
+    let v = read_int(r)?;
+    if v < 0 {
+        return Err(invalid("negative length or address"));
+    }
+
+    Ok(v as usize)
+}
+
+fn write_seq<'a, W: Write, I: ExactSizeIterator<Item = &'a i64>>(
+    w: &mut W,
+    seq: I,
+) -> io::Result<()> {
+    write_int(w, seq.len() as i64)?;
+    for &v in seq {
+        write_int(w, v)?;
+    }
+
+    Ok(())
+}
+
+fn read_seq<R: Read>(r: &mut R) -> io::Result<Vec<i64>> {
+    let len = read_usize(r)?;
+    // Do not trust the length for the allocation in case the snapshot is corrupt
+    let mut seq = Vec::with_capacity(len.min(1 << 16));
+    for _ in 0..len {
+        seq.push(read_int(r)?);
+    }
+
+    Ok(seq)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::State;
+    use super::*;
+
+    #[test]
+    fn varints() {
+        for &v in &[0, 1, -1, 63, -64, 64, 1 << 40, i64::MAX, i64::MIN] {
+            let mut buf = Vec::new();
+            write_int(&mut buf, v).unwrap();
+            assert_eq!(v, read_int(&mut buf.as_slice()).unwrap());
+        }
+
+        let mut buf = Vec::new();
+        write_int(&mut buf, -64).unwrap();
+        assert_eq!(vec![127], buf);
+    }
+
+    #[test]
+    fn resume_from_snapshot() {
+        // Outputs the sum of two inputs, twice
+        let rom = [3, 20, 3, 21, 1, 20, 21, 22, 4, 22, 1105, 1, 0];
+        let mut prg = Interpretor::new(&rom).with_memory_limit(100);
+        prg.input(1);
+        prg.input(2);
+        assert_eq!(State::Suspended(3), prg.run().unwrap());
+        prg.input(3);
+
+        let mut restored = Interpretor::from_bytes(&prg.to_bytes()).unwrap();
+        restored.input(4);
+        prg.input(4);
+        assert_eq!(State::Suspended(7), restored.run().unwrap());
+        assert_eq!(prg.run().unwrap(), State::Suspended(7));
+        assert_eq!(prg.to_bytes(), restored.to_bytes());
+    }
+
+    #[test]
+    fn reject_invalid_snapshots() {
+        assert!(Interpretor::from_bytes(b"nope").is_err());
+
+        let bytes = Interpretor::new(&[1, 2, 3]).to_bytes();
+        assert!(Interpretor::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(Interpretor::from_bytes(&trailing).is_err());
+
+        let bytes = Interpretor::new(&[1, 2, 3]).with_memory_limit(2).to_bytes();
+        assert!(Interpretor::from_bytes(&bytes).is_err());
+    }
+}
@@ -3,8 +3,12 @@ extern crate aoc_runner;
 #[macro_use]
 extern crate aoc_runner_derive;
 
+pub mod adventure;
 pub mod arkanoid;
 pub mod intcode;
+pub mod robot;
+pub mod sif;
+pub mod springscript;
 
 pub mod day01;
 pub mod day02;
@@ -26,10 +30,10 @@ pub mod day17;
 //pub mod day18;
 pub mod day19;
 //pub mod day20;
-//pub mod day21;
+pub mod day21;
 //pub mod day22;
-//pub mod day23;
+pub mod day23;
 //pub mod day24;
-//pub mod day25;
+pub mod day25;
 
 aoc_lib! { year = 2019 }
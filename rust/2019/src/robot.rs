@@ -0,0 +1,341 @@
+use crate::intcode::ascii::AsciiMachine;
+use crate::intcode::Interpretor;
+use anyhow::{anyhow, Result};
+use aocutil::{Direction, Grid, Point, Vector};
+use std::collections::{HashMap, VecDeque};
+
+/// Directions tried, in order, when exploring.
+pub const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+/// Translates between commands and responses of a robot and the values exchanged with its
+/// program.
+pub trait Codec {
+    type Command;
+    type Response;
+
+    /// Number of outputs making up a response.
+    const OUTPUTS: usize;
+
+    fn encode(&self, command: &Self::Command) -> Vec<i64>;
+    fn decode(&self, outputs: &[i64]) -> Result<Self::Response>;
+}
+
+/// A robot controlled by an Intcode program.
+#[derive(Clone)]
+pub struct Agent<C> {
+    prg: Interpretor,
+    codec: C,
+}
+
+impl<C: Codec> Agent<C> {
+    pub fn new(rom: &[i64], codec: C) -> Self {
+        Agent {
+            prg: Interpretor::new(rom),
+            codec,
+        }
+    }
+
+    /// Send a command and wait for the response. Returns `None` if the program terminated
+    /// instead of responding.
+    pub fn send(&mut self, command: &C::Command) -> Result<Option<C::Response>> {
+        for v in self.codec.encode(command) {
+            self.prg.input(v);
+        }
+
+        let mut outputs = Vec::with_capacity(C::OUTPUTS);
+        while outputs.len() < C::OUTPUTS {
+            match self.prg.next_output()? {
+                Some(v) => outputs.push(v),
+                None if outputs.is_empty() => return Ok(None),
+                None => {
+                    return Err(anyhow!(
+                        "terminated after {} of {} outputs",
+                        outputs.len(),
+                        C::OUTPUTS
+                    ))
+                }
+            }
+        }
+
+        self.codec.decode(&outputs).map(Some)
+    }
+
+    fn step(&mut self, command: &C::Command) -> Result<C::Response> {
+        self.send(command)?
+            .ok_or_else(|| anyhow!("robot terminated while exploring"))
+    }
+}
+
+/// Position and heading of a robot.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Pose {
+    pub position: Point,
+    pub heading: Direction,
+}
+
+impl Pose {
+    pub fn new(position: Point, heading: Direction) -> Self {
+        Pose { position, heading }
+    }
+
+    pub fn turn_left(&mut self) {
+        self.heading = self.heading.turn_left();
+    }
+
+    pub fn turn_right(&mut self) {
+        self.heading = self.heading.turn_right();
+    }
+
+    /// The position one step ahead.
+    pub fn ahead(&self) -> Point {
+        let v: Vector = self.heading.into();
+        self.position + v
+    }
+
+    pub fn advance(&mut self) {
+        self.position = self.ahead();
+    }
+}
+
+/// Outcome of trying to move onto a tile.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Step<T> {
+    Blocked(T),
+    Moved(T),
+}
+
+fn neighbour(p: Point, d: Direction) -> Point {
+    let v: Vector = d.into();
+    p + v
+}
+
+/// Map the world by walking the robot around, backtracking along its own path when it reaches a
+/// dead end. The robot starts at the origin on a tile of type `start` and ends up back there.
+pub fn explore_dfs<C, T, F>(agent: &mut Agent<C>, start: T, classify: F) -> Result<Grid<T>>
+where
+    C: Codec<Command = Direction>,
+    F: Fn(&C::Response) -> Step<T>,
+{
+    let mut world = Grid::new();
+    let mut pos = Point::new(0, 0);
+    let mut path: Vec<Direction> = Vec::new();
+    world.insert(pos, start);
+
+    loop {
+        let unknown = DIRECTIONS
+            .iter()
+            .find(|&&d| world.get(&neighbour(pos, d)).is_none());
+
+        match unknown {
+            Some(&d) => match classify(&agent.step(&d)?) {
+                Step::Blocked(t) => world.insert(neighbour(pos, d), t),
+                Step::Moved(t) => {
+                    pos = neighbour(pos, d);
+                    world.insert(pos, t);
+                    path.push(d);
+                }
+            },
+            None => match path.pop() {
+                Some(d) => {
+                    let back = d.reverse();
+                    if let Step::Blocked(_) = classify(&agent.step(&back)?) {
+                        return Err(anyhow!("unable to backtrack from {:?}", pos));
+                    }
+                    pos = neighbour(pos, back);
+                }
+                None => return Ok(world),
+            },
+        }
+    }
+}
+
+/// Map the world breadth-first, cloning the robot for every path instead of walking back.
+pub fn explore_bfs<C, T, F>(agent: &Agent<C>, start: T, classify: F) -> Result<Grid<T>>
+where
+    C: Codec<Command = Direction> + Clone,
+    F: Fn(&C::Response) -> Step<T>,
+{
+    let mut world = Grid::new();
+    let mut queue = VecDeque::new();
+    let origin = Point::new(0, 0);
+    world.insert(origin, start);
+    queue.push_back((origin, agent.clone()));
+
+    while let Some((pos, agent)) = queue.pop_front() {
+        for &d in DIRECTIONS.iter() {
+            let next = neighbour(pos, d);
+            if world.get(&next).is_some() {
+                continue;
+            }
+
+            let mut agent = agent.clone();
+            match classify(&agent.step(&d)?) {
+                Step::Blocked(t) => world.insert(next, t),
+                Step::Moved(t) => {
+                    world.insert(next, t);
+                    queue.push_back((next, agent));
+                }
+            }
+        }
+    }
+
+    Ok(world)
+}
+
+/// Number of steps from `start` to every tile reachable through `passable` tiles.
+pub fn flood<T, F>(world: &Grid<T>, start: Point, passable: F) -> HashMap<Point, usize>
+where
+    F: Fn(&T) -> bool,
+{
+    let mut distances = HashMap::new();
+    let mut queue = VecDeque::new();
+    distances.insert(start, 0);
+    queue.push_back(start);
+
+    while let Some(p) = queue.pop_front() {
+        let steps = distances[&p];
+        for &d in DIRECTIONS.iter() {
+            let next = neighbour(p, d);
+            match world.get(&next) {
+                Some(t) if passable(t) && !distances.contains_key(&next) => (),
+                _ => continue,
+            }
+            distances.insert(next, steps + 1);
+            queue.push_back(next);
+        }
+    }
+
+    distances
+}
+
+/// Read a picture of the world from an ASCII program. The first character is at the origin and
+/// rows continue downwards, as in `Grid::from_vec2d`.
+pub fn camera<T, F>(machine: &mut AsciiMachine, tile: F) -> Result<Grid<T>>
+where
+    F: Fn(char) -> Result<T>,
+{
+    let text = machine.read_all_text()?;
+    let mut world = Grid::new();
+    for (y, row) in text.lines().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            world.insert(Point::new(x as i64, -(y as i64)), tile(c)?);
+        }
+    }
+
+    Ok(world)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Moves through a maze given on its input and reports 0 for a wall, 1 after moving and 2
+    // after moving onto the goal.
+    //
+    //   #####
+    //   #..G#
+    //   #.#.#
+    //   #S..#
+    //   #####
+    #[derive(Clone)]
+    struct Maze;
+
+    impl Codec for Maze {
+        type Command = Direction;
+        type Response = i64;
+        const OUTPUTS: usize = 1;
+
+        fn encode(&self, d: &Direction) -> Vec<i64> {
+            vec![match d {
+                Direction::Up => 1,
+                Direction::Down => 2,
+                Direction::Left => 3,
+                Direction::Right => 4,
+            }]
+        }
+
+        fn decode(&self, outputs: &[i64]) -> Result<i64> {
+            Ok(outputs[0])
+        }
+    }
+
+    fn maze() -> Vec<i64> {
+        use crate::intcode::compiler::compile;
+        compile(
+            "
+            let x = 0;
+            let y = 0;
+            while 1 {
+                let d = input();
+                let nx = x + (d == 4) - (d == 3);
+                let ny = y + (d == 1) - (d == 2);
+                // Open tiles are the rows y = 0 and y = 2 and the column x = 0 and x = 2
+                let open = nx >= 0 && nx <= 2 && ny >= 0 && ny <= 2
+                    && (ny != 1 || nx != 1);
+                if open {
+                    x = nx;
+                    y = ny;
+                    output(1 + (x == 2 && y == 2));
+                } else {
+                    output(0);
+                }
+            }
+            ",
+        )
+        .unwrap()
+    }
+
+    fn classify(r: &i64) -> Step<char> {
+        match r {
+            0 => Step::Blocked('#'),
+            1 => Step::Moved('.'),
+            _ => Step::Moved('G'),
+        }
+    }
+
+    #[test]
+    fn explore() {
+        let rom = maze();
+        let mut agent = Agent::new(&rom, Maze);
+        let dfs = explore_dfs(&mut agent, '.', classify).unwrap();
+        assert_eq!(Some(&'G'), dfs.get(&Point::new(2, 2)));
+        assert_eq!(Some(&'#'), dfs.get(&Point::new(1, 1)));
+        assert_eq!(Some(&'#'), dfs.get(&Point::new(-1, 0)));
+        assert_eq!(21, dfs.len());
+        // Back at the start, so moving down hits the wall
+        assert_eq!(Some(0), agent.send(&Direction::Down).unwrap());
+
+        let bfs = explore_bfs(&Agent::new(&rom, Maze), '.', classify).unwrap();
+        assert_eq!(dfs, bfs);
+
+        let distances = flood(&bfs, Point::new(0, 0), |&t| t != '#');
+        assert_eq!(8, distances.len());
+        assert_eq!(4, distances[&Point::new(2, 2)]);
+    }
+
+    #[test]
+    fn pose() {
+        let mut pose = Pose::new(Point::new(0, 0), Direction::Up);
+        pose.advance();
+        pose.turn_right();
+        pose.advance();
+        pose.advance();
+        pose.turn_left();
+        pose.turn_left();
+        assert_eq!(Pose::new(Point::new(2, 1), Direction::Left), pose);
+        assert_eq!(Point::new(1, 1), pose.ahead());
+    }
+
+    #[test]
+    fn read_camera() {
+        let mut machine = AsciiMachine::new(&[104, 35, 104, 94, 104, 10, 104, 46, 104, 35, 99]);
+        let world = camera(&mut machine, Ok).unwrap();
+        assert_eq!(Some(&'^'), world.get(&Point::new(1, 0)));
+        assert_eq!(Some(&'.'), world.get(&Point::new(0, -1)));
+    }
+}
@@ -0,0 +1,363 @@
+//! The Space Image Format. An image is a sequence of layers of `width * height` digits, one per
+//! pixel in row-major order. Layers are listed front to back, and transparent pixels show the
+//! layers behind them.
+
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+pub type Grid = aocutil::Grid<Color>;
+type Point = aocutil::Point;
+
+/// Height of the letters read by `Image::ocr`.
+const GLYPH_HEIGHT: usize = 6;
+/// Width of a letter, including the blank column separating it from the next.
+const GLYPH_WIDTH: usize = 5;
+
+/// Letters of the 4x6 font, as rows of `#` for white and `.` for anything else.
+const GLYPHS: [(char, &str); 18] = [
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('I', ".###..#...#...#...#..###"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Y', "#...#....#.#..#...#...#."),
+    ('Z', "####...#..#..#..#...####"),
+];
+
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Color {
+    Black,
+    White,
+    #[default]
+    Transparent,
+}
+
+impl Color {
+    pub fn from_digit(c: char) -> Option<Self> {
+        match c {
+            '0' => Some(Color::Black),
+            '1' => Some(Color::White),
+            '2' => Some(Color::Transparent),
+            _ => None,
+        }
+    }
+
+    pub fn to_digit(self) -> char {
+        match self {
+            Color::Black => '0',
+            Color::White => '1',
+            Color::Transparent => '2',
+        }
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Color::White => '█',
+                Color::Black => '░',
+                Color::Transparent => ' ',
+            }
+        )
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars()
+            .next()
+            .and_then(Color::from_digit)
+            .ok_or_else(|| anyhow!("invalid color"))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    layers: Vec<Vec<Color>>,
+}
+
+impl Image {
+    /// Parse the digits of an image, ignoring whitespace. The digits must make up at least one
+    /// whole layer.
+    pub fn parse(digits: &str, width: usize, height: usize) -> Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(anyhow!("an image of {}x{} has no pixels", width, height));
+        }
+
+        let pixels = digits
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .enumerate()
+            .map(|(i, c)| {
+                Color::from_digit(c).ok_or_else(|| anyhow!("invalid digit {:?} at {}", c, i))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let size = width * height;
+        if pixels.is_empty() || pixels.len() % size != 0 {
+            return Err(anyhow!(
+                "{} digits do not make whole layers of {}x{}",
+                pixels.len(),
+                width,
+                height
+            ));
+        }
+
+        Ok(Image {
+            width,
+            height,
+            layers: pixels.chunks(size).map(|l| l.to_vec()).collect(),
+        })
+    }
+
+    /// A single layer image of the points `0..width` by `0..height` of `grid`, with the top row
+    /// at `y = height - 1` as in `composite`. Missing points are transparent.
+    pub fn from_grid(grid: &Grid, width: usize, height: usize) -> Self {
+        let layer = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let p = Point::new(x as i64, (height - 1 - y) as i64);
+                grid.get(&p).copied().unwrap_or_default()
+            })
+            .collect();
+
+        Image {
+            width,
+            height,
+            layers: vec![layer],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn layers(&self) -> &[Vec<Color>] {
+        &self.layers
+    }
+
+    /// The digits of the image, which `parse` reads back.
+    pub fn to_digits(&self) -> String {
+        self.layers.iter().flatten().map(|c| c.to_digit()).collect()
+    }
+
+    /// Number of pixels of each color in every layer.
+    pub fn histograms(&self) -> Vec<HashMap<Color, usize>> {
+        self.layers
+            .iter()
+            .map(|l| {
+                let mut counts = HashMap::new();
+                for &c in l {
+                    *counts.entry(c).or_insert(0) += 1;
+                }
+                counts
+            })
+            .collect()
+    }
+
+    /// The visible color at column `x` of row `y`, counting rows from the top.
+    fn pixel(&self, x: usize, y: usize) -> Color {
+        let i = y * self.width + x;
+        self.layers
+            .iter()
+            .map(|l| l[i])
+            .find(|&c| c != Color::Transparent)
+            .unwrap_or(Color::Transparent)
+    }
+
+    /// Flatten the layers. The top row is at `y = height - 1`, so the grid displays the right
+    /// way up.
+    pub fn composite(&self) -> Grid {
+        let mut g = Grid::default();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let p = Point::new(x as i64, (self.height - 1 - y) as i64);
+                g.insert(p, self.pixel(x, y));
+            }
+        }
+
+        g
+    }
+
+    /// The composite for a terminal, one line per row.
+    pub fn render(&self) -> String {
+        self.composite().to_string()
+    }
+
+    /// Write the composite as a plain PGM image. Transparent pixels are grey.
+    pub fn write_pgm<W: Write>(&self, out: W) -> io::Result<()> {
+        self.write_netpbm(out, "P2", |c| match c {
+            Color::Black => "0",
+            Color::White => "255",
+            Color::Transparent => "128",
+        })
+    }
+
+    /// Write the composite as a plain PPM image. Transparent pixels are magenta.
+    pub fn write_ppm<W: Write>(&self, out: W) -> io::Result<()> {
+        self.write_netpbm(out, "P3", |c| match c {
+            Color::Black => "0 0 0",
+            Color::White => "255 255 255",
+            Color::Transparent => "255 0 255",
+        })
+    }
+
+    fn write_netpbm<W, F>(&self, mut out: W, magic: &str, sample: F) -> io::Result<()>
+    where
+        W: Write,
+        F: Fn(Color) -> &'static str,
+    {
+        writeln!(out, "{}\n{} {}\n255", magic, self.width, self.height)?;
+        for y in 0..self.height {
+            let row: Vec<&str> = (0..self.width).map(|x| sample(self.pixel(x, y))).collect();
+            writeln!(out, "{}", row.join(" "))?;
+        }
+
+        Ok(())
+    }
+
+    /// Read the letters spelled out by white pixels. Letters that are not in the font are read
+    /// as `?`.
+    pub fn ocr(&self) -> Result<String> {
+        if self.height != GLYPH_HEIGHT {
+            return Err(anyhow!(
+                "letters are {} pixels high, not {}",
+                GLYPH_HEIGHT,
+                self.height
+            ));
+        }
+
+        let text = (0..(self.width + 1) / GLYPH_WIDTH)
+            .map(|i| {
+                let glyph: String = (0..GLYPH_HEIGHT)
+                    .flat_map(|y| (0..GLYPH_WIDTH - 1).map(move |x| (i * GLYPH_WIDTH + x, y)))
+                    .map(|(x, y)| match self.pixel(x, y) {
+                        Color::White => '#',
+                        _ => '.',
+                    })
+                    .collect();
+                GLYPHS
+                    .iter()
+                    .find(|(_, g)| *g == glyph)
+                    .map_or('?', |&(c, _)| c)
+            })
+            .collect();
+
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// An image of letters drawn with `#`, one glyph row per line.
+    fn banner(rows: &[&str]) -> Image {
+        let width = rows[0].len();
+        let mut g = Grid::default();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let color = if c == '#' { Color::White } else { Color::Black };
+                g.insert(Point::new(x as i64, (rows.len() - 1 - y) as i64), color);
+            }
+        }
+
+        Image::from_grid(&g, width, rows.len())
+    }
+
+    #[test]
+    fn test_parse() {
+        let image = Image::parse("123456789012", 3, 2);
+        assert!(image.is_err());
+
+        let image = Image::parse("120210\n222201\n", 3, 2).unwrap();
+        assert_eq!(2, image.layers().len());
+        assert_eq!("120210222201", image.to_digits());
+
+        assert_eq!(
+            "12 digits do not make whole layers of 5x2",
+            Image::parse("120210222201", 5, 2).unwrap_err().to_string()
+        );
+        assert!(Image::parse("", 3, 2).is_err());
+        assert!(Image::parse("1", 0, 1).is_err());
+    }
+
+    #[test]
+    fn test_histograms() {
+        let image = Image::parse("0222112222120000", 2, 2).unwrap();
+        let histograms = image.histograms();
+        assert_eq!(4, histograms.len());
+        assert_eq!(Some(&3), histograms[0].get(&Color::Transparent));
+        assert_eq!(None, histograms[3].get(&Color::White));
+        assert_eq!(Some(&4), histograms[3].get(&Color::Black));
+    }
+
+    #[test]
+    fn test_composite() {
+        let image = Image::parse("0222112222120000", 2, 2).unwrap();
+        assert_eq!("░█\n█░", image.render());
+
+        let mut pgm = vec![];
+        image.write_pgm(&mut pgm).unwrap();
+        assert_eq!(
+            "P2\n2 2\n255\n0 255\n255 0\n",
+            String::from_utf8(pgm).unwrap()
+        );
+
+        let mut ppm = vec![];
+        Image::parse("2", 1, 1)
+            .unwrap()
+            .write_ppm(&mut ppm)
+            .unwrap();
+        assert_eq!("P3\n1 1\n255\n255 0 255\n", String::from_utf8(ppm).unwrap());
+    }
+
+    #[test]
+    fn test_encode() {
+        let image = Image::parse("0222112222120000", 2, 2).unwrap();
+        let flat = Image::from_grid(&image.composite(), 2, 2);
+        assert_eq!("0110", flat.to_digits());
+        assert_eq!(image.composite(), flat.composite());
+    }
+
+    #[test]
+    fn test_ocr() {
+        let image = banner(&[
+            "#..#.###..####.",
+            "#..#.#..#.#....",
+            "####.#..#.###..",
+            "#..#.###..#....",
+            "#..#.#.#..#....",
+            "#..#.#..#.#....",
+        ]);
+        assert_eq!("HRF", image.ocr().unwrap());
+
+        let image = Image::parse(&image.to_digits(), 15, 6).unwrap();
+        assert_eq!("HRF", image.ocr().unwrap());
+        assert_eq!("?", banner(&["####"; 6]).ocr().unwrap());
+        assert!(Image::parse("1", 1, 1).unwrap().ocr().is_err());
+    }
+}
@@ -0,0 +1,480 @@
+use crate::intcode::ascii::AsciiMachine;
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+
+/// Maximum number of instructions accepted by the springdroid.
+pub const MAX_INSTRUCTIONS: usize = 15;
+
+/// Number of search states after which `synthesise` gives up.
+const SEARCH_LIMIT: usize = 1 << 22;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Mode {
+    Walk,
+    Run,
+}
+
+impl Mode {
+    /// Number of hull tiles the droid can sense ahead of itself.
+    pub fn range(self) -> usize {
+        match self {
+            Mode::Walk => 4,
+            Mode::Run => 9,
+        }
+    }
+
+    fn command(self) -> &'static str {
+        match self {
+            Mode::Walk => "WALK",
+            Mode::Run => "RUN",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Register {
+    /// Ground sensor `n` tiles ahead, starting at 0 for `A`.
+    Sensor(usize),
+    T,
+    J,
+}
+
+impl std::fmt::Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Register::Sensor(n) => write!(f, "{}", (b'A' + *n as u8) as char),
+            Register::T => write!(f, "T"),
+            Register::J => write!(f, "J"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Op {
+    And,
+    Or,
+    Not,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Instruction {
+    pub op: Op,
+    pub src: Register,
+    pub dst: Register,
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self.op {
+            Op::And => "AND",
+            Op::Or => "OR",
+            Op::Not => "NOT",
+        };
+        write!(f, "{} {} {}", op, self.src, self.dst)
+    }
+}
+
+fn parse_register(s: &str) -> Result<Register> {
+    match s.as_bytes() {
+        b"T" => Ok(Register::T),
+        b"J" => Ok(Register::J),
+        [c @ b'A'..=b'I'] => Ok(Register::Sensor((c - b'A') as usize)),
+        _ => Err(anyhow!("invalid register {}", s)),
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Script {
+    pub instructions: Vec<Instruction>,
+}
+
+impl std::fmt::Display for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for i in &self.instructions {
+            writeln!(f, "{}", i)?;
+        }
+        Ok(())
+    }
+}
+
+impl Script {
+    /// Parse one instruction per line. Blank lines are ignored.
+    pub fn parse(text: &str) -> Result<Script> {
+        let mut instructions = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let parts: Vec<_> = line.split_whitespace().collect();
+            if parts.is_empty() {
+                continue;
+            }
+
+            let instr = match parts[..] {
+                [op, src, dst] => {
+                    let op = match op {
+                        "AND" => Op::And,
+                        "OR" => Op::Or,
+                        "NOT" => Op::Not,
+                        _ => return Err(anyhow!("line {}: invalid operation {}", n + 1, op)),
+                    };
+                    let src = parse_register(src).map_err(|e| anyhow!("line {}: {}", n + 1, e))?;
+                    let dst = parse_register(dst).map_err(|e| anyhow!("line {}: {}", n + 1, e))?;
+                    if let Register::Sensor(_) = dst {
+                        return Err(anyhow!("line {}: can not write to sensor {}", n + 1, dst));
+                    }
+                    Instruction { op, src, dst }
+                }
+                _ => return Err(anyhow!("line {}: expected 3 words: {}", n + 1, line)),
+            };
+            instructions.push(instr);
+        }
+
+        Ok(Script { instructions })
+    }
+
+    /// Whether the droid jumps given the ground sensor readings.
+    pub fn jump(&self, sensors: &[bool]) -> bool {
+        let mut t = false;
+        let mut j = false;
+        for i in &self.instructions {
+            let x = match i.src {
+                Register::Sensor(n) => sensors.get(n).copied().unwrap_or(true),
+                Register::T => t,
+                Register::J => j,
+            };
+            let y = if i.dst == Register::T { &mut t } else { &mut j };
+            *y = match i.op {
+                Op::And => x && *y,
+                Op::Or => x || *y,
+                Op::Not => !x,
+            };
+        }
+
+        j
+    }
+
+    /// Walk the droid across `hull`. Returns the position of the hole it fell into, if any.
+    pub fn traverse(&self, hull: &Hull, mode: Mode) -> Option<usize> {
+        let mut pos = 0;
+        while pos < hull.len() {
+            if !hull.ground(pos) {
+                return Some(pos);
+            }
+            pos += if self.jump(&hull.sensors(pos, mode)) {
+                4
+            } else {
+                1
+            };
+        }
+
+        None
+    }
+
+    /// Render the script as input for the springdroid.
+    pub fn to_input(&self, mode: Mode) -> Result<String> {
+        if self.instructions.len() > MAX_INSTRUCTIONS {
+            return Err(anyhow!(
+                "script has {} instructions but at most {} are allowed",
+                self.instructions.len(),
+                MAX_INSTRUCTIONS
+            ));
+        }
+        for i in &self.instructions {
+            if let Register::Sensor(n) = i.src {
+                if n >= mode.range() {
+                    return Err(anyhow!(
+                        "sensor {} is not available in {} mode",
+                        i.src,
+                        mode.command()
+                    ));
+                }
+            }
+        }
+
+        Ok(format!("{}{}\n", self, mode.command()))
+    }
+}
+
+/// Hull layout where `true` is ground and `false` is a hole. The droid starts at position 0 and
+/// everything past the end is ground.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Hull(pub Vec<bool>);
+
+impl Hull {
+    /// Parse the hull from the springdroid's report of falling into space, or a single line of
+    /// `#` and `.`.
+    pub fn parse(text: &str) -> Option<Hull> {
+        text.lines()
+            .find(|l| l.contains('#') && l.chars().all(|c| c == '#' || c == '.'))
+            .map(|l| Hull(l.chars().map(|c| c == '#').collect()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn ground(&self, pos: usize) -> bool {
+        self.0.get(pos).copied().unwrap_or(true)
+    }
+
+    fn sensors(&self, pos: usize, mode: Mode) -> Vec<bool> {
+        (1..=mode.range()).map(|d| self.ground(pos + d)).collect()
+    }
+}
+
+type Bits = Vec<u64>;
+
+fn step(op: Op, x: &[u64], y: &[u64], mask: &[u64]) -> Bits {
+    x.iter()
+        .zip(y)
+        .zip(mask)
+        .map(|((x, y), m)| match op {
+            Op::And => x & y,
+            Op::Or => x | y,
+            Op::Not => !x & m,
+        })
+        .collect()
+}
+
+/// Find a shortest script that gets the droid across all `hulls`.
+///
+/// Scripts are only evaluated on the sensor readings which can occur on the given hulls, so that
+/// the values of `T` and `J` are small bitsets. A breadth-first search over these register
+/// values finds the fewest instructions needed to reach a jump decision that succeeds.
+pub fn synthesise(hulls: &[Hull], mode: Mode) -> Option<Script> {
+    // Index every distinct sensor reading and remember which one is seen at each position
+    let mut readings: HashMap<Vec<bool>, usize> = HashMap::new();
+    let positions: Vec<Vec<usize>> = hulls
+        .iter()
+        .map(|h| {
+            (0..h.len())
+                .map(|pos| {
+                    let n = readings.len();
+                    *readings.entry(h.sensors(pos, mode)).or_insert(n)
+                })
+                .collect()
+        })
+        .collect();
+
+    let words = readings.len() / 64 + 1;
+    let bit = |bits: &[u64], i: usize| bits[i / 64] & (1 << (i % 64)) != 0;
+    let mut mask = vec![0; words];
+    let mut sensors = vec![vec![0; words]; mode.range()];
+    for (reading, &i) in &readings {
+        mask[i / 64] |= 1 << (i % 64);
+        for (s, _) in reading.iter().enumerate().filter(|(_, &g)| g) {
+            sensors[s][i / 64] |= 1 << (i % 64);
+        }
+    }
+
+    let succeeds = |j: &[u64]| {
+        hulls.iter().zip(&positions).all(|(h, idx)| {
+            let mut pos = 0;
+            while pos < h.len() {
+                if !h.ground(pos) {
+                    return false;
+                }
+                pos += if bit(j, idx[pos]) { 4 } else { 1 };
+            }
+            true
+        })
+    };
+
+    let mut instructions = Vec::new();
+    for op in [Op::And, Op::Or, Op::Not].iter() {
+        let sources = (0..mode.range())
+            .map(Register::Sensor)
+            .chain(vec![Register::T, Register::J]);
+        for src in sources {
+            for &dst in [Register::T, Register::J].iter() {
+                instructions.push(Instruction { op: *op, src, dst });
+            }
+        }
+    }
+
+    // States are (T, J, parent, instruction) to rebuild the script once a solution is found
+    let empty = vec![0; words];
+    let mut states: Vec<(Bits, Bits, usize, Option<Instruction>)> =
+        vec![(empty.clone(), empty.clone(), 0, None)];
+    let mut seen: HashSet<(Bits, Bits)> = HashSet::new();
+    seen.insert((empty.clone(), empty));
+
+    let mut level = 0..1;
+    let mut found = if succeeds(&states[0].1) {
+        Some(0)
+    } else {
+        None
+    };
+    for _ in 0..MAX_INSTRUCTIONS {
+        if found.is_some() || level.is_empty() || states.len() > SEARCH_LIMIT {
+            break;
+        }
+
+        let next = states.len();
+        'level: for parent in level {
+            for instr in &instructions {
+                let (t, j) = (&states[parent].0, &states[parent].1);
+                let x = match instr.src {
+                    Register::Sensor(n) => &sensors[n],
+                    Register::T => t,
+                    Register::J => j,
+                };
+                let (t, j) = if instr.dst == Register::T {
+                    (step(instr.op, x, t, &mask), j.clone())
+                } else {
+                    (t.clone(), step(instr.op, x, j, &mask))
+                };
+
+                if seen.insert((t.clone(), j.clone())) {
+                    let done = succeeds(&j);
+                    states.push((t, j, parent, Some(*instr)));
+                    if done {
+                        found = Some(states.len() - 1);
+                        break 'level;
+                    }
+                }
+            }
+        }
+        level = next..states.len();
+    }
+
+    let mut idx = found?;
+    let mut script = Script::default();
+    while let Some(instr) = states[idx].3 {
+        script.instructions.push(instr);
+        idx = states[idx].2;
+    }
+    script.instructions.reverse();
+
+    Some(script)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// The droid made it across and reported the amount of hull damage.
+    Survived(i64),
+    Fell(Hull),
+}
+
+/// Run `script` on the springdroid.
+pub fn run(rom: &[i64], script: &Script, mode: Mode) -> Result<Outcome> {
+    let mut machine = AsciiMachine::new(rom);
+    machine.read_until_prompt()?;
+    machine.write(&script.to_input(mode)?);
+    let text = machine.read_all_text()?;
+
+    if let Some(damage) = machine.answer() {
+        return Ok(Outcome::Survived(damage));
+    }
+
+    Hull::parse(&text)
+        .map(Outcome::Fell)
+        .ok_or_else(|| anyhow!("unexpected output from springdroid: {}", text))
+}
+
+/// Repeatedly synthesise a script for all hulls seen so far and try it on the springdroid, until
+/// it makes it across.
+pub fn survey<F>(mode: Mode, mut attempt: F) -> Result<i64>
+where
+    F: FnMut(&Script) -> Result<Outcome>,
+{
+    let mut hulls: Vec<Hull> = Vec::new();
+    loop {
+        let script = synthesise(&hulls, mode)
+            .ok_or_else(|| anyhow!("no script gets across all {} hulls", hulls.len()))?;
+
+        match attempt(&script)? {
+            Outcome::Survived(damage) => return Ok(damage),
+            Outcome::Fell(hull) if hulls.contains(&hull) => {
+                return Err(anyhow!("script fell on a known hull:\n{}", script))
+            }
+            Outcome::Fell(hull) => hulls.push(hull),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hull(s: &str) -> Hull {
+        Hull::parse(s).unwrap()
+    }
+
+    #[test]
+    fn parse_and_evaluate() {
+        let text = "NOT A J\nNOT B T\nOR T J\nNOT C T\nOR T J\nAND D J\n";
+        let script = Script::parse(text).unwrap();
+        assert_eq!(6, script.instructions.len());
+        assert_eq!(text, script.to_string());
+        assert_eq!(
+            format!("{}WALK\n", text),
+            script.to_input(Mode::Walk).unwrap()
+        );
+
+        assert!(script.jump(&[false, true, true, true]));
+        assert!(!script.jump(&[false, true, true, false]));
+        assert!(!script.jump(&[true, true, true, true]));
+
+        assert_eq!(
+            None,
+            script.traverse(&hull("#####.#..########"), Mode::Walk)
+        );
+        assert_eq!(
+            Some(4),
+            Script::default().traverse(&hull("####.####"), Mode::Walk)
+        );
+
+        assert!(Script::parse("NOT A B").is_err());
+        assert!(Script::parse("XOR A J").is_err());
+        assert!(Script::parse("NOT E J")
+            .unwrap()
+            .to_input(Mode::Walk)
+            .is_err());
+    }
+
+    #[test]
+    fn parse_report() {
+        let report = "\nDidn't make it across:\n\n.................\n.................\n@................\n#####.#..########\n\n";
+        assert_eq!(Some(hull("#####.#..########")), Hull::parse(report));
+    }
+
+    #[test]
+    fn synthesise_minimal() {
+        assert_eq!(Some(Script::default()), synthesise(&[], Mode::Walk));
+
+        let hulls = [hull("#####.###"), hull("#####..#.########")];
+        let script = synthesise(&hulls, Mode::Walk).unwrap();
+        assert_eq!(3, script.instructions.len());
+        for h in &hulls {
+            assert_eq!(None, script.traverse(h, Mode::Walk));
+        }
+    }
+
+    #[test]
+    fn survey_hulls() {
+        let hulls = [
+            hull("#####.#..########"),
+            hull("#####...#########"),
+            hull("#####.#.##..#.###"),
+            hull("#####.##.##.#.###"),
+            hull("#####.#.##...####"),
+        ];
+        let mut attempts = 0;
+        let damage = survey(Mode::Run, |script| {
+            attempts += 1;
+            for h in &hulls {
+                if script.traverse(h, Mode::Run).is_some() {
+                    return Ok(Outcome::Fell(h.clone()));
+                }
+            }
+            Ok(Outcome::Survived(42))
+        })
+        .unwrap();
+
+        assert_eq!(42, damage);
+        assert!(attempts <= hulls.len() + 1);
+    }
+}
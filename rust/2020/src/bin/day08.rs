@@ -1,98 +1,49 @@
-use anyhow::{anyhow, Result};
-use std::collections::HashSet;
-
-#[derive(Clone, Copy, Debug)]
-enum Instr {
-    Jmp(i64),
-    Acc(i64),
-    Nop(i64),
+use anyhow::Result;
+use aocutil::vm::{patch_and_retry, Effect, Instruction, InstructionSet, Machine};
+
+fn handheld() -> InstructionSet {
+    InstructionSet::new()
+        .op("acc", 1, 1, |r, a| {
+            r.add("acc", r.value(&a[0]));
+            Effect::Next
+        })
+        .op("jmp", 1, 1, |r, a| Effect::Jump(r.value(&a[0])))
+        .op("nop", 1, 1, |_, _| Effect::Next)
 }
 
-fn parse_input(s: &str) -> Result<Vec<Instr>> {
-    s.lines()
-        .map(|l| {
-            let mut parts = l.split(" ");
-            let instr = parts.next().ok_or_else(|| anyhow!("instruction missing"))?;
-            let arg = parts
-                .next()
-                .ok_or_else(|| anyhow!("argument missing"))?
-                .parse()?;
-            match instr {
-                "acc" => Ok(Instr::Acc(arg)),
-                "jmp" => Ok(Instr::Jmp(arg)),
-                "nop" => Ok(Instr::Nop(arg)),
-                instr => Err(anyhow!("invalid instruction {}", instr)),
-            }
-        })
-        .collect()
+fn parse_input(s: &str, set: &InstructionSet) -> Result<Vec<Instruction>> {
+    Ok(set.parse(s)?)
 }
 
 fn part_one(input: &str) -> String {
-    let program = parse_input(input).unwrap();
-    let mut history: HashSet<usize> = HashSet::default();
-    let mut pc: usize = 0;
-    let mut acc: i64 = 0;
-
-    while history.insert(pc) {
-        let mut jmp: i64 = 1;
+    let set = handheld();
+    let program = parse_input(input, &set).unwrap();
+    let mut machine = Machine::new(&set, program).unwrap();
+    machine.run_until_loop();
 
-        match program[pc] {
-            Instr::Acc(v) => acc += v,
-            Instr::Jmp(v) => jmp = v,
-            _ => {}
-        }
-
-        pc = (pc as i64 + jmp) as usize;
-    }
-
-    acc.to_string()
+    machine.registers.get("acc").to_string()
 }
 
 fn part_two(input: &str) -> String {
-    let original = parse_input(input).unwrap();
-
-    original
-        .iter()
-        .enumerate()
-        .fold(Vec::default(), |mut programs, (pc, &instr)| {
-            let new_instr = match instr {
-                Instr::Nop(v) => Instr::Jmp(v),
-                Instr::Jmp(v) => Instr::Nop(v),
-                _ => return programs,
-            };
-
-            let mut new_program = original.clone();
-            new_program[pc] = new_instr;
-
-            programs.push(new_program);
-            programs
+    let set = handheld();
+    let program = parse_input(input, &set).unwrap();
+
+    let (_, machine) = patch_and_retry(&set, &program, |instr| {
+        let op = match instr.op.as_str() {
+            "nop" => "jmp",
+            "jmp" => "nop",
+            _ => return None,
+        };
+
+        Some(Instruction {
+            op: op.to_string(),
+            ..instr.clone()
         })
-        .iter()
-        .find_map(|program| {
-            let mut history: HashSet<usize> = HashSet::default();
-            let mut pc: usize = 0;
-            let mut acc: i64 = 0;
-
-            while pc < program.len() {
-                if !history.insert(pc) {
-                    return None;
-                }
-
-                let mut jmp: i64 = 1;
+    })
+    .unwrap()
+    .unwrap();
 
-                match program[pc] {
-                    Instr::Acc(v) => acc += v,
-                    Instr::Jmp(v) => jmp = v,
-                    _ => {}
-                }
-
-                pc = (pc as i64 + jmp) as usize;
-            }
-
-            Some(acc)
-        })
-        .unwrap()
-        .to_string()
+    machine.registers.get("acc").to_string()
 }
 
 fn main() -> Result<()> {
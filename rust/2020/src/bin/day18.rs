@@ -1,129 +1,37 @@
-use std::collections::HashMap;
-use rayon::str::ParallelString;
+use aocutil::{Assoc, Operators};
 use rayon::iter::ParallelIterator;
+use rayon::str::ParallelString;
 
-#[derive(Debug)]
-enum Expr {
-    Add(Box<Expr>, Box<Expr>),
-    Mul(Box<Expr>, Box<Expr>),
-    Num(i64),
-}
-
-impl Expr {
-    fn eval(&self) -> i64 {
-        match self {
-            Expr::Add(a, b) => a.eval() + b.eval(),
-            Expr::Mul(a, b) => a.eval() * b.eval(),
-            Expr::Num(n) => *n,
-        }
-    }
+fn add(a: i64, b: i64) -> i64 {
+    a + b
 }
 
-struct Parser<'a> {
-    s: Vec<u8>,
-    cursor: usize,
-    max_precedence: u8,
-    prec: &'a HashMap<u8, u8>,
+fn mul(a: i64, b: i64) -> i64 {
+    a * b
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(s: &str, prec: &'a HashMap<u8, u8>) -> Self {
-        let max_precedence = *prec.iter().max_by_key(|&(_, p)| p).unwrap().1;
-        Parser {
-            s: s.as_bytes().to_owned(),
-            cursor: 0,
-            max_precedence,
-            prec,
-        }
-    }
-
-    pub fn expr(&mut self) -> Expr {
-        self.binary_operator(0)
-    }
-
-    fn binary_operator(&mut self, precedence: u8) -> Expr {
-        if precedence > self.max_precedence {
-            return self.operand();
-        }
-
-        let mut lhs = self.binary_operator(precedence + 1);
-        while self.cursor < self.s.len() && self.next_is_operator() {
-            let op = self.next();
-            let op_prec = self.prec.get(&op).unwrap();
-            let rhs = self.binary_operator(op_prec + 1);
-            lhs = match op {
-                b'+' => Expr::Add(Box::new(lhs), Box::new(rhs)),
-                b'*' => Expr::Mul(Box::new(lhs), Box::new(rhs)),
-                _ => unreachable!(),
-            }
-        }
-
-        lhs
-    }
-
-    fn operand(&mut self) -> Expr {
-        if self.peek() == b'(' {
-            self.paren()
-        } else {
-            self.num()
-        }
-    }
-
-    fn paren(&mut self) -> Expr {
-        self.drop(b'(');
-        let r = self.expr();
-        self.drop(b')');
-        r
-    }
-
-    fn num(&mut self) -> Expr {
-        Expr::Num((self.next() - b'0') as i64)
-    }
-
-    fn next_is_operator(&self) -> bool {
-        let c = self.peek();
-        [b'+', b'*'].iter().find(|&&b| b == c).is_some()
-    }
-
-    fn peek(&self) -> u8 {
-        self.s[self.cursor]
-    }
-
-    fn next(&mut self) -> u8 {
-        let c = self.s[self.cursor];
-        self.cursor += 1;
-        c
-    }
-
-    fn drop(&mut self, _b: u8) {
-        self.next();
-    }
+fn sum(input: &str, ops: &Operators) -> String {
+    input
+        .par_lines()
+        .map(|expr| ops.evaluate(expr).unwrap())
+        .sum::<i64>()
+        .to_string()
 }
 
 fn part_one(input: &str) -> String {
-    let mut prec = HashMap::new();
-    prec.insert(b'+', 1);
-    prec.insert(b'*', 1);
+    let ops = Operators::new()
+        .binary("+", 1, Assoc::Left, add)
+        .binary("*", 1, Assoc::Left, mul);
 
-    input
-        .replace(" ", "")
-        .lines()
-        .map(|expr| Parser::new(expr, &prec).expr().eval())
-        .sum::<i64>()
-        .to_string()
+    sum(input, &ops)
 }
 
 fn part_two(input: &str) -> String {
-    let mut prec = HashMap::new();
-    prec.insert(b'+', 2);
-    prec.insert(b'*', 1);
+    let ops = Operators::new()
+        .binary("+", 2, Assoc::Left, add)
+        .binary("*", 1, Assoc::Left, mul);
 
-    input
-        .replace(" ", "")
-        .par_lines()
-        .map(|expr| Parser::new(expr, &prec).expr().eval())
-        .sum::<i64>()
-        .to_string()
+    sum(input, &ops)
 }
 
 fn main() {
@@ -1,141 +1,27 @@
-use anyhow::{anyhow, Result};
-use std::collections::HashMap;
-use rayon::str::ParallelString;
+use anyhow::Result;
+use aocutil::Grammar;
 use rayon::iter::ParallelIterator;
+use rayon::str::ParallelString;
 
-#[derive(Clone, PartialEq, Debug)]
-enum Rule {
-    Char(char),
-    Seq(Vec<i64>),
-    Alt(Vec<i64>, Vec<i64>),
-}
-
-impl std::str::FromStr for Rule {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.contains('|') {
-            let mut parts = s.split(" | ");
-            let left = parts
-                .next()
-                .ok_or_else(|| anyhow!("missing left alternate"))?
-                .split(' ')
-                .map(|n| n.parse().unwrap())
-                .collect();
-            let right = parts
-                .next()
-                .ok_or_else(|| anyhow!("missing right alternate"))?
-                .split(' ')
-                .map(|n| n.parse().unwrap())
-                .collect();
-
-            Ok(Rule::Alt(left, right))
-        } else if s.starts_with('"') {
-            let c = s
-                .chars()
-                .nth(1)
-                .ok_or_else(|| anyhow!("missing reference"))?;
-
-            Ok(Rule::Char(c))
-        } else {
-            let ids = s
-                .split(' ')
-                .map(|id| {
-                    id.parse()
-                        .map_err(|err| anyhow!("invalid reference: {}", err))
-                })
-                .collect::<Result<_>>()?;
-
-            Ok(Rule::Seq(ids))
-        }
-    }
-}
-
-struct RuleEngine {
-    rules: HashMap<i64, Rule>,
-}
-
-impl RuleEngine {
-    pub fn new(rules: HashMap<i64, Rule>) -> Self {
-        RuleEngine { rules }
-    }
-
-    pub fn matches(&self, s: &str) -> bool {
-        self.match_rule_id(s, 0).contains(&Some(""))
-    }
-
-    fn match_rule_id<'a>(&self, s: &'a str, rule_id: i64) -> Vec<Option<&'a str>> {
-        let rule = self.rules.get(&rule_id).unwrap();
-
-        self.match_rule(s, rule)
-    }
-
-    fn match_rule<'a>(&self, s: &'a str, rule: &Rule) -> Vec<Option<&'a str>> {
-        match rule {
-            Rule::Char(c) if s.chars().next() == Some(*c) => vec![Some(&s[1..])],
-            Rule::Char(_) => vec![None],
-            Rule::Seq(rs) => self.match_seq(s, rs),
-            Rule::Alt(left, right) => self.match_alt(s, left, right),
-        }
-    }
-
-    fn match_seq<'a>(&self, s: &'a str, rules: &[i64]) -> Vec<Option<&'a str>> {
-        rules.iter().fold(vec![Some(s)], |ss, r| {
-            ss.iter()
-                .flat_map(|s| match s {
-                    Some(s) if !s.is_empty() => self.match_rule_id(s, *r),
-                    _ => vec![None],
-                })
-                .collect()
-        })
-    }
-
-    fn match_alt<'a>(&self, s: &'a str, left: &[i64], right: &[i64]) -> Vec<Option<&'a str>> {
-        [left, right]
-            .iter()
-            .flat_map(|rs| self.match_seq(s, rs))
-            .collect()
-    }
-}
-
-impl std::str::FromStr for RuleEngine {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let rules = s
-            .par_lines()
-            .map(|l| {
-                let mut parts = l.split(": ");
-                Ok((
-                    parts.next().ok_or_else(|| anyhow!("missing id"))?.parse()?,
-                    parts
-                        .next()
-                        .ok_or_else(|| anyhow!("missing definition"))?
-                        .parse()?,
-                ))
-            })
-            .collect::<Result<_>>()?;
-
-        Ok(RuleEngine::new(rules))
-    }
-}
-
-fn parse_input<'a>(input: &'a str) -> (RuleEngine, impl ParallelIterator<Item = &'a str> + 'a) {
+fn parse_input<'a>(
+    input: &'a str,
+) -> Result<(Grammar, impl ParallelIterator<Item = &'a str> + 'a)> {
     let mut parts = input.split("\n\n");
-    (
-        parts.next().unwrap().parse().unwrap(),
-        parts.next().unwrap().par_lines(),
-    )
+    Ok((
+        parts.next().unwrap_or("").parse()?,
+        parts.next().unwrap_or("").par_lines(),
+    ))
 }
 
 fn part_one(input: &str) -> String {
-    let (rules, messages) = parse_input(input);
+    let (grammar, messages) = parse_input(input).unwrap();
 
-    messages.filter(|m| rules.matches(m)).count().to_string()
+    messages.filter(|m| grammar.matches(m)).count().to_string()
 }
 
-fn part_two(input: &str) -> String {
-    let input: String = input
+/// Replace rules 8 and 11 with their looping versions.
+fn rewrite_loops(input: &str) -> String {
+    input
         .lines()
         .map(|l| {
             if l.starts_with("8: ") {
@@ -147,9 +33,11 @@ fn part_two(input: &str) -> String {
             }
         })
         .collect::<Vec<&str>>()
-        .join("\n");
+        .join("\n")
+}
 
-    part_one(&input)
+fn part_two(input: &str) -> String {
+    part_one(&rewrite_loops(input))
 }
 
 fn main() {
@@ -177,69 +65,19 @@ mod test {
     test_example!(example_two_5, part_two, 19, 2, 5);
 
     #[test]
-    fn parse_char() -> Result<()> {
-        assert_eq!(Rule::from_str("\"a\"")?, Rule::Char('a'));
-        Ok(())
-    }
-
-    #[test]
-    fn parse_seq() -> Result<()> {
-        assert_eq!(Rule::from_str("1 2")?, Rule::Seq(vec![1, 2]));
-        Ok(())
-    }
-
-    #[test]
-    fn parse_alt() -> Result<()> {
-        assert_eq!(Rule::from_str("1 | 2")?, alt(vec![1], vec![2]),);
+    fn loop_rewriting() -> Result<()> {
+        let input = include_str!("../../../../example/2020/day19-02-01.txt");
+        let rules = input.split("\n\n").next().unwrap();
+        let original = Grammar::from_str(rules)?;
+        let rewritten = Grammar::from_str(&rewrite_loops(rules))?;
+
+        // Rules 42 and 31 match five characters, so the original grammar only generates
+        // strings of length 15, which the rewritten one generates too
+        assert_eq!(original.language(15), rewritten.language(15));
+        assert_eq!(original.count(20), 0);
+        let longer = rewritten.language(20);
+        assert!(!longer.is_empty());
+        assert!(longer.iter().step_by(1000).all(|s| rewritten.matches(s)));
         Ok(())
     }
-
-    #[test]
-    fn match_char() {
-        let mut rules = HashMap::new();
-        rules.insert(0, Rule::Char('a'));
-
-        assert!(RuleEngine::new(rules).matches("a"));
-    }
-
-    #[test]
-    fn match_seq() {
-        let mut rules = HashMap::new();
-        rules.insert(0, Rule::Seq(vec![1, 2]));
-        rules.insert(1, Rule::Char('a'));
-        rules.insert(2, Rule::Char('b'));
-
-        assert!(RuleEngine::new(rules).matches("ab"));
-    }
-
-    #[test]
-    fn match_alt() {
-        let mut rules = HashMap::new();
-        rules.insert(0, alt(vec![1], vec![2]));
-        rules.insert(1, Rule::Char('a'));
-        rules.insert(2, Rule::Char('b'));
-
-        let engine = RuleEngine::new(rules);
-        assert!(engine.matches("a"));
-        assert!(engine.matches("b"));
-    }
-
-    #[test]
-    fn match_seq_alt() {
-        let mut rules = HashMap::new();
-        rules.insert(0, Rule::Seq(vec![1, 1]));
-        rules.insert(1, alt(vec![2], vec![3]));
-        rules.insert(2, Rule::Char('a'));
-        rules.insert(3, Rule::Char('b'));
-
-        let engine = RuleEngine::new(rules);
-        assert!(engine.matches("aa"));
-        assert!(engine.matches("ab"));
-        assert!(engine.matches("ba"));
-        assert!(engine.matches("bb"));
-    }
-
-    fn alt(left: Vec<i64>, right: Vec<i64>) -> Rule {
-        Rule::Alt(left, right)
-    }
 }
@@ -1,88 +1,43 @@
-use aocutil::{Grid, Point};
-use std::collections::HashMap;
+use aocutil::jigsaw::{assemble, search, Cells, Pattern, Tile};
 
-#[derive(Clone, Debug)]
-struct Image {
-    id: i64,
-    grid: Grid<char>,
-}
+const SEA_MONSTER: &str = "                  # \n#    ##    ##    ###\n #  #  #  #  #  #   ";
 
-fn parse_input<'a>(input: &'a str) -> Vec<Image> {
+fn parse_input(input: &str) -> Vec<Tile> {
     input
         .split("\n\n")
+        .filter(|tile| !tile.trim().is_empty())
         .map(|tile| {
             let mut lines = tile.lines();
             let id = lines
                 .next()
                 .expect("missing tile header")
-                .split(" ")
+                .split(' ')
                 .nth(1)
-                .map(|id| id[0..id.len() - 1].parse().expect("invalid id"))
+                .map(|id| id.trim_end_matches(':').parse().expect("invalid id"))
                 .expect("missing id");
-            let mut grid = Grid::new();
-
-            for (dy, l) in lines.enumerate() {
-                for (x, c) in l.chars().enumerate() {
-                    grid.insert(Point::new(x as i64, 0 - dy as i64), c);
-                }
-            }
+            let cells: Cells = lines
+                .map(|l| l.chars().map(|c| c == '#').collect())
+                .collect();
 
-            Image { id, grid }
+            Tile { id, cells }
         })
         .collect()
 }
 
 fn part_one(input: &str) -> String {
-    let images = parse_input(input);
-    let mut borders: HashMap<String, Vec<&Image>> = HashMap::new();
-
-    for img in &images {
-        let g = &img.grid;
-        let min_y = 0 - (g.rows() as i64);
-        let max_x = g.cols() as i64;
-
-        let get_cell = |x, y| {
-            g.get(&Point::new(x, y))
-                .expect(&format!("cell missing at {},{}", x, y))
-        };
-
-        let left: String = (min_y..=0).map(|y| get_cell(0, y)).collect();
-        let right: String = (min_y..=0).map(|y| get_cell(max_x, y)).collect();
-        let top: String = (0..=max_x).map(|x| get_cell(x, 0)).collect();
-        let bottom: String = (0..=max_x).map(|x| get_cell(x, min_y)).collect();
-
-        for border in &[left, top, right, bottom] {
-            borders
-                .entry(border.to_owned())
-                .or_insert(Vec::new())
-                .push(img);
-            let rev = border.chars().rev().collect();
-            borders.entry(rev).or_insert(Vec::new()).push(&img);
-        }
-    }
-
-    let mut corner = HashMap::new();
-    // Borders on the edges will not touch any other image. For each border matching only one
-    // image, add it to a HashMap<ID, Vec<Border>>. This lets us find the edge images which have
-    // two borders, i.e. corners.
-    borders
-        .iter()
-        .filter(|(_, imgs)| imgs.len() == 1)
-        .flat_map(|(border, imgs)| imgs.iter().map(move |i| (border, i)))
-        .for_each(|(border, img)| corner.entry(img.id).or_insert(Vec::new()).push(border));
+    let tiles = parse_input(input);
+    let mosaic = assemble(&tiles).unwrap();
 
-    // Corners will have two edge borders. Images in the middle will only have one. Since both
-    // permutations of the border are added, the amount of images is doubled.
-    corner
-        .iter()
-        .filter(|(_, imgs)| imgs.len() == 4)
-        .map(|(id, _)| id)
-        .product::<i64>()
-        .to_string()
+    mosaic.corners().iter().product::<i64>().to_string()
 }
 
-fn part_two(_input: &str) -> String {
-    "".to_string()
+fn part_two(input: &str) -> String {
+    let tiles = parse_input(input);
+    let image = assemble(&tiles).unwrap().image();
+    let found = search(&image, &Pattern::parse(SEA_MONSTER)).expect("no sea monsters");
+
+    let set = image.iter().flatten().filter(|&&b| b).count();
+    (set - found.covered).to_string()
 }
 
 fn main() {
@@ -97,5 +52,21 @@ mod test {
     use aocutil::test_example;
 
     test_example!(example_one_1, part_one, 20, 1, 1);
-    //test_example!(example_two_1, part_two, 20, 2, 1);
+    test_example!(example_two_1, part_two, 20, 2, 1);
+
+    #[test]
+    fn sea_monsters() {
+        let tiles = parse_input(include_str!("../../../../example/2020/day20-01-01.txt"));
+        let mosaic = assemble(&tiles).unwrap();
+        assert_eq!(mosaic.side, 3);
+        let mut corners = mosaic.corners();
+        corners.sort_unstable();
+        assert_eq!(corners, [1171, 1951, 2971, 3079]);
+
+        let image = mosaic.image();
+        assert_eq!(image.len(), 24);
+        let found = search(&image, &Pattern::parse(SEA_MONSTER)).unwrap();
+        assert_eq!(found.positions.len(), 2);
+        assert_eq!(found.covered, 30);
+    }
 }
@@ -1,45 +1,29 @@
-fn find_dest(cups: &[usize], cur: usize, ignore: &[usize]) -> usize {
+use aocutil::Ring;
+
+/// The label of the destination cup, skipping the cups which were picked up.
+fn find_dest(len: usize, cur: usize, picked: &[usize]) -> usize {
     let mut d = cur;
     loop {
-        d = if d > 1 { d - 1 } else { cups.len() - 1 };
-        if !ignore.contains(&d) {
+        d = if d > 0 { d - 1 } else { len - 1 };
+        if !picked.contains(&d) {
             return d;
         }
     }
 }
 
-fn play_game(input: &str, moves: usize, pad: usize) -> Vec<usize> {
-    // Create a linked list with each element pointing to the next cup label (i.e. [2, 0, 1]
-    // indicates the first cup links to the third cup, the third cup links to the second cup, and
-    // the second cups links to the first).
-    //
-    // This abuses the fact that the cups are sequential (albeit 1-indexed) and thus can be
-    // represented in contiguous memory.
-    let (mut cups, mut c): (Vec<usize>, usize) = {
-        let mut v = vec![0; pad + 1];
-        let mut cups: Vec<usize> = parse_input(input).chain(10..=pad).collect();
-        cups.push(cups[0]);
-        for (&cur, &next) in cups.iter().zip(cups.iter().skip(1)) {
-            v[cur] = next;
-        }
-        (v, cups[0])
-    };
+/// Play the game with the cups of the input followed by the rest of the cups up to `pad`. Cups
+/// are labelled from zero in the ring.
+fn play_game(input: &str, moves: usize, pad: usize) -> Ring {
+    let labels: Vec<usize> = parse_input(input).chain(10..=pad).map(|c| c - 1).collect();
+    let mut cups = Ring::from_order(labels.iter().copied(), labels.len());
+    let mut c = labels[0];
 
     for _ in 1..=moves {
-        let next = |n| cups[n];
-        let r = [next(c), next(next(c)), next(next(next(c)))];
-        // Remove the cups
-        //    /-r[0]->-r[1]->-r[2]-\
-        // c ------------------------> X
-        cups[c] = cups[r[2]];
-        let d = find_dest(&cups, c, &r);
-        // Add the cups back in at destination
-        //     /-r[0]->-r[1]->-r[2]-\
-        // d -xxxxxxxxxxxxxxxxxxxxxxxx-> X
-        cups[r[2]] = cups[d];
-        cups[d] = r[0];
-        // Select the new cup
-        c = cups[c];
+        let picked = cups.splice_out(c, 3);
+        let r = [picked.first, cups.next(picked.first), picked.last];
+        let d = find_dest(cups.capacity(), c, &r);
+        cups.splice_in(d, picked);
+        c = cups.next(c);
     }
 
     cups
@@ -53,23 +37,19 @@ fn parse_input<'a>(input: &'a str) -> impl Iterator<Item = usize> + 'a {
 }
 
 fn part_one(input: &str) -> String {
-    let cups = play_game(input, 100, input.chars().count() - 1);
-
-    let mut output: Vec<String> = Vec::new();
-    let mut n = cups[1];
-    while n != 1 {
-        output.push(format!("{}", n));
-        n = cups[n];
-    }
+    let cups = play_game(input, 100, input.trim().chars().count());
 
-    output.join("")
+    cups.iter_from(0)
+        .skip(1)
+        .map(|c| (c + 1).to_string())
+        .collect()
 }
 
 fn part_two(input: &str) -> String {
     let cups = play_game(input, 10_000_000, 1_000_000);
 
-    let a = cups[1];
-    let b = cups[a];
+    let a = cups.next(0) + 1;
+    let b = cups.next(a - 1) + 1;
     (a * b).to_string()
 }
 
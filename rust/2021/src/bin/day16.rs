@@ -1,153 +1,13 @@
+use anyhow::{anyhow, Result};
+use aocutil::bits::{self, DecodeError, Packet, PacketPayload};
 use std::collections::VecDeque;
 
-type Bit = u8;
-
-#[derive(Debug, PartialEq)]
-struct Packet {
-    version: u64,
-    kind: u64,
-    payload: PacketPayload,
-}
-
-impl Packet {
-    fn value(&self) -> u64 {
-        match &self.payload {
-            PacketPayload::Literal(v) => *v,
-            PacketPayload::Operation(ps) => {
-                let mut vs = ps.iter().map(|p| p.value());
-                match self.kind {
-                    0 => vs.sum(),
-                    1 => vs.product(),
-                    2 => vs.min().unwrap(),
-                    3 => vs.max().unwrap(),
-                    5 => (vs.next().unwrap() > vs.next().unwrap()) as u64,
-                    6 => (vs.next().unwrap() < vs.next().unwrap()) as u64,
-                    7 => (vs.next().unwrap() == vs.next().unwrap()) as u64,
-                    _ => panic!("invalid operation kind {}", self.kind),
-                }
-            }
-        }
-    }
-}
-
-#[derive(Debug, PartialEq)]
-enum PacketPayload {
-    Literal(u64),
-    Operation(Vec<Packet>),
-}
-
-impl From<&str> for Packet {
-    fn from(hex: &str) -> Packet {
-        Packet::from(&parse_bitvec(hex))
-    }
-}
-
-impl From<&Vec<Bit>> for Packet {
-    fn from(bits: &Vec<Bit>) -> Packet {
-        read_packet(bits).0
-    }
+fn parse_input(s: &str) -> Result<Packet, DecodeError> {
+    bits::decode(s)
 }
 
-fn read_packet(bits: &[Bit]) -> (Packet, usize) {
-    let version = load(&bits[0..3]);
-    let kind = load(&bits[3..6]);
-    let mut bits_read = 6;
-
-    let payload = if kind == 4 {
-        let (payload, read) = parse_literal(&bits[6..]);
-        bits_read += read;
-        payload
-    } else if bits[bits_read] == 0 {
-        bits_read += 1;
-        let length = load(&bits[bits_read..bits_read + 15]) as usize;
-        bits_read += 15;
-
-        let end = bits_read + length;
-
-        let mut sub_packets = vec![];
-        while bits_read < end {
-            let (p, r) = read_packet(&bits[bits_read..]);
-            bits_read += r;
-            sub_packets.push(p);
-        }
-
-        PacketPayload::Operation(sub_packets)
-    } else {
-        bits_read += 1;
-        let count = load(&bits[bits_read..bits_read + 11]) as usize;
-        bits_read += 11;
-
-        let mut sub_packets = Vec::with_capacity(count);
-        for _ in 0..count {
-            let (p, r) = read_packet(&bits[bits_read..]);
-            bits_read += r;
-            sub_packets.push(p);
-        }
-
-        PacketPayload::Operation(sub_packets)
-    };
-
-    (
-        Packet {
-            version,
-            kind,
-            payload,
-        },
-        bits_read,
-    )
-}
-
-fn parse_literal(bits: &[Bit]) -> (PacketPayload, usize) {
-    let mut offset = 0;
-    let mut value = 0;
-
-    loop {
-        value <<= 4;
-        value += load(&bits[offset + 1..offset + 5]);
-        offset += 5;
-
-        if bits[offset - 5] == 0 {
-            break;
-        }
-    }
-    (PacketPayload::Literal(value), offset)
-}
-
-fn load(bits: &[Bit]) -> u64 {
-    bits.iter().fold(0, |x, &b| b as u64 + (x << 1))
-}
-
-fn parse_bitvec(hex: &str) -> Vec<Bit> {
-    hex.trim().chars().fold(Default::default(), |mut v, c| {
-        match c {
-            '0' => v.extend([0, 0, 0, 0]),
-            '1' => v.extend([0, 0, 0, 1]),
-            '2' => v.extend([0, 0, 1, 0]),
-            '3' => v.extend([0, 0, 1, 1]),
-            '4' => v.extend([0, 1, 0, 0]),
-            '5' => v.extend([0, 1, 0, 1]),
-            '6' => v.extend([0, 1, 1, 0]),
-            '7' => v.extend([0, 1, 1, 1]),
-            '8' => v.extend([1, 0, 0, 0]),
-            '9' => v.extend([1, 0, 0, 1]),
-            'A' => v.extend([1, 0, 1, 0]),
-            'B' => v.extend([1, 0, 1, 1]),
-            'C' => v.extend([1, 1, 0, 0]),
-            'D' => v.extend([1, 1, 0, 1]),
-            'E' => v.extend([1, 1, 1, 0]),
-            'F' => v.extend([1, 1, 1, 1]),
-            _ => panic!("'{}' is not hex", c),
-        };
-        v
-    })
-}
-
-fn parse_input(s: &str) -> Packet {
-    s.into()
-}
-
-fn part_one(s: &str) -> String {
-    let input = parse_input(s);
+fn part_one(s: &str) -> Result<String> {
+    let input = parse_input(s)?;
     let mut queue = VecDeque::new();
     queue.push_front(input);
 
@@ -160,112 +20,61 @@ fn part_one(s: &str) -> String {
         }
     }
 
-    format!("{}", output)
+    Ok(format!("{}", output))
 }
 
-fn part_two(s: &str) -> String {
-    let input = parse_input(s);
+fn part_two(s: &str) -> Result<String> {
+    let input = parse_input(s)?;
 
-    let output = input.value();
+    let output = input
+        .value()
+        .ok_or_else(|| anyhow!("the transmission overflows"))?;
 
-    format!("{}", output)
+    Ok(format!("{}", output))
 }
 
-fn main() {
+fn main() -> Result<()> {
     let input = include_str!("../../../../input/2021/day16.txt");
-    println!("Part one: {}", part_one(input));
-    println!("Part two: {}", part_two(input));
+    println!("Part one: {}", part_one(input)?);
+    println!("Part two: {}", part_two(input)?);
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod test_day16 {
     use super::*;
+    use aocutil::bits::{decode, encode, LengthType};
     use aocutil::test_example;
 
-    test_example!(example_16_1_1, part_one, 16, 1, 1);
-    test_example!(example_16_1_2, part_one, 16, 1, 2);
-    test_example!(example_16_1_3, part_one, 16, 1, 3);
-    test_example!(example_16_1_4, part_one, 16, 1, 4);
-    test_example!(example_16_2_1, part_two, 16, 2, 1);
-    test_example!(example_16_2_2, part_two, 16, 2, 2);
-    test_example!(example_16_2_3, part_two, 16, 2, 3);
-    test_example!(example_16_2_4, part_two, 16, 2, 4);
-    test_example!(example_16_2_5, part_two, 16, 2, 5);
-    test_example!(example_16_2_6, part_two, 16, 2, 6);
-    test_example!(example_16_2_7, part_two, 16, 2, 7);
-    test_example!(example_16_2_8, part_two, 16, 2, 8);
-
-    #[test]
-    fn parse_bitvec_from_hex() {
-        assert_eq!(
-            parse_bitvec("D2FE28"),
-            vec![1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 1, 0, 1, 0, 0, 0]
-        );
+    fn solve_one(s: &str) -> String {
+        part_one(s).unwrap()
     }
 
-    #[test]
-    fn parse_packet_literal() {
-        assert_eq!(
-            Packet::from("D2FE28"),
-            Packet {
-                version: 6,
-                kind: 4,
-                payload: PacketPayload::Literal(2021),
-            },
-        );
+    fn solve_two(s: &str) -> String {
+        part_two(s).unwrap()
     }
 
-    #[test]
-    fn parse_packet() {
-        assert_eq!(
-            read_packet(&parse_bitvec("38006F45291200")),
-            (
-                Packet {
-                    version: 1,
-                    kind: 6,
-                    payload: PacketPayload::Operation(vec![
-                        Packet {
-                            version: 6,
-                            kind: 4,
-                            payload: PacketPayload::Literal(10),
-                        },
-                        Packet {
-                            version: 2,
-                            kind: 4,
-                            payload: PacketPayload::Literal(20),
-                        }
-                    ]),
-                },
-                49
-            )
-        );
+    test_example!(example_16_1_1, solve_one, 16, 1, 1);
+    test_example!(example_16_1_2, solve_one, 16, 1, 2);
+    test_example!(example_16_1_3, solve_one, 16, 1, 3);
+    test_example!(example_16_1_4, solve_one, 16, 1, 4);
+    test_example!(example_16_2_1, solve_two, 16, 2, 1);
+    test_example!(example_16_2_2, solve_two, 16, 2, 2);
+    test_example!(example_16_2_3, solve_two, 16, 2, 3);
+    test_example!(example_16_2_4, solve_two, 16, 2, 4);
+    test_example!(example_16_2_5, solve_two, 16, 2, 5);
+    test_example!(example_16_2_6, solve_two, 16, 2, 6);
+    test_example!(example_16_2_7, solve_two, 16, 2, 7);
+    test_example!(example_16_2_8, solve_two, 16, 2, 8);
 
-        assert_eq!(
-            read_packet(&parse_bitvec("EE00D40C823060")),
-            (
-                Packet {
-                    version: 7,
-                    kind: 3,
-                    payload: PacketPayload::Operation(vec![
-                        Packet {
-                            version: 2,
-                            kind: 4,
-                            payload: PacketPayload::Literal(1),
-                        },
-                        Packet {
-                            version: 4,
-                            kind: 4,
-                            payload: PacketPayload::Literal(2),
-                        },
-                        Packet {
-                            version: 1,
-                            kind: 4,
-                            payload: PacketPayload::Literal(3),
-                        }
-                    ]),
-                },
-                51
-            )
-        );
+    #[test]
+    fn encode_roundtrip() {
+        let input = include_str!("../../../../input/2021/day16.txt");
+        let packet = decode(input).unwrap();
+        for &length_type in &[LengthType::Bits, LengthType::Count] {
+            let hex = encode(&packet, length_type).unwrap();
+            assert_eq!(decode(&hex), Ok(packet.clone()));
+        }
     }
 }
@@ -1,44 +1,38 @@
 use anyhow::Result;
+use aocutil::vm::{Effect, Instruction, InstructionSet, Machine};
 use itertools::Itertools;
 
 const LIT: char = '#';
 const DARK: char = '.';
 
-enum Instr {
-    Noop,
-    AddX(i64),
+fn cpu() -> InstructionSet {
+    InstructionSet::new()
+        .op("noop", 0, 1, |_, _| Effect::Next)
+        .op("addx", 1, 2, |r, a| {
+            r.add("x", r.value(&a[0]));
+            Effect::Next
+        })
 }
 
-fn process<I: Iterator<Item = Instr>>(instructions: I) -> Vec<i64> {
-    instructions.fold(vec![1], |mut cycles, instr| {
-        let x = cycles[cycles.len() - 1];
+/// The value of the X register during each cycle.
+fn process(program: Vec<Instruction>) -> Result<Vec<i64>> {
+    let set = cpu();
+    let mut machine = Machine::new(&set, program)?;
+    machine.registers.set("x", 1);
 
-        match instr {
-            Instr::Noop => {
-                cycles.push(x);
-            }
-            Instr::AddX(n) => {
-                cycles.push(x);
-                cycles.push(x + n);
-            }
-        }
+    let mut cycles = vec![];
+    machine.run_with(|_, r| cycles.push(r.get("x")));
 
-        cycles
-    })
+    Ok(cycles)
 }
 
-fn parse_input(s: &str) -> Result<impl Iterator<Item = Instr> + '_> {
-    Ok(s.lines().flat_map(|l| -> Result<Instr> {
-        Ok(match l.split_once(' ') {
-            None => Instr::Noop,
-            Some((_, n)) => Instr::AddX(n.parse()?),
-        })
-    }))
+fn parse_input(s: &str) -> Result<Vec<Instruction>> {
+    Ok(cpu().parse(s)?)
 }
 
 fn part_one(s: &str) -> String {
     let input = parse_input(s).unwrap();
-    let cycles = process(input);
+    let cycles = process(input).unwrap();
 
     std::iter::once((19, &cycles[19]))
         .chain(cycles.iter().enumerate().skip(59).step_by(40))
@@ -49,7 +43,7 @@ fn part_one(s: &str) -> String {
 
 fn part_two(s: &str) -> String {
     let input = parse_input(s).unwrap();
-    let cycles = process(input);
+    let cycles = process(input).unwrap();
     let width: i64 = 40;
 
     cycles
@@ -0,0 +1,670 @@
+use std::fmt;
+
+pub type Bit = u8;
+
+/// Packet type of literal values. All other types are operators.
+pub const LITERAL: u64 = 4;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Packet {
+    pub version: u64,
+    pub kind: u64,
+    pub payload: PacketPayload,
+}
+
+/// Smallest and largest number of sub-packets taken by operators of type `kind`, or `None` if
+/// `kind` is not an operator.
+fn arity(kind: u64) -> Option<(usize, usize)> {
+    match kind {
+        0..=3 => Some((1, usize::MAX)),
+        5..=7 => Some((2, 2)),
+        _ => None,
+    }
+}
+
+impl Packet {
+    /// Evaluate the expression. This is `None` if it overflows, or if an operator has an unknown
+    /// type or the wrong number of sub-packets, which `decode` rejects.
+    pub fn value(&self) -> Option<u64> {
+        let ps = match &self.payload {
+            PacketPayload::Literal(v) => return Some(*v),
+            PacketPayload::Operation(ps) => ps,
+        };
+        let (min, max) = arity(self.kind)?;
+        if ps.len() < min || ps.len() > max {
+            return None;
+        }
+
+        let vs = ps.iter().map(|p| p.value()).collect::<Option<Vec<_>>>()?;
+        match self.kind {
+            0 => vs.iter().try_fold(0u64, |acc, &v| acc.checked_add(v)),
+            1 => vs.iter().try_fold(1u64, |acc, &v| acc.checked_mul(v)),
+            2 => vs.iter().copied().min(),
+            3 => vs.iter().copied().max(),
+            5 => Some((vs[0] > vs[1]) as u64),
+            6 => Some((vs[0] < vs[1]) as u64),
+            _ => Some((vs[0] == vs[1]) as u64),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PacketPayload {
+    Literal(u64),
+    Operation(Vec<Packet>),
+}
+
+/// How an operator packet declares the extent of its sub-packets.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LengthType {
+    /// Total number of bits, in 15 bits.
+    Bits,
+    /// Number of sub-packets, in 11 bits.
+    Count,
+}
+
+/// Errors raised while decoding. Offsets are in bits from the start of the transmission.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DecodeError {
+    /// `index` counts characters from the start of the untrimmed input.
+    InvalidHex {
+        index: usize,
+        found: char,
+    },
+    UnexpectedEnd {
+        offset: usize,
+        wanted: usize,
+    },
+    LiteralOverflow {
+        offset: usize,
+    },
+    LengthMismatch {
+        offset: usize,
+        expected: usize,
+        actual: usize,
+    },
+    TrailingData {
+        offset: usize,
+    },
+    Arity {
+        offset: usize,
+        kind: u64,
+        count: usize,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidHex { index, found } => {
+                write!(f, "'{}' at index {} is not hex", found, index)
+            }
+            DecodeError::UnexpectedEnd { offset, wanted } => {
+                write!(f, "expected {} more bits at bit {}", wanted, offset)
+            }
+            DecodeError::LiteralOverflow { offset } => {
+                write!(f, "literal at bit {} does not fit in 64 bits", offset)
+            }
+            DecodeError::LengthMismatch {
+                offset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "sub-packets of operator at bit {} take {} bits instead of {}",
+                offset, actual, expected
+            ),
+            DecodeError::TrailingData { offset } => {
+                write!(f, "unexpected data after the packet at bit {}", offset)
+            }
+            DecodeError::Arity {
+                offset,
+                kind,
+                count,
+            } => write!(
+                f,
+                "operator of type {} at bit {} has {} sub-packets",
+                kind, offset, count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum EncodeError {
+    FieldTooLarge {
+        field: &'static str,
+        value: u64,
+        bits: usize,
+    },
+    KindMismatch {
+        kind: u64,
+    },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::FieldTooLarge { field, value, bits } => {
+                write!(f, "{} {} does not fit in {} bits", field, value, bits)
+            }
+            EncodeError::KindMismatch { kind } => {
+                write!(f, "payload does not match packet type {}", kind)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+pub fn parse_bitvec(hex: &str) -> Result<Vec<Bit>, DecodeError> {
+    let mut bits = Vec::with_capacity(hex.len() * 4);
+    let skipped = hex.chars().take_while(|c| c.is_whitespace()).count();
+    for (index, c) in hex.trim().chars().enumerate() {
+        let nibble = c.to_digit(16).ok_or(DecodeError::InvalidHex {
+            index: skipped + index,
+            found: c,
+        })?;
+        bits.extend((0..4).rev().map(|i| (nibble >> i & 1) as Bit));
+    }
+
+    Ok(bits)
+}
+
+struct Reader<'a> {
+    bits: &'a [Bit],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, n: usize) -> Result<u64, DecodeError> {
+        let bits =
+            self.bits
+                .get(self.offset..self.offset + n)
+                .ok_or(DecodeError::UnexpectedEnd {
+                    offset: self.offset,
+                    wanted: n - (self.bits.len() - self.offset).min(n),
+                })?;
+        self.offset += n;
+
+        Ok(bits.iter().fold(0, |x, &b| b as u64 + (x << 1)))
+    }
+
+    fn packet(&mut self) -> Result<Packet, DecodeError> {
+        let start = self.offset;
+        let version = self.read(3)?;
+        let kind = self.read(3)?;
+
+        if kind == LITERAL {
+            return Ok(Packet {
+                version,
+                kind,
+                payload: PacketPayload::Literal(self.literal()?),
+            });
+        }
+
+        let sub_packets = if self.read(1)? == 0 {
+            let length = self.read(15)? as usize;
+            let end = self.offset + length;
+
+            let mut sub_packets = vec![];
+            while self.offset < end {
+                sub_packets.push(self.packet()?);
+            }
+            if self.offset != end {
+                return Err(DecodeError::LengthMismatch {
+                    offset: start,
+                    expected: length,
+                    actual: length + self.offset - end,
+                });
+            }
+
+            sub_packets
+        } else {
+            let count = self.read(11)? as usize;
+            (0..count)
+                .map(|_| self.packet())
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        match arity(kind) {
+            Some((min, max)) if (min..=max).contains(&sub_packets.len()) => Ok(Packet {
+                version,
+                kind,
+                payload: PacketPayload::Operation(sub_packets),
+            }),
+            _ => Err(DecodeError::Arity {
+                offset: start,
+                kind,
+                count: sub_packets.len(),
+            }),
+        }
+    }
+
+    fn literal(&mut self) -> Result<u64, DecodeError> {
+        let start = self.offset;
+        let mut value: u64 = 0;
+
+        loop {
+            let more = self.read(1)? == 1;
+            if value >> 60 != 0 {
+                return Err(DecodeError::LiteralOverflow { offset: start });
+            }
+            value = value << 4 | self.read(4)?;
+
+            if !more {
+                return Ok(value);
+            }
+        }
+    }
+}
+
+/// Decode the packet at the start of `bits`, returning it with the number of bits read.
+pub fn read_packet(bits: &[Bit]) -> Result<(Packet, usize), DecodeError> {
+    let mut reader = Reader { bits, offset: 0 };
+    let packet = reader.packet()?;
+
+    Ok((packet, reader.offset))
+}
+
+/// Decode a hex transmission consisting of a single packet followed by zero padding.
+pub fn decode(hex: &str) -> Result<Packet, DecodeError> {
+    let bits = parse_bitvec(hex)?;
+    let (packet, read) = read_packet(&bits)?;
+    if let Some(offset) = bits[read..].iter().position(|&b| b != 0) {
+        return Err(DecodeError::TrailingData {
+            offset: read + offset,
+        });
+    }
+
+    Ok(packet)
+}
+
+fn write(
+    bits: &mut Vec<Bit>,
+    field: &'static str,
+    value: u64,
+    n: usize,
+) -> Result<(), EncodeError> {
+    if value >> n != 0 {
+        return Err(EncodeError::FieldTooLarge {
+            field,
+            value,
+            bits: n,
+        });
+    }
+    bits.extend((0..n).rev().map(|i| (value >> i & 1) as Bit));
+
+    Ok(())
+}
+
+fn write_packet(
+    bits: &mut Vec<Bit>,
+    packet: &Packet,
+    length_type: LengthType,
+) -> Result<(), EncodeError> {
+    write(bits, "version", packet.version, 3)?;
+    write(bits, "type", packet.kind, 3)?;
+
+    match (&packet.payload, packet.kind == LITERAL) {
+        (PacketPayload::Literal(v), true) => {
+            let groups = (1..16).rev().find(|i| v >> (4 * i) != 0).unwrap_or(0);
+            for i in (0..=groups).rev() {
+                bits.push((i > 0) as Bit);
+                write(bits, "literal", v >> (4 * i) & 0xF, 4)?;
+            }
+        }
+        (PacketPayload::Operation(ps), false) => {
+            let mut sub_packets = vec![];
+            for p in ps {
+                write_packet(&mut sub_packets, p, length_type)?;
+            }
+            match length_type {
+                LengthType::Bits => {
+                    bits.push(0);
+                    write(bits, "length", sub_packets.len() as u64, 15)?;
+                }
+                LengthType::Count => {
+                    bits.push(1);
+                    write(bits, "count", ps.len() as u64, 11)?;
+                }
+            }
+            bits.extend(sub_packets);
+        }
+        _ => return Err(EncodeError::KindMismatch { kind: packet.kind }),
+    }
+
+    Ok(())
+}
+
+/// Encode a packet as hex, padded with zeros to whole bytes. Every operator uses
+/// `length_type`.
+pub fn encode(packet: &Packet, length_type: LengthType) -> Result<String, EncodeError> {
+    let mut bits = vec![];
+    write_packet(&mut bits, packet, length_type)?;
+    bits.resize(bits.len().div_ceil(8) * 8, 0);
+
+    Ok(bits
+        .chunks(4)
+        .map(|c| {
+            let nibble = c.iter().fold(0, |x, &b| b as u32 + (x << 1));
+            std::char::from_digit(nibble, 16)
+                .unwrap()
+                .to_ascii_uppercase()
+        })
+        .collect())
+}
+
+fn operator(kind: u64) -> &'static str {
+    match kind {
+        0 => "+",
+        1 => "*",
+        2 => "min",
+        3 => "max",
+        5 => ">",
+        6 => "<",
+        7 => "==",
+        _ => "?",
+    }
+}
+
+/// Render the packet as an S-expression, e.g. `(+ 1 (* 2 3))`.
+pub fn to_sexpr(packet: &Packet) -> String {
+    match &packet.payload {
+        PacketPayload::Literal(v) => v.to_string(),
+        PacketPayload::Operation(ps) => {
+            let mut s = format!("({}", operator(packet.kind));
+            for p in ps {
+                s.push(' ');
+                s.push_str(&to_sexpr(p));
+            }
+            s.push(')');
+            s
+        }
+    }
+}
+
+/// Render the packet in infix notation, e.g. `1 + 2 * 3`, returning its precedence.
+fn infix(packet: &Packet) -> (String, u8) {
+    let ps = match &packet.payload {
+        PacketPayload::Literal(v) => return (v.to_string(), 3),
+        PacketPayload::Operation(ps) => ps,
+    };
+
+    let prec = match packet.kind {
+        0 => 1,
+        1 => 2,
+        2 | 3 => {
+            let args: Vec<_> = ps.iter().map(|p| infix(p).0).collect();
+            return (format!("{}({})", operator(packet.kind), args.join(", ")), 3);
+        }
+        _ => 0,
+    };
+    if ps.len() == 1 {
+        return infix(&ps[0]);
+    }
+
+    let operands: Vec<_> = ps
+        .iter()
+        .map(|p| match infix(p) {
+            // Comparisons do not chain, so nested comparisons always need parentheses
+            (s, p) if p < prec || (p == 0 && prec == 0) => format!("({})", s),
+            (s, _) => s,
+        })
+        .collect();
+
+    (operands.join(&format!(" {} ", operator(packet.kind))), prec)
+}
+
+pub fn to_infix(packet: &Packet) -> String {
+    infix(packet).0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_bitvec_from_hex() {
+        assert_eq!(
+            parse_bitvec("D2FE28"),
+            Ok(vec![
+                1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 1, 0, 1, 0, 0, 0
+            ])
+        );
+        assert_eq!(parse_bitvec("d2fe28\n"), parse_bitvec("D2FE28"));
+    }
+
+    #[test]
+    fn parse_packet_literal() {
+        assert_eq!(
+            decode("D2FE28"),
+            Ok(Packet {
+                version: 6,
+                kind: 4,
+                payload: PacketPayload::Literal(2021),
+            }),
+        );
+    }
+
+    #[test]
+    fn parse_packet() {
+        assert_eq!(
+            read_packet(&parse_bitvec("38006F45291200").unwrap()),
+            Ok((
+                Packet {
+                    version: 1,
+                    kind: 6,
+                    payload: PacketPayload::Operation(vec![
+                        Packet {
+                            version: 6,
+                            kind: 4,
+                            payload: PacketPayload::Literal(10),
+                        },
+                        Packet {
+                            version: 2,
+                            kind: 4,
+                            payload: PacketPayload::Literal(20),
+                        }
+                    ]),
+                },
+                49
+            ))
+        );
+
+        assert_eq!(
+            read_packet(&parse_bitvec("EE00D40C823060").unwrap()),
+            Ok((
+                Packet {
+                    version: 7,
+                    kind: 3,
+                    payload: PacketPayload::Operation(vec![
+                        Packet {
+                            version: 2,
+                            kind: 4,
+                            payload: PacketPayload::Literal(1),
+                        },
+                        Packet {
+                            version: 4,
+                            kind: 4,
+                            payload: PacketPayload::Literal(2),
+                        },
+                        Packet {
+                            version: 1,
+                            kind: 4,
+                            payload: PacketPayload::Literal(3),
+                        }
+                    ]),
+                },
+                51
+            ))
+        );
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(
+            decode("D2FX28"),
+            Err(DecodeError::InvalidHex {
+                index: 3,
+                found: 'X'
+            })
+        );
+        assert_eq!(
+            decode("\n  D2FX28"),
+            Err(DecodeError::InvalidHex {
+                index: 6,
+                found: 'X'
+            })
+        );
+        // Literal cut off after its first group
+        assert_eq!(
+            decode("D2F"),
+            Err(DecodeError::UnexpectedEnd {
+                offset: 12,
+                wanted: 4
+            })
+        );
+        // Sub-packets overrun the declared length of 27 bits by one bit
+        assert_eq!(
+            decode("38006B45291200"),
+            Err(DecodeError::LengthMismatch {
+                offset: 0,
+                expected: 26,
+                actual: 27
+            })
+        );
+        assert_eq!(
+            decode("D2FE29"),
+            Err(DecodeError::TrailingData { offset: 23 })
+        );
+        assert_eq!(
+            decode("13FFFFFFFFFFFFFFFFFFFFEF"),
+            Err(DecodeError::LiteralOverflow { offset: 6 })
+        );
+        // Minimum of no sub-packets
+        assert_eq!(
+            decode("0A000"),
+            Err(DecodeError::Arity {
+                offset: 0,
+                kind: 2,
+                count: 0
+            })
+        );
+        let hex = encode(&op(5, vec![lit(1)]), LengthType::Count).unwrap();
+        assert_eq!(
+            decode(&hex),
+            Err(DecodeError::Arity {
+                offset: 0,
+                kind: 5,
+                count: 1
+            })
+        );
+    }
+
+    #[test]
+    fn encode_packet() {
+        let packet = decode("38006F45291200").unwrap();
+        assert_eq!(
+            encode(&packet, LengthType::Bits),
+            Ok("38006F45291200".to_string())
+        );
+        let packet = decode("EE00D40C823060").unwrap();
+        assert_eq!(
+            encode(&packet, LengthType::Count),
+            Ok("EE00D40C823060".to_string())
+        );
+        assert_eq!(
+            encode(&decode("D2FE28").unwrap(), LengthType::Bits),
+            Ok("D2FE28".to_string())
+        );
+
+        let invalid = Packet {
+            version: 8,
+            kind: 4,
+            payload: PacketPayload::Literal(0),
+        };
+        assert_eq!(
+            encode(&invalid, LengthType::Bits),
+            Err(EncodeError::FieldTooLarge {
+                field: "version",
+                value: 8,
+                bits: 3
+            })
+        );
+        let invalid = Packet {
+            version: 0,
+            kind: 0,
+            payload: PacketPayload::Literal(0),
+        };
+        assert_eq!(
+            encode(&invalid, LengthType::Bits),
+            Err(EncodeError::KindMismatch { kind: 0 })
+        );
+    }
+
+    #[test]
+    fn encode_roundtrip() {
+        let literal = Packet {
+            version: 5,
+            kind: 4,
+            payload: PacketPayload::Literal(u64::MAX),
+        };
+        let hex = encode(&literal, LengthType::Bits).unwrap();
+        assert_eq!(decode(&hex), Ok(literal));
+    }
+
+    fn op(kind: u64, ps: Vec<Packet>) -> Packet {
+        Packet {
+            version: 0,
+            kind,
+            payload: PacketPayload::Operation(ps),
+        }
+    }
+
+    fn lit(v: u64) -> Packet {
+        Packet {
+            version: 0,
+            kind: LITERAL,
+            payload: PacketPayload::Literal(v),
+        }
+    }
+
+    #[test]
+    fn pretty_print() {
+        let packet = decode("9C0141080250320F1802104A08").unwrap();
+        assert_eq!(to_sexpr(&packet), "(== (+ 1 3) (* 2 2))");
+        assert_eq!(to_infix(&packet), "1 + 3 == 2 * 2");
+
+        let packet = op(
+            1,
+            vec![
+                op(0, vec![lit(1), lit(2)]),
+                op(2, vec![lit(3), op(1, vec![lit(4), lit(5)])]),
+                op(5, vec![op(6, vec![lit(1), lit(2)]), lit(0)]),
+            ],
+        );
+        assert_eq!(
+            to_sexpr(&packet),
+            "(* (+ 1 2) (min 3 (* 4 5)) (> (< 1 2) 0))"
+        );
+        assert_eq!(to_infix(&packet), "(1 + 2) * min(3, 4 * 5) * ((1 < 2) > 0)");
+    }
+
+    #[test]
+    fn value() {
+        assert_eq!(
+            decode("9C0141080250320F1802104A08").unwrap().value(),
+            Some(1)
+        );
+        assert_eq!(op(3, vec![lit(1), lit(7), lit(2)]).value(), Some(7));
+        assert_eq!(op(7, vec![lit(1), op(2, vec![lit(1)])]).value(), Some(1));
+
+        assert_eq!(op(2, vec![]).value(), None);
+        assert_eq!(op(5, vec![lit(1), lit(2), lit(3)]).value(), None);
+        assert_eq!(op(LITERAL, vec![lit(1)]).value(), None);
+        assert_eq!(op(1, vec![lit(u64::MAX), lit(2)]).value(), None);
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt::Display;
+
+/// Constraints on a compressed sequence. Lengths are measured in characters when tokens are
+/// written out separated by commas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// Maximum number of sub-routines.
+    pub routines: usize,
+    /// Maximum textual length of each sub-routine.
+    pub routine_len: usize,
+    /// Maximum textual length of the main routine.
+    pub main_len: usize,
+}
+
+impl Limits {
+    pub fn new(routines: usize, len: usize) -> Self {
+        Limits {
+            routines,
+            routine_len: len,
+            main_len: len,
+        }
+    }
+}
+
+/// A sequence encoded as calls to sub-routines. Routines are numbered in order of their first
+/// call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compression<T> {
+    pub main: Vec<usize>,
+    pub routines: Vec<Vec<T>>,
+}
+
+impl<T: Display> Compression<T> {
+    /// Routine names, starting with `A`.
+    pub fn name(idx: usize) -> char {
+        (b'A' + idx as u8) as char
+    }
+
+    pub fn main_text(&self) -> String {
+        join(self.main.iter().map(|&i| Self::name(i)))
+    }
+
+    pub fn routine_text(&self, idx: usize) -> String {
+        join(self.routines[idx].iter())
+    }
+
+    /// The main routine followed by each sub-routine, one per line.
+    pub fn lines(&self) -> Vec<String> {
+        std::iter::once(self.main_text())
+            .chain((0..self.routines.len()).map(|i| self.routine_text(i)))
+            .collect()
+    }
+}
+
+fn join<I: Iterator<Item = D>, D: Display>(items: I) -> String {
+    items.map(|i| i.to_string()).collect::<Vec<_>>().join(",")
+}
+
+struct Search<'a, T> {
+    tokens: &'a [T],
+    widths: Vec<usize>,
+    limits: Limits,
+    main: Vec<usize>,
+    routines: Vec<&'a [T]>,
+    found: Vec<Compression<T>>,
+}
+
+impl<'a, T: PartialEq + Clone> Search<'a, T> {
+    fn visit(&mut self, pos: usize) {
+        if pos == self.tokens.len() {
+            self.found.push(Compression {
+                main: self.main.clone(),
+                routines: self.routines.iter().map(|r| r.to_vec()).collect(),
+            });
+            return;
+        }
+
+        // Each call adds a name and a separator to the main routine
+        if 2 * self.main.len() + 1 > self.limits.main_len {
+            return;
+        }
+
+        for idx in 0..self.routines.len() {
+            let routine = self.routines[idx];
+            if self.tokens[pos..].starts_with(routine) {
+                self.main.push(idx);
+                self.visit(pos + routine.len());
+                self.main.pop();
+            }
+        }
+
+        if self.routines.len() == self.limits.routines {
+            return;
+        }
+
+        let mut len = 0;
+        for end in pos + 1..=self.tokens.len() {
+            len += self.widths[end - 1] + if end > pos + 1 { 1 } else { 0 };
+            if len > self.limits.routine_len {
+                break;
+            }
+
+            let routine = &self.tokens[pos..end];
+            if self.routines.contains(&routine) {
+                continue;
+            }
+
+            self.routines.push(routine);
+            self.main.push(self.routines.len() - 1);
+            self.visit(end);
+            self.main.pop();
+            self.routines.pop();
+        }
+    }
+}
+
+/// Find every way of encoding `tokens` as a main routine calling sub-routines within `limits`.
+pub fn compress<T: PartialEq + Clone + Display>(
+    tokens: &[T],
+    limits: Limits,
+) -> Vec<Compression<T>> {
+    let mut search = Search {
+        tokens,
+        widths: tokens.iter().map(|t| t.to_string().len()).collect(),
+        limits,
+        main: Vec::new(),
+        routines: Vec::new(),
+        found: Vec::new(),
+    };
+    search.visit(0);
+
+    search.found
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tokens(path: &str) -> Vec<String> {
+        let parts: Vec<_> = path.split(',').collect();
+        parts.chunks(2).map(|c| c.join(",")).collect()
+    }
+
+    #[test]
+    fn test_compress_scaffold_path() {
+        let path = tokens("R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2");
+        let found = compress(&path, Limits::new(3, 20));
+        assert!(!found.is_empty());
+
+        for c in &found {
+            assert!(c.routines.len() <= 3);
+            assert!(c.lines().iter().all(|l| l.len() <= 20));
+            let expanded: Vec<_> = c
+                .main
+                .iter()
+                .flat_map(|&i| c.routines[i].iter().cloned())
+                .collect();
+            assert_eq!(path, expanded);
+        }
+
+        // The example from the puzzle
+        let example = Compression {
+            main: vec![0, 1, 2, 1, 0, 2],
+            routines: vec![tokens("R,8,R,8"), tokens("R,4,R,4,R,8"), tokens("L,6,L,2")],
+        };
+        assert!(found.contains(&example));
+        assert_eq!(
+            vec!["A,B,C,B,A,C", "R,8,R,8", "R,4,R,4,R,8", "L,6,L,2"],
+            example.lines()
+        );
+    }
+
+    #[test]
+    fn test_compress_limits() {
+        let path = [1, 2, 3, 1, 2, 3];
+        assert_eq!(
+            vec![Compression {
+                main: vec![0, 0],
+                routines: vec![vec![1, 2, 3]]
+            }],
+            compress(&path, Limits::new(1, 5))
+        );
+        assert!(compress(&path, Limits::new(1, 3)).is_empty());
+        assert!(compress(&[1, 2, 3], Limits::new(2, 1)).is_empty());
+    }
+}
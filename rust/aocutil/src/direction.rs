@@ -20,3 +20,31 @@ impl Into<Vector2D<i64, UnknownUnit>> for Direction {
         }
     }
 }
+
+impl Direction {
+    pub fn turn_left(self) -> Self {
+        use Direction::*;
+
+        match self {
+            Up => Left,
+            Left => Down,
+            Down => Right,
+            Right => Up,
+        }
+    }
+
+    pub fn turn_right(self) -> Self {
+        self.turn_left().reverse()
+    }
+
+    pub fn reverse(self) -> Self {
+        use Direction::*;
+
+        match self {
+            Up => Down,
+            Down => Up,
+            Left => Right,
+            Right => Left,
+        }
+    }
+}
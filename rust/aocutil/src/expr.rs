@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+#[derive(Clone)]
+struct Binary {
+    symbol: String,
+    precedence: u8,
+    assoc: Assoc,
+    apply: fn(i64, i64) -> i64,
+}
+
+#[derive(Clone)]
+struct Unary {
+    symbol: String,
+    precedence: u8,
+    apply: fn(i64) -> i64,
+}
+
+/// The operators understood by a parser. Operators with a higher precedence bind tighter.
+#[derive(Clone, Default)]
+pub struct Operators {
+    binary: Vec<Binary>,
+    unary: Vec<Unary>,
+}
+
+impl Operators {
+    pub fn new() -> Self {
+        Operators::default()
+    }
+
+    pub fn binary(
+        mut self,
+        symbol: &str,
+        precedence: u8,
+        assoc: Assoc,
+        apply: fn(i64, i64) -> i64,
+    ) -> Self {
+        self.binary.push(Binary {
+            symbol: symbol.to_string(),
+            precedence,
+            assoc,
+            apply,
+        });
+        self
+    }
+
+    /// Declare a prefix operator. Its operand extends over binary operators of a higher
+    /// precedence.
+    pub fn unary(mut self, symbol: &str, precedence: u8, apply: fn(i64) -> i64) -> Self {
+        self.unary.push(Unary {
+            symbol: symbol.to_string(),
+            precedence,
+            apply,
+        });
+        self
+    }
+
+    pub fn parse(&self, s: &str) -> Result<Expr, ParseError> {
+        let mut parser = Parser {
+            ops: self,
+            s,
+            pos: 0,
+        };
+        let expr = parser.expr(0)?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(expr),
+            Some(')') => Err(parser.error("unmatched ')'")),
+            Some(_) => Err(parser.error("expected an operator")),
+        }
+    }
+
+    /// Evaluate an expression parsed with these operators, looking up variables in `env`.
+    pub fn eval(&self, expr: &Expr, env: &HashMap<String, i64>) -> Result<i64, EvalError> {
+        Ok(match expr {
+            Expr::Num(n) => *n,
+            Expr::Var(name) => *env
+                .get(name)
+                .ok_or_else(|| EvalError::Unbound(name.clone()))?,
+            Expr::Unary(op, e) => {
+                let op = self.unary.iter().find(|u| &u.symbol == op);
+                let op = op.ok_or_else(|| EvalError::UnknownOperator(expr.to_string()))?;
+                (op.apply)(self.eval(e, env)?)
+            }
+            Expr::Binary(op, a, b) => {
+                let op = self.binary.iter().find(|u| &u.symbol == op);
+                let op = op.ok_or_else(|| EvalError::UnknownOperator(expr.to_string()))?;
+                (op.apply)(self.eval(a, env)?, self.eval(b, env)?)
+            }
+        })
+    }
+
+    /// Parse and evaluate an expression without variables.
+    pub fn evaluate(&self, s: &str) -> Result<i64, ExprError> {
+        let expr = self.parse(s)?;
+        Ok(self.eval(&expr, &HashMap::new())?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Num(i64),
+    Var(String),
+    Unary(String, Box<Expr>),
+    Binary(String, Box<Expr>, Box<Expr>),
+}
+
+/// Writes the expression fully parenthesised, e.g. `((1 + 2) * 3)`.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Num(n) => write!(f, "{}", n),
+            Expr::Var(name) => write!(f, "{}", name),
+            Expr::Unary(op, e) => write!(f, "({}{})", op, e),
+            Expr::Binary(op, a, b) => write!(f, "({} {} {})", a, op, b),
+        }
+    }
+}
+
+/// An error at a character offset of the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+    /// Position of the `(` which was still open when the error occurred, if it was missing its
+    /// `)`.
+    pub unclosed: Option<usize>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)?;
+        if let Some(open) = self.unclosed {
+            write!(f, " to close '(' from position {}", open)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    Unbound(String),
+    UnknownOperator(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Unbound(name) => write!(f, "unbound variable {}", name),
+            EvalError::UnknownOperator(e) => write!(f, "unknown operator in {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// Error from parsing and then evaluating an expression in one go.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprError {
+    Parse(ParseError),
+    Eval(EvalError),
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::Parse(e) => e.fmt(f),
+            ExprError::Eval(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ExprError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExprError::Parse(e) => Some(e),
+            ExprError::Eval(e) => Some(e),
+        }
+    }
+}
+
+impl From<ParseError> for ExprError {
+    fn from(e: ParseError) -> Self {
+        ExprError::Parse(e)
+    }
+}
+
+impl From<EvalError> for ExprError {
+    fn from(e: EvalError) -> Self {
+        ExprError::Eval(e)
+    }
+}
+
+struct Parser<'a> {
+    ops: &'a Operators,
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    // Precedences are widened so that binding tighter than the highest precedence is possible
+    fn expr(&mut self, min_precedence: u16) -> Result<Expr, ParseError> {
+        let mut lhs = self.operand()?;
+
+        loop {
+            self.skip_whitespace();
+            let op = match self.symbol(self.ops.binary.iter().map(|b| (&b.symbol, b))) {
+                Some(op) if u16::from(op.precedence) >= min_precedence => op,
+                _ => return Ok(lhs),
+            };
+            self.pos += op.symbol.len();
+
+            let next = match op.assoc {
+                Assoc::Left => u16::from(op.precedence) + 1,
+                Assoc::Right => u16::from(op.precedence),
+            };
+            let rhs = self.expr(next)?;
+            lhs = Expr::Binary(op.symbol.clone(), Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn operand(&mut self) -> Result<Expr, ParseError> {
+        self.skip_whitespace();
+        let rest = &self.s[self.pos..];
+        let c = match rest.chars().next() {
+            Some(c) => c,
+            None => return Err(self.error("unexpected end of expression")),
+        };
+
+        if c == '(' {
+            let open = self.position();
+            self.pos += 1;
+            let e = self.expr(0)?;
+            self.skip_whitespace();
+            if self.peek() != Some(')') {
+                return Err(ParseError {
+                    unclosed: Some(open),
+                    ..self.error("expected ')'")
+                });
+            }
+            self.pos += 1;
+            Ok(e)
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let n = rest[..len]
+                .parse()
+                .map_err(|_| self.error("number out of range"))?;
+            self.pos += len;
+            Ok(Expr::Num(n))
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            self.pos += len;
+            Ok(Expr::Var(rest[..len].to_string()))
+        } else if let Some(op) = self.symbol(self.ops.unary.iter().map(|u| (&u.symbol, u))) {
+            self.pos += op.symbol.len();
+            let e = self.expr(u16::from(op.precedence) + 1)?;
+            Ok(Expr::Unary(op.symbol.clone(), Box::new(e)))
+        } else {
+            Err(self.error(&format!("unexpected '{}'", c)))
+        }
+    }
+
+    /// The longest operator symbol at the cursor.
+    fn symbol<'o, T>(&self, ops: impl Iterator<Item = (&'o String, &'o T)>) -> Option<&'o T> {
+        let rest = &self.s[self.pos..];
+        ops.filter(|(s, _)| rest.starts_with(s.as_str()))
+            .max_by_key(|(s, _)| s.len())
+            .map(|(_, op)| op)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.s[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.s[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Character offset of the cursor.
+    fn position(&self) -> usize {
+        self.s[..self.pos].chars().count()
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            position: self.position(),
+            message: message.to_string(),
+            unclosed: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn arithmetic() -> Operators {
+        Operators::new()
+            .binary("+", 1, Assoc::Left, |a, b| a + b)
+            .binary("-", 1, Assoc::Left, |a, b| a - b)
+            .binary("*", 2, Assoc::Left, |a, b| a * b)
+            .binary("/", 2, Assoc::Left, |a, b| a / b)
+            .binary("**", 4, Assoc::Right, |a, b| a.pow(b as u32))
+            .unary("-", 3, |a| -a)
+    }
+
+    #[test]
+    fn test_precedence() {
+        let ops = arithmetic();
+        let cases = [
+            ("1 + 2 * 3", "(1 + (2 * 3))", 7),
+            ("10 - 4 - 3", "((10 - 4) - 3)", 3),
+            ("2 ** 3 ** 2", "(2 ** (3 ** 2))", 512),
+            ("-2 ** 2", "(-(2 ** 2))", -4),
+            ("-2 * 3", "((-2) * 3)", -6),
+            ("(1 + 2) * -(3 - 10)", "((1 + 2) * (-(3 - 10)))", 21),
+            ("123*45", "(123 * 45)", 5535),
+        ];
+        for &(s, tree, value) in &cases {
+            let expr = ops.parse(s).unwrap();
+            assert_eq!(tree, expr.to_string(), "{}", s);
+            assert_eq!(value, ops.evaluate(s).unwrap(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_variables() {
+        let ops = arithmetic();
+        let expr = ops.parse("width * height_2 - x").unwrap();
+        let mut env = HashMap::new();
+        env.insert("width".to_string(), 3);
+        env.insert("height_2".to_string(), 4);
+        assert_eq!(
+            Err(EvalError::Unbound("x".to_string())),
+            ops.eval(&expr, &env)
+        );
+        env.insert("x".to_string(), 2);
+        assert_eq!(Ok(10), ops.eval(&expr, &env));
+    }
+
+    #[test]
+    fn test_errors() {
+        let ops = arithmetic();
+        let error = |s| ops.parse(s).unwrap_err();
+        assert_eq!(
+            ParseError {
+                position: 4,
+                message: "unexpected end of expression".to_string(),
+                unclosed: None,
+            },
+            error("1 + ")
+        );
+        assert_eq!(
+            "expected ')' at position 10 to close '(' from position 4",
+            error("2 * (3 + 4").to_string()
+        );
+        assert_eq!(Some(0), error("((1 + 2)").unclosed);
+        assert_eq!(Some(4), error("é + (1").unclosed);
+        assert_eq!(4, error("1 + % 2").position);
+        assert_eq!("unexpected '%'", error("1 + % 2").message);
+        assert_eq!("unmatched ')'", error("1 + 2)").message);
+        assert_eq!("expected an operator", error("1 2").message);
+        assert_eq!(2, error("1 2").position);
+
+        match ops.evaluate("2 * (3 + 4") {
+            Err(ExprError::Parse(e)) => assert_eq!((10, Some(4)), (e.position, e.unclosed)),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert_eq!(
+            Err(ExprError::Eval(EvalError::Unbound("x".to_string()))),
+            ops.evaluate("1 + x")
+        );
+    }
+
+    #[test]
+    fn test_extreme_precedence() {
+        let ops = Operators::new()
+            .binary("-", u8::MAX, Assoc::Left, |a, b| a - b)
+            .binary("^", u8::MAX, Assoc::Right, |a, b| a.pow(b as u32))
+            .unary("!", u8::MAX, |a| a ^ 1);
+        assert_eq!("((8 - 4) - 2)", ops.parse("8 - 4 - 2").unwrap().to_string());
+        assert_eq!("(2 ^ (3 ^ 2))", ops.parse("2 ^ 3 ^ 2").unwrap().to_string());
+        assert_eq!("((!1) - 1)", ops.parse("!1 - 1").unwrap().to_string());
+    }
+}
@@ -0,0 +1,511 @@
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Rule {
+    Char(char),
+    Seq(Vec<i64>),
+    Alt(Vec<Vec<i64>>),
+}
+
+impl Rule {
+    fn alternatives(&self) -> &[Vec<i64>] {
+        match self {
+            Rule::Char(_) => &[],
+            Rule::Seq(ids) => std::slice::from_ref(ids),
+            Rule::Alt(alts) => alts,
+        }
+    }
+}
+
+fn parse_ids(s: &str) -> Result<Vec<i64>> {
+    s.split_whitespace()
+        .map(|id| {
+            id.parse()
+                .map_err(|err| anyhow!("invalid reference {:?}: {}", id, err))
+        })
+        .collect()
+}
+
+impl std::str::FromStr for Rule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains('|') {
+            let alts = s.split('|').map(parse_ids).collect::<Result<_>>()?;
+
+            Ok(Rule::Alt(alts))
+        } else if s.starts_with('"') {
+            let c = s
+                .chars()
+                .nth(1)
+                .ok_or_else(|| anyhow!("missing character"))?;
+
+            Ok(Rule::Char(c))
+        } else {
+            Ok(Rule::Seq(parse_ids(s)?))
+        }
+    }
+}
+
+/// A derivation of a string. Leaves are the characters matched by `Char` rules.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Tree {
+    Leaf(i64, char),
+    Node(i64, Vec<Tree>),
+}
+
+/// Writes the tree as an S-expression, e.g. `(0 a (1 b a))`.
+impl fmt::Display for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tree::Leaf(_, c) => write!(f, "{}", c),
+            Tree::Node(id, children) => {
+                write!(f, "({}", id)?;
+                for c in children {
+                    write!(f, " {}", c)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// A partially matched alternative of a rule, which started matching at `origin`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Item {
+    rule: i64,
+    alt: usize,
+    dot: usize,
+    origin: usize,
+}
+
+/// The outcome of an Earley parse: which rule alternatives match which spans.
+struct Chart {
+    chars: Vec<char>,
+    complete: HashSet<(i64, usize, usize, usize)>,
+}
+
+/// A context-free grammar without empty alternatives.
+pub struct Grammar {
+    rules: HashMap<i64, Rule>,
+    start: i64,
+}
+
+impl Grammar {
+    pub fn new(rules: HashMap<i64, Rule>, start: i64) -> Result<Self> {
+        if !rules.contains_key(&start) {
+            return Err(anyhow!("missing start rule {}", start));
+        }
+        for (id, rule) in &rules {
+            for alt in rule.alternatives() {
+                if alt.is_empty() {
+                    return Err(anyhow!("rule {} has an empty alternative", id));
+                }
+                if let Some(r) = alt.iter().find(|r| !rules.contains_key(r)) {
+                    return Err(anyhow!("rule {} refers to missing rule {}", id, r));
+                }
+            }
+        }
+
+        Ok(Grammar { rules, start })
+    }
+
+    pub fn matches(&self, s: &str) -> bool {
+        let chart = self.chart(s);
+        self.spans(&chart, self.start, 0, chart.chars.len())
+    }
+
+    /// A derivation of `s` from the start rule, if there is one.
+    pub fn parse(&self, s: &str) -> Option<Tree> {
+        let chart = self.chart(s);
+        self.tree(&chart, self.start, 0, chart.chars.len(), &mut vec![])
+    }
+
+    fn symbols(&self, item: &Item) -> &[i64] {
+        &self.rules[&item.rule].alternatives()[item.alt]
+    }
+
+    fn chart(&self, s: &str) -> Chart {
+        let chars: Vec<char> = s.chars().collect();
+        let mut sets: Vec<Vec<Item>> = vec![vec![]; chars.len() + 1];
+        let mut seen: Vec<HashSet<Item>> = vec![HashSet::new(); chars.len() + 1];
+        let mut complete = HashSet::new();
+
+        let mut add = |sets: &mut Vec<Vec<Item>>, k: usize, item: Item| {
+            if seen[k].insert(item) {
+                sets[k].push(item);
+            }
+        };
+
+        for alt in 0..self.rules[&self.start].alternatives().len() {
+            let item = Item {
+                rule: self.start,
+                alt,
+                dot: 0,
+                origin: 0,
+            };
+            add(&mut sets, 0, item);
+        }
+
+        for k in 0..sets.len() {
+            let mut i = 0;
+            while i < sets[k].len() {
+                let item = sets[k][i];
+                i += 1;
+
+                let next = match self.symbols(&item).get(item.dot) {
+                    Some(&next) => next,
+                    None => {
+                        // Without empty alternatives the origin set is already complete
+                        complete.insert((item.rule, item.alt, item.origin, k));
+                        for j in 0..sets[item.origin].len() {
+                            let parent = sets[item.origin][j];
+                            if self.symbols(&parent).get(parent.dot) == Some(&item.rule) {
+                                let advanced = Item {
+                                    dot: parent.dot + 1,
+                                    ..parent
+                                };
+                                add(&mut sets, k, advanced);
+                            }
+                        }
+                        continue;
+                    }
+                };
+
+                match &self.rules[&next] {
+                    Rule::Char(c) => {
+                        if chars.get(k) == Some(c) {
+                            let advanced = Item {
+                                dot: item.dot + 1,
+                                ..item
+                            };
+                            add(&mut sets, k + 1, advanced);
+                        }
+                    }
+                    rule => {
+                        for alt in 0..rule.alternatives().len() {
+                            let predicted = Item {
+                                rule: next,
+                                alt,
+                                dot: 0,
+                                origin: k,
+                            };
+                            add(&mut sets, k, predicted);
+                        }
+                    }
+                }
+            }
+        }
+
+        Chart { chars, complete }
+    }
+
+    fn spans(&self, chart: &Chart, id: i64, start: usize, end: usize) -> bool {
+        match &self.rules[&id] {
+            Rule::Char(c) => end == start + 1 && chart.chars.get(start) == Some(c),
+            rule => (0..rule.alternatives().len())
+                .any(|alt| chart.complete.contains(&(id, alt, start, end))),
+        }
+    }
+
+    fn tree(
+        &self,
+        chart: &Chart,
+        id: i64,
+        start: usize,
+        end: usize,
+        stack: &mut Vec<(i64, usize, usize)>,
+    ) -> Option<Tree> {
+        let rule = &self.rules[&id];
+        if let Rule::Char(c) = rule {
+            return Some(Tree::Leaf(id, *c)).filter(|_| self.spans(chart, id, start, end));
+        }
+        // Cycles of single-symbol alternatives would otherwise recurse forever
+        if stack.contains(&(id, start, end)) {
+            return None;
+        }
+
+        stack.push((id, start, end));
+        let tree = rule
+            .alternatives()
+            .iter()
+            .enumerate()
+            .filter(|&(alt, _)| chart.complete.contains(&(id, alt, start, end)))
+            .find_map(|(_, symbols)| self.children(chart, symbols, start, end, stack))
+            .map(|children| Tree::Node(id, children));
+        stack.pop();
+
+        tree
+    }
+
+    fn children(
+        &self,
+        chart: &Chart,
+        symbols: &[i64],
+        start: usize,
+        end: usize,
+        stack: &mut Vec<(i64, usize, usize)>,
+    ) -> Option<Vec<Tree>> {
+        let (&first, rest) = match symbols.split_first() {
+            Some(split) => split,
+            None if start == end => return Some(vec![]),
+            None => return None,
+        };
+        if end - start < symbols.len() {
+            return None;
+        }
+
+        // Every symbol matches at least one character
+        for mid in start + 1..=end - rest.len() {
+            if !self.spans(chart, first, start, mid) {
+                continue;
+            }
+            if let Some(mut tail) = self.children(chart, rest, mid, end, stack) {
+                if let Some(head) = self.tree(chart, first, start, mid, stack) {
+                    tail.insert(0, head);
+                    return Some(tail);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Every string of length `len` generated by the grammar.
+    pub fn language(&self, len: usize) -> BTreeSet<String> {
+        // Strings of each length generated by each rule
+        let mut table: Vec<HashMap<i64, BTreeSet<String>>> = vec![HashMap::new()];
+
+        for n in 1..=len {
+            let mut strings: HashMap<i64, BTreeSet<String>> = HashMap::new();
+            for (&id, rule) in &self.rules {
+                let entry = strings.entry(id).or_default();
+                match rule {
+                    Rule::Char(c) if n == 1 => {
+                        entry.insert(c.to_string());
+                    }
+                    Rule::Char(_) => (),
+                    rule => {
+                        for alt in rule.alternatives().iter().filter(|alt| alt.len() > 1) {
+                            entry.extend(concatenations(&table, alt, n));
+                        }
+                    }
+                }
+            }
+
+            // Single-symbol alternatives refer to strings of the same length, so copy them
+            // over until nothing new is added
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for (&id, rule) in &self.rules {
+                    for alt in rule.alternatives().iter().filter(|alt| alt.len() == 1) {
+                        let missing: Vec<String> = strings[&alt[0]]
+                            .difference(&strings[&id])
+                            .cloned()
+                            .collect();
+                        changed |= !missing.is_empty();
+                        strings.get_mut(&id).unwrap().extend(missing);
+                    }
+                }
+            }
+            table.push(strings);
+        }
+
+        table[len].remove(&self.start).unwrap_or_default()
+    }
+
+    /// Number of distinct strings of length `len` generated by the grammar.
+    pub fn count(&self, len: usize) -> usize {
+        self.language(len).len()
+    }
+}
+
+fn concatenations(
+    table: &[HashMap<i64, BTreeSet<String>>],
+    symbols: &[i64],
+    len: usize,
+) -> BTreeSet<String> {
+    let (first, rest) = match symbols.split_first() {
+        Some(split) => split,
+        None if len == 0 => return std::iter::once(String::new()).collect(),
+        None => return BTreeSet::new(),
+    };
+    if len < symbols.len() {
+        return BTreeSet::new();
+    }
+
+    let mut strings = BTreeSet::new();
+    for n in 1..=len - rest.len() {
+        let heads = match table[n].get(first) {
+            Some(heads) if !heads.is_empty() => heads,
+            _ => continue,
+        };
+        let tails = concatenations(table, rest, len - n);
+        for head in heads {
+            for tail in &tails {
+                strings.insert(format!("{}{}", head, tail));
+            }
+        }
+    }
+
+    strings
+}
+
+impl std::str::FromStr for Grammar {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rules = s
+            .lines()
+            .map(|l| {
+                let mut parts = l.split(": ");
+                Ok((
+                    parts.next().ok_or_else(|| anyhow!("missing id"))?.parse()?,
+                    parts
+                        .next()
+                        .ok_or_else(|| anyhow!("missing definition"))?
+                        .parse()?,
+                ))
+            })
+            .collect::<Result<_>>()?;
+
+        Grammar::new(rules, 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn parse_char() -> Result<()> {
+        assert_eq!(Rule::from_str("\"a\"")?, Rule::Char('a'));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_seq() -> Result<()> {
+        assert_eq!(Rule::from_str("1 2")?, Rule::Seq(vec![1, 2]));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_alt() -> Result<()> {
+        assert_eq!(Rule::from_str("1 | 2")?, alt(vec![1], vec![2]),);
+        Ok(())
+    }
+
+    #[test]
+    fn match_char() {
+        let mut rules = HashMap::new();
+        rules.insert(0, Rule::Char('a'));
+
+        assert!(Grammar::new(rules, 0).unwrap().matches("a"));
+    }
+
+    #[test]
+    fn match_seq() {
+        let mut rules = HashMap::new();
+        rules.insert(0, Rule::Seq(vec![1, 2]));
+        rules.insert(1, Rule::Char('a'));
+        rules.insert(2, Rule::Char('b'));
+
+        assert!(Grammar::new(rules, 0).unwrap().matches("ab"));
+    }
+
+    #[test]
+    fn match_alt() {
+        let mut rules = HashMap::new();
+        rules.insert(0, alt(vec![1], vec![2]));
+        rules.insert(1, Rule::Char('a'));
+        rules.insert(2, Rule::Char('b'));
+
+        let engine = Grammar::new(rules, 0).unwrap();
+        assert!(engine.matches("a"));
+        assert!(engine.matches("b"));
+    }
+
+    #[test]
+    fn match_seq_alt() {
+        let mut rules = HashMap::new();
+        rules.insert(0, Rule::Seq(vec![1, 1]));
+        rules.insert(1, alt(vec![2], vec![3]));
+        rules.insert(2, Rule::Char('a'));
+        rules.insert(3, Rule::Char('b'));
+
+        let engine = Grammar::new(rules, 0).unwrap();
+        assert!(engine.matches("aa"));
+        assert!(engine.matches("ab"));
+        assert!(engine.matches("ba"));
+        assert!(engine.matches("bb"));
+    }
+
+    fn alt(left: Vec<i64>, right: Vec<i64>) -> Rule {
+        Rule::Alt(vec![left, right])
+    }
+
+    #[test]
+    fn parse_many_alternatives() -> Result<()> {
+        assert_eq!(
+            Rule::from_str("1 | 2 3 | 4")?,
+            Rule::Alt(vec![vec![1], vec![2, 3], vec![4]])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn match_recursive() -> Result<()> {
+        // Balanced parentheses
+        let grammar = Grammar::from_str("0: 1 2 | 1 0 2 | 0 0\n1: \"(\"\n2: \")\"")?;
+        assert!(grammar.matches("()"));
+        assert!(grammar.matches("(()())()"));
+        assert!(!grammar.matches("(()"));
+        assert!(!grammar.matches(")("));
+        assert!(!grammar.matches(""));
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_grammar() {
+        assert!(Grammar::from_str("0: 1 2\n1: \"a\"").is_err());
+        assert!(Grammar::from_str("1: \"a\"").is_err());
+    }
+
+    #[test]
+    fn parse_tree() -> Result<()> {
+        let grammar = Grammar::from_str("0: 1 | 2 0\n1: \"a\"\n2: \"b\"")?;
+        assert_eq!(
+            grammar.parse("ba"),
+            Some(Tree::Node(
+                0,
+                vec![Tree::Leaf(2, 'b'), Tree::Node(0, vec![Tree::Leaf(1, 'a')])]
+            ))
+        );
+        assert_eq!(
+            grammar.parse("bba").unwrap().to_string(),
+            "(0 b (0 b (0 a)))"
+        );
+        assert_eq!(grammar.parse("ab"), None);
+
+        // A cycle of single-symbol alternatives
+        let grammar = Grammar::from_str("0: 3 | 1\n3: 0\n1: \"a\"")?;
+        assert_eq!(grammar.parse("a").unwrap().to_string(), "(0 a)");
+        Ok(())
+    }
+
+    #[test]
+    fn language() -> Result<()> {
+        let grammar = Grammar::from_str("0: 1 | 2 0 | 0 2\n1: \"a\"\n2: \"b\"")?;
+        let strings: Vec<_> = grammar.language(3).into_iter().collect();
+        // Exactly one a
+        assert_eq!(strings, vec!["abb", "bab", "bba"]);
+        assert!(strings.iter().all(|s| grammar.parse(s).is_some()));
+        assert_eq!(grammar.count(5), 5);
+        assert_eq!(grammar.count(0), 0);
+        Ok(())
+    }
+}
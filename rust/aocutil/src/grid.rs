@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::iter;
 use std::iter::FromIterator;
 
@@ -103,6 +103,36 @@ impl<T> Grid<T> {
         Surrounding::new(self, p, mask)
     }
 
+    /// Group the cells for which `include` holds into regions, where cells are connected to the
+    /// neighbours selected by `mask` (see `surrounding`).
+    pub fn components<F>(&self, mask: u8, include: F) -> Vec<Vec<Point>>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let mut seen = HashSet::new();
+        let mut components = vec![];
+
+        for (&start, c) in self.iter() {
+            if !include(c) || !seen.insert(start) {
+                continue;
+            }
+
+            let mut component = vec![start];
+            let mut stack = vec![start];
+            while let Some(p) = stack.pop() {
+                for (n, c) in self.surrounding(&p, mask) {
+                    if include(c) && seen.insert(n) {
+                        component.push(n);
+                        stack.push(n);
+                    }
+                }
+            }
+            components.push(component);
+        }
+
+        components
+    }
+
     fn reset_bounds(&mut self) {
         self.bounds = Box::from_points(self.coords.keys());
     }
@@ -191,10 +221,7 @@ impl<'a, T> Surrounding<'a, T> {
     }
 }
 
-impl<'a, T> Iterator for Surrounding<'a, T>
-where
-    T: PartialEq + Copy,
-{
+impl<'a, T> Iterator for Surrounding<'a, T> {
     type Item = (Point, &'a T);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -383,4 +410,36 @@ d e"#,
         assert_eq!(g.get(&Point::new(0, 0)).unwrap(), &'a');
         assert_eq!(g.get(&Point::new(2, 1)).unwrap(), &'c');
     }
+
+    #[test]
+    fn components() {
+        let g = Grid::from_vec2d(vec![
+            vec!['#', '#', '.', '#'],
+            vec!['.', '#', '.', '.'],
+            vec!['#', '.', '#', '#'],
+        ]);
+
+        let mut sizes: Vec<usize> = g
+            .components(MASK_CROSSHAIR, |&c| c == '#')
+            .iter()
+            .map(|c| c.len())
+            .collect();
+        sizes.sort_unstable();
+        assert_eq!(vec![1, 1, 2, 3], sizes);
+
+        let mut sizes: Vec<usize> = g
+            .components(MASK_ALL, |&c| c == '#')
+            .iter()
+            .map(|c| c.len())
+            .collect();
+        sizes.sort_unstable();
+        assert_eq!(vec![1, 6], sizes);
+
+        // Cells need not be Copy
+        let mut words = Grid::new();
+        words.insert(Point::new(0, 0), "a".to_string());
+        words.insert(Point::new(1, 0), "b".to_string());
+        words.insert(Point::new(3, 0), "c".to_string());
+        assert_eq!(2, words.components(MASK_CROSSHAIR, |w| !w.is_empty()).len());
+    }
 }
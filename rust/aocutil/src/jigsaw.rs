@@ -0,0 +1,501 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+pub type Cells = Vec<Vec<bool>>;
+
+/// Widest tile whose edges fit in a signature.
+pub const MAX_TILE_SIZE: usize = 32;
+
+/// One of the 8 ways to lay down a square: `0..4` clockwise quarter turns, after flipping
+/// horizontally for orientations `4..8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Orientation(pub u8);
+
+impl Orientation {
+    pub fn all() -> impl Iterator<Item = Orientation> {
+        (0..8).map(Orientation)
+    }
+
+    pub fn apply(self, cells: &[Vec<bool>]) -> Cells {
+        let mut cells = if self.0 >= 4 {
+            cells
+                .iter()
+                .map(|row| row.iter().rev().copied().collect())
+                .collect()
+        } else {
+            cells.to_vec()
+        };
+        for _ in 0..self.0 % 4 {
+            cells = rotate(&cells);
+        }
+        cells
+    }
+}
+
+fn rotate(cells: &[Vec<bool>]) -> Cells {
+    let n = cells.len();
+    (0..n)
+        .map(|r| (0..n).map(|c| cells[n - 1 - c][r]).collect())
+        .collect()
+}
+
+fn bits<I: Iterator<Item = bool>>(cells: I) -> u32 {
+    cells.fold(0, |acc, b| acc << 1 | b as u32)
+}
+
+/// The top, right, bottom and left edges as bits, read left to right and top to bottom. The
+/// cells must be square and at most `MAX_TILE_SIZE` wide.
+fn edges(cells: &[Vec<bool>]) -> [u32; 4] {
+    let n = cells.len();
+    [
+        bits(cells[0].iter().copied()),
+        bits(cells.iter().map(|row| row[n - 1])),
+        bits(cells[n - 1].iter().copied()),
+        bits(cells.iter().map(|row| row[0])),
+    ]
+}
+
+/// The same signature for an edge of `len` bits read in either direction.
+fn canonical(edge: u32, len: usize) -> u32 {
+    edge.min(edge.reverse_bits() >> (32 - len))
+}
+
+#[derive(Debug, Clone)]
+pub struct Tile {
+    pub id: i64,
+    pub cells: Cells,
+}
+
+impl Tile {
+    pub fn size(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Edges of the tile in each orientation.
+    fn oriented_edges(&self) -> Vec<[u32; 4]> {
+        Orientation::all()
+            .map(|o| edges(&o.apply(&self.cells)))
+            .collect()
+    }
+
+    /// Signatures of the edges, which do not depend on the orientation.
+    fn signatures(&self) -> [u32; 4] {
+        let mut edges = edges(&self.cells);
+        for e in edges.iter_mut() {
+            *e = canonical(*e, self.size());
+        }
+        edges
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub tile: usize,
+    pub orientation: Orientation,
+}
+
+/// Tiles laid out in a square, in row-major order.
+pub struct Mosaic<'a> {
+    tiles: &'a [Tile],
+    pub side: usize,
+    pub placements: Vec<Placement>,
+}
+
+impl<'a> Mosaic<'a> {
+    pub fn id(&self, row: usize, col: usize) -> i64 {
+        self.tiles[self.placements[row * self.side + col].tile].id
+    }
+
+    pub fn corners(&self) -> [i64; 4] {
+        let last = self.side - 1;
+        [
+            self.id(0, 0),
+            self.id(0, last),
+            self.id(last, 0),
+            self.id(last, last),
+        ]
+    }
+
+    /// The picture formed by the tiles without their borders.
+    pub fn image(&self) -> Cells {
+        let inner = self.tiles[0].size() - 2;
+        let mut image = vec![vec![false; inner * self.side]; inner * self.side];
+        for (i, p) in self.placements.iter().enumerate() {
+            let cells = p.orientation.apply(&self.tiles[p.tile].cells);
+            let (row, col) = (i / self.side, i % self.side);
+            for r in 0..inner {
+                for c in 0..inner {
+                    image[row * inner + r][col * inner + c] = cells[r + 1][c + 1];
+                }
+            }
+        }
+        image
+    }
+}
+
+struct Assembly<'a> {
+    tiles: &'a [Tile],
+    side: usize,
+    edges: Vec<Vec<[u32; 4]>>,
+    /// Number of edges with each signature. Edges on the outside of the mosaic are unique.
+    signatures: HashMap<u32, usize>,
+    placements: Vec<Placement>,
+    used: Vec<bool>,
+}
+
+impl<'a> Assembly<'a> {
+    fn is_outer(&self, edge: u32) -> bool {
+        self.signatures[&canonical(edge, self.tiles[0].size())] == 1
+    }
+
+    fn placed_edges(&self, pos: usize) -> [u32; 4] {
+        let p = self.placements[pos];
+        self.edges[p.tile][p.orientation.0 as usize]
+    }
+
+    fn fits(&self, pos: usize, e: &[u32; 4]) -> bool {
+        let (row, col) = (pos / self.side, pos % self.side);
+        let top = match row {
+            0 => self.is_outer(e[0]),
+            _ => self.placed_edges(pos - self.side)[2] == e[0],
+        };
+        let left = match col {
+            0 => self.is_outer(e[3]),
+            _ => self.placed_edges(pos - 1)[1] == e[3],
+        };
+        top && left
+    }
+
+    fn place(&mut self, order: &[usize]) -> bool {
+        let pos = self.placements.len();
+        if pos == self.tiles.len() {
+            return true;
+        }
+
+        for &tile in order {
+            if self.used[tile] {
+                continue;
+            }
+            for orientation in Orientation::all() {
+                if !self.fits(pos, &self.edges[tile][orientation.0 as usize]) {
+                    continue;
+                }
+
+                self.used[tile] = true;
+                self.placements.push(Placement { tile, orientation });
+                if self.place(order) {
+                    return true;
+                }
+                self.placements.pop();
+                self.used[tile] = false;
+            }
+        }
+
+        false
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JigsawError {
+    /// The number of tiles is not a square.
+    Count(usize),
+    /// A tile is not square, or not the size of the first tile.
+    Shape(i64),
+    /// A tile is smaller than 2 or wider than `MAX_TILE_SIZE`.
+    Size(i64, usize),
+    NoFit,
+}
+
+impl fmt::Display for JigsawError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JigsawError::Count(n) => write!(f, "{} tiles do not make a square", n),
+            JigsawError::Shape(id) => write!(f, "tile {} is not the same square as the rest", id),
+            JigsawError::Size(id, size) => write!(
+                f,
+                "tile {} is {} wide instead of 2 to {}",
+                id, size, MAX_TILE_SIZE
+            ),
+            JigsawError::NoFit => write!(f, "the tiles do not fit together"),
+        }
+    }
+}
+
+impl std::error::Error for JigsawError {}
+
+fn check(tiles: &[Tile]) -> Result<usize, JigsawError> {
+    let side = (tiles.len() as f64).sqrt() as usize;
+    if tiles.is_empty() || side * side != tiles.len() {
+        return Err(JigsawError::Count(tiles.len()));
+    }
+
+    let size = tiles[0].size();
+    if !(2..=MAX_TILE_SIZE).contains(&size) {
+        return Err(JigsawError::Size(tiles[0].id, size));
+    }
+    if let Some(t) = tiles
+        .iter()
+        .find(|t| t.size() != size || t.cells.iter().any(|row| row.len() != size))
+    {
+        return Err(JigsawError::Shape(t.id));
+    }
+
+    Ok(side)
+}
+
+/// Arrange square tiles of equal size into a square so that touching edges match. Tiles
+/// with the most unmatched edges, i.e. the corners, are tried first.
+pub fn assemble(tiles: &[Tile]) -> Result<Mosaic<'_>, JigsawError> {
+    let side = check(tiles)?;
+
+    let mut signatures = HashMap::new();
+    for t in tiles {
+        for s in t.signatures().iter() {
+            *signatures.entry(*s).or_insert(0) += 1;
+        }
+    }
+
+    let mut order: Vec<usize> = (0..tiles.len()).collect();
+    order.sort_by_key(|&i| {
+        let outer = tiles[i]
+            .signatures()
+            .iter()
+            .filter(|s| signatures[s] == 1)
+            .count();
+        std::cmp::Reverse(outer)
+    });
+
+    let mut assembly = Assembly {
+        tiles,
+        side,
+        edges: tiles.iter().map(|t| t.oriented_edges()).collect(),
+        signatures,
+        placements: Vec::with_capacity(tiles.len()),
+        used: vec![false; tiles.len()],
+    };
+    if !assembly.place(&order) {
+        return Err(JigsawError::NoFit);
+    }
+
+    Ok(Mosaic {
+        tiles,
+        side,
+        placements: assembly.placements,
+    })
+}
+
+/// Cells which must be set for a pattern to match. Other cells are ignored.
+pub struct Pattern {
+    cells: Vec<(usize, usize)>,
+    height: usize,
+    width: usize,
+}
+
+impl Pattern {
+    /// Lines of `#` for required cells, and any other character for cells to ignore.
+    pub fn parse(s: &str) -> Self {
+        let cells: Vec<(usize, usize)> = s
+            .lines()
+            .enumerate()
+            .flat_map(|(r, l)| {
+                l.chars()
+                    .enumerate()
+                    .filter(|&(_, c)| c == '#')
+                    .map(move |(c, _)| (r, c))
+            })
+            .collect();
+        let height = cells.iter().map(|&(r, _)| r + 1).max().unwrap_or(0);
+        let width = cells.iter().map(|&(_, c)| c + 1).max().unwrap_or(0);
+
+        Pattern {
+            cells,
+            height,
+            width,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Found {
+    /// Orientation of the image in which the pattern was found.
+    pub orientation: Orientation,
+    /// Top-left corners of each match in the oriented image.
+    pub positions: Vec<(usize, usize)>,
+    /// Number of set cells which are part of a match.
+    pub covered: usize,
+}
+
+/// Find the orientation of a square image with the most occurrences of `pattern`. There is
+/// none if the image is not square.
+pub fn search(image: &[Vec<bool>], pattern: &Pattern) -> Option<Found> {
+    let n = image.len();
+    if image.iter().any(|row| row.len() != n) {
+        return None;
+    }
+    if pattern.cells.is_empty() || pattern.height > n || pattern.width > n {
+        return None;
+    }
+
+    Orientation::all()
+        .map(|orientation| {
+            let image = orientation.apply(image);
+            let mut positions = vec![];
+            let mut covered = HashSet::new();
+            for r in 0..=n - pattern.height {
+                for c in 0..=n - pattern.width {
+                    if pattern.cells.iter().all(|&(pr, pc)| image[r + pr][c + pc]) {
+                        positions.push((r, c));
+                        covered.extend(pattern.cells.iter().map(|&(pr, pc)| (r + pr, c + pc)));
+                    }
+                }
+            }
+            Found {
+                orientation,
+                positions,
+                covered: covered.len(),
+            }
+        })
+        .filter(|f| !f.positions.is_empty())
+        .max_by_key(|f| f.positions.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cells(rows: &[&str]) -> Cells {
+        rows.iter()
+            .map(|r| r.chars().map(|c| c == '#').collect())
+            .collect()
+    }
+
+    fn tile(id: i64, size: usize) -> Tile {
+        Tile {
+            id,
+            cells: vec![vec![false; size]; size],
+        }
+    }
+
+    #[test]
+    fn orientations() {
+        let cells = vec![vec![true, false], vec![false, false]];
+        let all: Vec<Cells> = Orientation::all().map(|o| o.apply(&cells)).collect();
+        // The corner ends up in each of the four positions, twice
+        assert_eq!(all[1], vec![vec![false, true], vec![false, false]]);
+        assert_eq!(all[4], vec![vec![false, true], vec![false, false]]);
+        assert_eq!(all[0], Orientation(0).apply(&Orientation(2).apply(&all[2])));
+    }
+
+    #[test]
+    fn edge_signatures() {
+        let tile = Tile {
+            id: 2311,
+            cells: cells(&[
+                "..##.#..#.",
+                "##..#.....",
+                "#...##..#.",
+                "####.#...#",
+                "##.##.###.",
+                "##...#.###",
+                ".#.#.#..##",
+                "..#....#..",
+                "###...#.#.",
+                "..###..###",
+            ]),
+        };
+        assert_eq!(edges(&tile.cells)[0], 0b0011010010);
+        assert_eq!(canonical(0b0100101100, 10), 0b0011010010);
+        for e in tile.oriented_edges() {
+            let mut signatures = e.map(|e| canonical(e, 10));
+            let mut expected = tile.signatures();
+            signatures.sort_unstable();
+            expected.sort_unstable();
+            assert_eq!(signatures, expected);
+        }
+
+        // The widest edges use every bit
+        assert_eq!(canonical(1, MAX_TILE_SIZE), 1);
+        assert_eq!(canonical(1 << 31, MAX_TILE_SIZE), 1);
+    }
+
+    #[test]
+    fn invalid_tiles() {
+        let err = |tiles: &[Tile]| assemble(tiles).err().unwrap();
+        assert_eq!(err(&[]), JigsawError::Count(0));
+        assert_eq!(err(&[tile(1, 3), tile(2, 3)]), JigsawError::Count(2));
+        assert_eq!(err(&[tile(1, 33)]), JigsawError::Size(1, 33));
+        assert_eq!(err(&[tile(1, 1)]), JigsawError::Size(1, 1));
+        assert_eq!(
+            err(&[tile(1, 3), tile(2, 3), tile(3, 4), tile(4, 3)]),
+            JigsawError::Shape(3)
+        );
+
+        let mut narrow = tile(7, 3);
+        narrow.cells[1].pop();
+        assert_eq!(err(&[narrow]), JigsawError::Shape(7));
+        assert_eq!(
+            err(&[tile(1, 3), tile(2, 3), tile(3, 3), tile(4, 3)]).to_string(),
+            "the tiles do not fit together"
+        );
+    }
+
+    #[test]
+    fn assembly() {
+        // Cut a 3x3 mosaic of 8x8 tiles out of a noisy picture, so that neighbouring tiles share
+        // their touching edges, then scramble the order and orientation of the tiles
+        let (side, size) = (3, 8);
+        let mut seed = 0x2545_f491u32;
+        let picture: Cells = (0..side * (size - 1) + 1)
+            .map(|_| {
+                (0..side * (size - 1) + 1)
+                    .map(|_| {
+                        seed ^= seed << 13;
+                        seed ^= seed >> 17;
+                        seed ^= seed << 5;
+                        seed & 1 == 1
+                    })
+                    .collect()
+            })
+            .collect();
+        let piece = |row: usize, col: usize, inset: usize| -> Cells {
+            picture[row * (size - 1) + inset..row * (size - 1) + size - inset]
+                .iter()
+                .map(|r| r[col * (size - 1) + inset..col * (size - 1) + size - inset].to_vec())
+                .collect()
+        };
+        let tiles: Vec<Tile> = (0..side * side)
+            .rev()
+            .map(|i| Tile {
+                id: i as i64,
+                cells: Orientation((i * 3 % 8) as u8).apply(&piece(i / side, i % side, 0)),
+            })
+            .collect();
+
+        let mosaic = assemble(&tiles).unwrap();
+        assert_eq!(mosaic.side, side);
+        let mut corners = mosaic.corners();
+        corners.sort_unstable();
+        assert_eq!(corners, [0, 2, 6, 8]);
+
+        let mut expected = vec![vec![]; side * (size - 2)];
+        for i in 0..side * side {
+            for (r, cells) in piece(i / side, i % side, 1).into_iter().enumerate() {
+                expected[i / side * (size - 2) + r].extend(cells);
+            }
+        }
+        let image = mosaic.image();
+        assert!(Orientation::all().any(|o| o.apply(&expected) == image));
+    }
+
+    #[test]
+    fn patterns() {
+        let image = cells(&["#..", ".#.", "..#"]);
+        let found = search(&image, &Pattern::parse("#.\n.#")).unwrap();
+        // The diagonal is symmetric, so the positions are the same in every orientation found
+        assert_eq!(found.positions, vec![(0, 0), (1, 1)]);
+        assert_eq!(found.covered, 3);
+
+        assert_eq!(search(&image, &Pattern::parse("#\n#")), None);
+        assert_eq!(search(&cells(&["##", "#"]), &Pattern::parse("#")), None);
+    }
+}
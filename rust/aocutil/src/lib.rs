@@ -1,13 +1,34 @@
+pub mod bits;
+mod compress;
 mod direction;
+mod expr;
+mod grammar;
 mod grid;
 mod iter;
+pub mod jigsaw;
+mod math;
+mod nbody;
 mod point;
+mod ring;
+mod segment;
 mod test;
+mod vfs;
+mod visibility;
+pub mod vm;
 
+pub use compress::*;
 pub use direction::*;
+pub use expr::*;
+pub use grammar::*;
 pub use grid::*;
 pub use iter::*;
+pub use math::*;
+pub use nbody::*;
+pub use ring::*;
+pub use segment::*;
 pub use test::*;
+pub use vfs::*;
+pub use visibility::*;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,43 @@
+use num_traits::PrimInt;
+
+/// Greatest common divisor, which is never negative. `gcd(0, 0)` is zero.
+pub fn gcd<T: PrimInt>(a: T, b: T) -> T {
+    let (mut a, mut b) = (a, b);
+    while !b.is_zero() {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+
+    if a < T::zero() {
+        T::zero() - a
+    } else {
+        a
+    }
+}
+
+/// Least common multiple of positive numbers.
+pub fn lcm<T: PrimInt>(a: T, b: T) -> T {
+    a / gcd(a, b) * b
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_gcd() {
+        assert_eq!(6, gcd(12, 18));
+        assert_eq!(6, gcd(-12, 18));
+        assert_eq!(6, gcd(12, -18));
+        assert_eq!(7, gcd(0, -7));
+        assert_eq!(0, gcd(0, 0));
+        assert_eq!(1, gcd(17u64, 5));
+    }
+
+    #[test]
+    fn test_lcm() {
+        assert_eq!(36, lcm(12, 18));
+        assert_eq!(4686774924u64, lcm(lcm(2028u64, 5898), 4702));
+    }
+}
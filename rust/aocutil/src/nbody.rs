@@ -0,0 +1,240 @@
+use crate::math::gcd;
+use num_traits::{CheckedAdd, Signed};
+
+/// Most steps searched for the period of an axis, unless set with `System::with_period_limit`.
+pub const DEFAULT_PERIOD_LIMIT: u64 = 1 << 24;
+
+/// How much a body at `own` changes its velocity towards a body at `other`, along one axis.
+pub type Pull<T> = fn(own: T, other: T) -> T;
+
+/// One unit of velocity towards the other body, as between moons.
+pub fn unit_pull<T: Signed>(own: T, other: T) -> T {
+    (other - own).signum()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Body<T, const N: usize> {
+    pub pos: [T; N],
+    pub vel: [T; N],
+}
+
+impl<T: Copy + Signed, const N: usize> Body<T, N> {
+    /// A body at rest.
+    pub fn at(pos: [T; N]) -> Self {
+        Body {
+            pos,
+            vel: [T::zero(); N],
+        }
+    }
+
+    pub fn potential_energy(&self) -> T {
+        self.pos.iter().fold(T::zero(), |e, x| e + x.abs())
+    }
+
+    pub fn kinetic_energy(&self) -> T {
+        self.vel.iter().fold(T::zero(), |e, v| e + v.abs())
+    }
+
+    pub fn energy(&self) -> T {
+        self.potential_energy() * self.kinetic_energy()
+    }
+}
+
+/// Bodies in `N` dimensions which pull on each other in pairs.
+///
+/// Each step, every body changes its velocity by the pull of every other body, and then moves by
+/// its velocity. The pull along an axis only depends on positions along that axis, so the axes
+/// can be simulated independently.
+#[derive(Debug, Clone)]
+pub struct System<T, const N: usize> {
+    bodies: Vec<Body<T, N>>,
+    pull: Pull<T>,
+    steps: u64,
+    period_limit: u64,
+}
+
+/// Positions and velocities of every body along one axis.
+type Axis<T> = (Vec<T>, Vec<T>);
+
+/// Advance an axis by one step, or `None` if a velocity or position overflows.
+fn step_axis<T: Copy + Signed + CheckedAdd>((pos, vel): &mut Axis<T>, pull: Pull<T>) -> Option<()> {
+    for i in 0..pos.len() {
+        for j in 0..pos.len() {
+            if i != j {
+                vel[i] = vel[i].checked_add(&pull(pos[i], pos[j]))?;
+            }
+        }
+    }
+    for (x, v) in pos.iter_mut().zip(vel.iter()) {
+        *x = x.checked_add(v)?;
+    }
+
+    Some(())
+}
+
+impl<T: Copy + Signed + CheckedAdd, const N: usize> System<T, N> {
+    /// Bodies at rest at `positions`.
+    pub fn new<I: IntoIterator<Item = [T; N]>>(positions: I, pull: Pull<T>) -> Self {
+        System {
+            bodies: positions.into_iter().map(Body::at).collect(),
+            pull,
+            steps: 0,
+            period_limit: DEFAULT_PERIOD_LIMIT,
+        }
+    }
+
+    pub fn with_period_limit(mut self, limit: u64) -> Self {
+        self.period_limit = limit;
+        self
+    }
+
+    pub fn bodies(&self) -> &[Body<T, N>] {
+        &self.bodies
+    }
+
+    /// Number of steps simulated so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    fn axis(&self, a: usize) -> Axis<T> {
+        self.bodies.iter().map(|b| (b.pos[a], b.vel[a])).unzip()
+    }
+
+    pub fn step(&mut self) {
+        self.run(1);
+    }
+
+    pub fn run(&mut self, steps: u64) {
+        for a in 0..N {
+            let mut axis = self.axis(a);
+            for _ in 0..steps {
+                step_axis(&mut axis, self.pull).expect("body escaped the number type");
+            }
+            for (b, (&x, &v)) in self.bodies.iter_mut().zip(axis.0.iter().zip(axis.1.iter())) {
+                b.pos[a] = x;
+                b.vel[a] = v;
+            }
+        }
+        self.steps += steps;
+    }
+
+    /// Total energy of the bodies.
+    pub fn energy(&self) -> T {
+        self.bodies.iter().fold(T::zero(), |e, b| e + b.energy())
+    }
+
+    /// Number of steps until each axis first returns to its current state.
+    ///
+    /// A step can always be undone, since the previous positions follow from the velocities, so
+    /// the first repeated state of an axis is the one it started in. Pulls which drive bodies
+    /// apart never get back there, so there is no period if an axis takes more steps than the
+    /// period limit or overflows.
+    pub fn axis_periods(&self) -> Option<[u64; N]> {
+        let mut periods = [0; N];
+        for (a, period) in periods.iter_mut().enumerate() {
+            let start = self.axis(a);
+            let mut axis = start.clone();
+            loop {
+                if *period == self.period_limit {
+                    return None;
+                }
+                step_axis(&mut axis, self.pull)?;
+                *period += 1;
+                if axis == start {
+                    break;
+                }
+            }
+        }
+
+        Some(periods)
+    }
+
+    /// Number of steps until the whole system first returns to its current state: the least
+    /// common multiple of the axis periods, if it fits.
+    pub fn period(&self) -> Option<u64> {
+        self.axis_periods()?
+            .iter()
+            .try_fold(1u64, |acc, &p| (acc / gcd(acc, p)).checked_mul(p))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn moons() -> System<i64, 3> {
+        System::new(
+            vec![[-1, 0, 2], [2, -10, -7], [4, -8, 8], [3, 5, -1]],
+            unit_pull,
+        )
+    }
+
+    #[test]
+    fn test_step() {
+        let mut system = moons();
+        system.step();
+        assert_eq!(
+            Body {
+                pos: [2, -1, 1],
+                vel: [3, -1, -1]
+            },
+            system.bodies()[0]
+        );
+        system.run(9);
+        assert_eq!(10, system.steps());
+        assert_eq!(
+            Body {
+                pos: [2, 0, 4],
+                vel: [1, -1, -1]
+            },
+            system.bodies()[3]
+        );
+        assert_eq!(179, system.energy());
+    }
+
+    #[test]
+    fn test_energy() {
+        let body = Body {
+            pos: [1, 8, 0],
+            vel: [1, 1, 3],
+        };
+        assert_eq!(9, body.potential_energy());
+        assert_eq!(5, body.kinetic_energy());
+        assert_eq!(45, body.energy());
+    }
+
+    #[test]
+    fn test_period() {
+        assert_eq!(Some(2772), moons().period());
+
+        let system: System<i32, 3> = System::new(
+            vec![[-8, -10, 0], [5, 5, 10], [2, -7, 3], [9, -8, -3]],
+            unit_pull,
+        );
+        assert_eq!(Some(4686774924), system.period());
+    }
+
+    #[test]
+    fn test_repelling() {
+        // Bodies pushed apart speed up forever, so the search gives up at the limit
+        let system: System<i64, 1> =
+            System::new(vec![[0], [1], [3]], |a, b| (a - b).signum()).with_period_limit(10_000);
+        assert_eq!(None, system.axis_periods());
+        assert_eq!(None, system.period());
+
+        // or once they overflow
+        let system: System<i32, 2> =
+            System::new(vec![[0, 0], [1, 0], [3, 0]], |a, b| a.cmp(&b) as i32);
+        assert_eq!(None, system.axis_periods());
+    }
+
+    #[test]
+    fn test_other_dimensions() {
+        // Two bodies on a line, pulled twice as hard as moons
+        let mut system: System<i32, 1> = System::new(vec![[0], [3]], |a, b| 2 * (b - a).signum());
+        assert_eq!(Some([4]), system.axis_periods());
+        system.run(2);
+        assert_eq!([[2], [1]], [system.bodies()[0].pos, system.bodies()[1].pos]);
+    }
+}
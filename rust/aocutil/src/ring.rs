@@ -0,0 +1,307 @@
+const DETACHED: usize = usize::MAX;
+
+/// A circular doubly linked list of the labels `0..capacity`, stored as successor and
+/// predecessor arrays. Labels are their own index, so finding a label is O(1) and no node is
+/// allocated separately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ring {
+    next: Vec<usize>,
+    prev: Vec<usize>,
+    len: usize,
+}
+
+/// A run of labels removed from a ring, which can be inserted again as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chain {
+    pub first: usize,
+    pub last: usize,
+    pub len: usize,
+}
+
+impl Ring {
+    /// An empty ring with room for the labels `0..capacity`.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Ring {
+            next: vec![DETACHED; capacity],
+            prev: vec![DETACHED; capacity],
+            len: 0,
+        }
+    }
+
+    /// A ring containing `labels` in order, with room for the labels `0..capacity`.
+    ///
+    /// Panics if a label is out of range or repeated.
+    pub fn from_order<I: IntoIterator<Item = usize>>(labels: I, capacity: usize) -> Self {
+        let mut ring = Ring::with_capacity(capacity);
+        let mut last = None;
+        for label in labels {
+            match last {
+                None => ring.insert_first(label),
+                Some(l) => ring.insert_after(l, label),
+            }
+            last = Some(label);
+        }
+        ring
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.next.len()
+    }
+
+    /// Whether `label` has been inserted. Labels taken out by `splice_out` still count.
+    pub fn contains(&self, label: usize) -> bool {
+        label < self.capacity() && self.next[label] != DETACHED
+    }
+
+    pub fn next(&self, label: usize) -> usize {
+        debug_assert!(self.contains(label), "{} is not in the ring", label);
+        self.next[label]
+    }
+
+    pub fn prev(&self, label: usize) -> usize {
+        debug_assert!(self.contains(label), "{} is not in the ring", label);
+        self.prev[label]
+    }
+
+    /// The label `steps` places clockwise from `label`. Steps are taken modulo the length, so
+    /// this is O(min(steps, len)). An empty ring has nowhere to step, so this is `label`.
+    pub fn nth(&self, label: usize, steps: usize) -> usize {
+        (0..self.wrap(steps)).fold(label, |l, _| self.next[l])
+    }
+
+    /// The label `steps` places anti-clockwise from `label`.
+    pub fn nth_back(&self, label: usize, steps: usize) -> usize {
+        (0..self.wrap(steps)).fold(label, |l, _| self.prev[l])
+    }
+
+    fn wrap(&self, steps: usize) -> usize {
+        steps.checked_rem(self.len).unwrap_or(0)
+    }
+
+    fn attach(&mut self, label: usize) {
+        assert!(
+            label < self.capacity() && !self.contains(label),
+            "{} cannot be inserted",
+            label
+        );
+        self.len += 1;
+    }
+
+    fn insert_first(&mut self, label: usize) {
+        assert!(self.is_empty(), "the ring is not empty");
+        self.attach(label);
+        self.next[label] = label;
+        self.prev[label] = label;
+    }
+
+    /// Insert a new label after `after`, or as the only element of an empty ring.
+    pub fn insert_after(&mut self, after: usize, label: usize) {
+        if self.is_empty() {
+            return self.insert_first(label);
+        }
+        self.attach(label);
+        let next = self.next[after];
+        self.next[label] = next;
+        self.prev[label] = after;
+        self.prev[next] = label;
+        self.next[after] = label;
+    }
+
+    /// Remove `label` from the ring.
+    ///
+    /// Panics if `label` is not in the ring.
+    pub fn remove(&mut self, label: usize) {
+        assert!(self.contains(label), "{} is not in the ring", label);
+        if self.len > 1 {
+            self.splice_out(self.prev(label), 1);
+        } else {
+            self.len = 0;
+        }
+        self.next[label] = DETACHED;
+        self.prev[label] = DETACHED;
+    }
+
+    /// Take the `len` labels following `after` out of the ring. They keep their order, but
+    /// cannot be reached from the ring until inserted again with `splice_in`.
+    ///
+    /// This is O(len), and O(1) for the short runs of most puzzles.
+    pub fn splice_out(&mut self, after: usize, len: usize) -> Chain {
+        assert!(
+            len > 0 && len < self.len,
+            "cannot splice out {} labels",
+            len
+        );
+        let first = self.next[after];
+        let last = self.nth(after, len);
+        let rest = self.next[last];
+        self.next[after] = rest;
+        self.prev[rest] = after;
+        self.len -= len;
+
+        Chain { first, last, len }
+    }
+
+    /// Insert a chain from `splice_out` after `after`.
+    pub fn splice_in(&mut self, after: usize, chain: Chain) {
+        let next = self.next[after];
+        self.next[after] = chain.first;
+        self.prev[chain.first] = after;
+        self.next[chain.last] = next;
+        self.prev[next] = chain.last;
+        self.len += chain.len;
+    }
+
+    /// Labels of a chain taken out of the ring.
+    pub fn chain(&self, chain: Chain) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(Some(chain.first), move |&l| Some(self.next[l])).take(chain.len)
+    }
+
+    /// Reverse the order of the `len` labels starting at `start`, so that the label which was
+    /// last now takes the place of `start`. Returns that label.
+    pub fn reverse(&mut self, start: usize, len: usize) -> usize {
+        assert!(
+            len <= self.len,
+            "cannot reverse {} of {} labels",
+            len,
+            self.len
+        );
+        if len <= 1 {
+            return start;
+        }
+
+        let end = self.nth(start, len - 1);
+        let before = self.prev[start];
+        let after = self.next[end];
+
+        let mut label = start;
+        for _ in 0..len {
+            let next = self.next[label];
+            std::mem::swap(&mut self.next[label], &mut self.prev[label]);
+            label = next;
+        }
+
+        // Reversing the whole ring only changes its direction
+        if len < self.len {
+            self.next[before] = end;
+            self.prev[end] = before;
+            self.next[start] = after;
+            self.prev[after] = start;
+        }
+
+        end
+    }
+
+    /// Every label once, clockwise from `start`.
+    pub fn iter_from(&self, start: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(Some(start), move |&l| Some(self.next[l])).take(self.len)
+    }
+}
+
+/// A ring with a current label, which `rotate` moves around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub ring: Ring,
+    pub current: usize,
+}
+
+impl Cursor {
+    pub fn new(ring: Ring, current: usize) -> Self {
+        Cursor { ring, current }
+    }
+
+    /// Move the current label clockwise by `steps`, or anti-clockwise if negative.
+    pub fn rotate(&mut self, steps: i64) {
+        self.current = if steps >= 0 {
+            self.ring.nth(self.current, steps as usize)
+        } else {
+            self.ring
+                .nth_back(self.current, steps.unsigned_abs() as usize)
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn order(ring: &Ring, start: usize) -> Vec<usize> {
+        ring.iter_from(start).collect()
+    }
+
+    #[test]
+    fn test_splice() {
+        // The first move of the crab cups example, with labels shifted down by one
+        let mut ring = Ring::from_order(vec![2, 7, 6, 1, 4, 0, 5, 8, 3], 9);
+        let chain = ring.splice_out(2, 3);
+        assert_eq!(vec![7, 6, 1], ring.chain(chain).collect::<Vec<_>>());
+        assert_eq!(vec![2, 4, 0, 5, 8, 3], order(&ring, 2));
+        ring.splice_in(0, chain);
+        assert_eq!(vec![2, 4, 0, 7, 6, 1, 5, 8, 3], order(&ring, 2));
+        assert_eq!(9, ring.len());
+        assert_eq!(3, ring.prev(2));
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut ring = Ring::with_capacity(4);
+        assert!(ring.is_empty());
+        ring.insert_after(0, 0);
+        ring.insert_after(0, 2);
+        ring.insert_after(0, 1);
+        assert_eq!(vec![0, 1, 2], order(&ring, 0));
+        assert!(!ring.contains(3));
+        ring.remove(1);
+        assert!(!ring.contains(1));
+        assert_eq!(vec![2, 0], order(&ring, 2));
+        assert_eq!(2, ring.nth(0, 5));
+        assert_eq!(0, ring.nth_back(2, 3));
+        ring.remove(0);
+        ring.remove(2);
+        assert!(ring.is_empty());
+        assert_eq!(2, ring.nth(2, 3));
+        assert_eq!(2, ring.nth_back(2, 3));
+        ring.insert_after(0, 3);
+        assert_eq!(vec![3], order(&ring, 3));
+    }
+
+    #[test]
+    #[should_panic(expected = "1 is not in the ring")]
+    fn test_remove_missing() {
+        let mut ring = Ring::from_order(vec![0, 2, 3], 4);
+        ring.remove(1);
+    }
+
+    #[test]
+    fn test_reverse() {
+        let mut ring = Ring::from_order(0..5, 5);
+        assert_eq!(2, ring.reverse(0, 3));
+        assert_eq!(vec![2, 1, 0, 3, 4], order(&ring, 2));
+        // Wrapping around
+        assert_eq!(1, ring.reverse(3, 4));
+        assert_eq!(vec![4, 3, 0, 1, 2], order(&ring, 4));
+        // The whole ring
+        assert_eq!(4, ring.reverse(3, 5));
+        assert_eq!(vec![0, 3, 4, 2, 1], order(&ring, 0));
+        assert_eq!(4, ring.reverse(4, 1));
+        assert_eq!(vec![0, 3, 4, 2, 1], order(&ring, 0));
+        let prev: Vec<_> = ring.iter_from(0).map(|l| ring.prev(l)).collect();
+        assert_eq!(vec![1, 0, 3, 4, 2], prev);
+    }
+
+    #[test]
+    fn test_cursor() {
+        let mut cursor = Cursor::new(Ring::from_order(0..4, 4), 0);
+        cursor.rotate(6);
+        assert_eq!(2, cursor.current);
+        cursor.rotate(-3);
+        assert_eq!(3, cursor.current);
+    }
+}
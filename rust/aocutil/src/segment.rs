@@ -0,0 +1,421 @@
+use crate::direction::Direction;
+use crate::grid::{Point, Vector};
+use crate::math::gcd;
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, HashMap};
+
+/// A line segment between two integer points, including both ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Segment {
+    pub start: Point,
+    pub end: Point,
+}
+
+/// Where two segments meet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intersection {
+    /// A single point, as `x` and `y` numerators over a common positive `denominator`, in lowest
+    /// terms. Crossing segments need not meet at an integer point.
+    Point { x: i64, y: i64, denominator: i64 },
+    /// The segments are collinear and share more than one point.
+    Overlap(Segment),
+}
+
+impl Intersection {
+    fn at(p: Point) -> Self {
+        Intersection::Point {
+            x: p.x,
+            y: p.y,
+            denominator: 1,
+        }
+    }
+
+    /// The intersection point, if it is a single integer point.
+    pub fn lattice(&self) -> Option<Point> {
+        match *self {
+            Intersection::Point {
+                x,
+                y,
+                denominator: 1,
+            } => Some(Point::new(x, y)),
+            _ => None,
+        }
+    }
+}
+
+fn cross(a: Vector, b: Vector) -> i64 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Points ordered by x and then y, which orders the points of any line along it.
+fn key(p: &Point) -> (i64, i64) {
+    (p.x, p.y)
+}
+
+impl Segment {
+    pub fn new(start: Point, end: Point) -> Self {
+        Segment { start, end }
+    }
+
+    pub fn is_horizontal(&self) -> bool {
+        self.start.y == self.end.y
+    }
+
+    pub fn is_vertical(&self) -> bool {
+        self.start.x == self.end.x
+    }
+
+    pub fn is_axis_aligned(&self) -> bool {
+        self.is_horizontal() || self.is_vertical()
+    }
+
+    /// Number of unit steps along the grid from one end to the other.
+    pub fn manhattan_len(&self) -> i64 {
+        (self.end.x - self.start.x).abs() + (self.end.y - self.start.y).abs()
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        cross(self.end - self.start, p - self.start) == 0
+            && self.start.x.min(self.end.x) <= p.x
+            && p.x <= self.start.x.max(self.end.x)
+            && self.start.y.min(self.end.y) <= p.y
+            && p.y <= self.start.y.max(self.end.y)
+    }
+
+    /// The ends in order along the line.
+    fn ordered(&self) -> (Point, Point) {
+        if key(&self.start) <= key(&self.end) {
+            (self.start, self.end)
+        } else {
+            (self.end, self.start)
+        }
+    }
+
+    pub fn intersects(&self, other: &Segment) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// Where the segments meet, computed exactly.
+    pub fn intersection(&self, other: &Segment) -> Option<Intersection> {
+        let r = self.end - self.start;
+        let s = other.end - other.start;
+        let qp = other.start - self.start;
+
+        if r == Vector::zero() {
+            return Some(Intersection::at(self.start)).filter(|_| other.contains(self.start));
+        }
+        if s == Vector::zero() {
+            return Some(Intersection::at(other.start)).filter(|_| self.contains(other.start));
+        }
+
+        let mut denominator = cross(r, s);
+        if denominator == 0 {
+            if cross(qp, r) != 0 {
+                return None;
+            }
+
+            // Collinear, so the overlap is between the inner pair of ends
+            let (a0, a1) = self.ordered();
+            let (b0, b1) = other.ordered();
+            let lo = if key(&a0) >= key(&b0) { a0 } else { b0 };
+            let hi = if key(&a1) <= key(&b1) { a1 } else { b1 };
+            return match key(&lo).cmp(&key(&hi)) {
+                std::cmp::Ordering::Less => Some(Intersection::Overlap(Segment::new(lo, hi))),
+                std::cmp::Ordering::Equal => Some(Intersection::at(lo)),
+                std::cmp::Ordering::Greater => None,
+            };
+        }
+
+        // The segments meet at start + r * t / denominator = other.start + s * u / denominator
+        let mut t = cross(qp, s);
+        let mut u = cross(qp, r);
+        if denominator < 0 {
+            denominator = -denominator;
+            t = -t;
+            u = -u;
+        }
+        if t < 0 || t > denominator || u < 0 || u > denominator {
+            return None;
+        }
+
+        let x = self.start.x * denominator + r.x * t;
+        let y = self.start.y * denominator + r.y * t;
+        let divisor = gcd(gcd(x, y), denominator);
+        Some(Intersection::Point {
+            x: x / divisor,
+            y: y / divisor,
+            denominator: denominator / divisor,
+        })
+    }
+}
+
+/// A path of axis-aligned segments, each starting where the last ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polyline {
+    segments: Vec<Segment>,
+}
+
+impl Polyline {
+    /// Follow `moves` from `start`.
+    pub fn from_moves<I>(start: Point, moves: I) -> Self
+    where
+        I: IntoIterator<Item = (Direction, i64)>,
+    {
+        let mut at = start;
+        let segments = moves
+            .into_iter()
+            .map(|(dir, distance)| {
+                let v: Vector = dir.into();
+                let segment = Segment::new(at, at + v * distance);
+                at = segment.end;
+                segment
+            })
+            .collect();
+
+        Polyline { segments }
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Number of steps along the path until it first reaches `p`.
+    pub fn steps_to(&self, p: Point) -> Option<i64> {
+        let mut steps = 0;
+        for segment in &self.segments {
+            if segment.contains(p) {
+                return Some(steps + Segment::new(segment.start, p).manhattan_len());
+            }
+            steps += segment.manhattan_len();
+        }
+
+        None
+    }
+
+    /// Every point shared by both paths, ordered by x and then y.
+    ///
+    /// Perpendicular crossings are found by sweeping across the x axis, and collinear overlaps
+    /// by comparing segments on the same row or column, so this never visits the points of a
+    /// path one by one.
+    pub fn crossings(&self, other: &Polyline) -> Vec<Point> {
+        let (h1, v1) = self.split();
+        let (h2, v2) = other.split();
+
+        let mut points = sweep(&h1, &v2);
+        points.extend(sweep(&h2, &v1));
+        points.extend(overlaps(&h1, &h2, |s| s.start.y, Point::new));
+        points.extend(overlaps(&v1, &v2, |s| s.start.x, |y, x| Point::new(x, y)));
+
+        points.sort_by_key(key);
+        points.dedup();
+        points
+    }
+
+    /// Horizontal and vertical segments. Segments of no length count as horizontal.
+    fn split(&self) -> (Vec<Segment>, Vec<Segment>) {
+        self.segments.iter().partition(|s| s.is_horizontal())
+    }
+}
+
+/// Points where horizontal segments cross vertical ones.
+fn sweep(horizontals: &[Segment], verticals: &[Segment]) -> Vec<Point> {
+    // At equal x, segments start before and end after the crossings are looked up, since their
+    // ends are included
+    const START: u8 = 0;
+    const QUERY: u8 = 1;
+    const END: u8 = 2;
+
+    let mut events = vec![];
+    for h in horizontals {
+        let (a, b) = h.ordered();
+        events.push((a.x, START, a.y, a.y));
+        events.push((b.x, END, a.y, a.y));
+    }
+    for v in verticals {
+        let (a, b) = v.ordered();
+        events.push((a.x, QUERY, a.y, b.y));
+    }
+    events.sort_unstable();
+
+    // Number of horizontal segments on each row under the sweep line
+    let mut active: BTreeMap<i64, usize> = BTreeMap::new();
+    let mut points = vec![];
+    for (x, kind, lo, hi) in events {
+        match kind {
+            START => *active.entry(lo).or_insert(0) += 1,
+            END => {
+                let count = active.get_mut(&lo).expect("segment was started");
+                *count -= 1;
+                if *count == 0 {
+                    active.remove(&lo);
+                }
+            }
+            _ => points.extend(active.range(lo..=hi).map(|(&y, _)| Point::new(x, y))),
+        }
+    }
+
+    points
+}
+
+/// Points shared by parallel segments on the same line, which `line` identifies. Positions
+/// along the line are combined with the line by `point`.
+fn overlaps<L, P>(a: &[Segment], b: &[Segment], line: L, point: P) -> Vec<Point>
+where
+    L: Fn(&Segment) -> i64,
+    P: Fn(i64, i64) -> Point,
+{
+    let mut lines: HashMap<i64, Vec<&Segment>> = HashMap::new();
+    for s in b {
+        lines.entry(line(s)).or_default().push(s);
+    }
+
+    let mut points = vec![];
+    for s in a {
+        for t in lines.get(&line(s)).into_iter().flatten() {
+            match s.intersection(t) {
+                Some(Intersection::Overlap(o)) => {
+                    let (lo, hi) = o.ordered();
+                    let (lo, hi) = (key(&lo), key(&hi));
+                    // The position along the line is whichever coordinate varies
+                    let (from, to) = if lo.0 == hi.0 {
+                        (lo.1, hi.1)
+                    } else {
+                        (lo.0, hi.0)
+                    };
+                    points.extend((from..=to).map(|i| point(i, line(s))));
+                }
+                Some(i) => points.extend(i.lattice()),
+                None => (),
+            }
+        }
+    }
+
+    points
+}
+
+impl std::str::FromStr for Polyline {
+    type Err = anyhow::Error;
+
+    /// Parse moves such as `R75,D30` from the origin.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let moves = s
+            .trim()
+            .split(',')
+            .map(|m| {
+                let m = m.trim();
+                let dir = match m.chars().next() {
+                    Some('U') => Direction::Up,
+                    Some('D') => Direction::Down,
+                    Some('L') => Direction::Left,
+                    Some('R') => Direction::Right,
+                    _ => return Err(anyhow!("invalid move {:?}", m)),
+                };
+                let distance = m[1..]
+                    .parse()
+                    .map_err(|err| anyhow!("invalid move {:?}: {}", m, err))?;
+
+                Ok((dir, distance))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Polyline::from_moves(Point::origin(), moves))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn seg(x0: i64, y0: i64, x1: i64, y1: i64) -> Segment {
+        Segment::new(Point::new(x0, y0), Point::new(x1, y1))
+    }
+
+    #[test]
+    fn test_intersection() {
+        assert_eq!(
+            Some(Point::new(2, 0)),
+            seg(0, 0, 4, 0)
+                .intersection(&seg(2, -3, 2, 3))
+                .and_then(|i| i.lattice())
+        );
+        assert_eq!(
+            Some(Intersection::Point {
+                x: 1,
+                y: 1,
+                denominator: 2
+            }),
+            seg(0, 0, 1, 1).intersection(&seg(0, 1, 1, 0))
+        );
+        assert_eq!(None, seg(0, 0, 1, 1).intersection(&seg(2, 0, 3, -1)));
+        assert_eq!(None, seg(0, 0, 4, 0).intersection(&seg(0, 1, 4, 1)));
+
+        // Touching at the ends
+        assert_eq!(
+            Some(Point::new(4, 0)),
+            seg(0, 0, 4, 0)
+                .intersection(&seg(4, 0, 4, 5))
+                .and_then(|i| i.lattice())
+        );
+        assert_eq!(
+            Some(Point::new(3, 3)),
+            seg(1, 1, 3, 3)
+                .intersection(&seg(5, 5, 3, 3))
+                .and_then(|i| i.lattice())
+        );
+        assert_eq!(
+            Some(Point::new(1, 2)),
+            seg(1, 2, 1, 2)
+                .intersection(&seg(0, 0, 2, 4))
+                .and_then(|i| i.lattice())
+        );
+    }
+
+    #[test]
+    fn test_overlap() {
+        assert_eq!(
+            Some(Intersection::Overlap(seg(2, 0, 4, 0))),
+            seg(0, 0, 4, 0).intersection(&seg(6, 0, 2, 0))
+        );
+        assert_eq!(
+            Some(Intersection::Overlap(seg(1, 3, 2, 6))),
+            seg(2, 6, 0, 0).intersection(&seg(1, 3, 3, 9))
+        );
+        assert_eq!(None, seg(0, 0, 1, 1).intersection(&seg(2, 2, 3, 3)));
+        assert!(seg(0, 5, 0, 0).intersects(&seg(0, 1, 0, 2)));
+    }
+
+    #[test]
+    fn test_parse_and_steps() {
+        let path: Polyline = "R8,U5,L5,D3".parse().unwrap();
+        assert_eq!(4, path.segments().len());
+        assert_eq!(seg(8, 5, 3, 5), path.segments()[2]);
+        assert_eq!(Some(20), path.steps_to(Point::new(3, 3)));
+        assert_eq!(Some(15), path.steps_to(Point::new(6, 5)));
+        assert_eq!(None, path.steps_to(Point::new(1, 1)));
+        assert!("R8,X5".parse::<Polyline>().is_err());
+        assert!("R8,U".parse::<Polyline>().is_err());
+    }
+
+    #[test]
+    fn test_crossings() {
+        let a: Polyline = "R8,U5,L5,D3".parse().unwrap();
+        let b: Polyline = "U7,R6,D4,L4".parse().unwrap();
+        assert_eq!(
+            vec![Point::new(0, 0), Point::new(3, 3), Point::new(6, 5)],
+            a.crossings(&b)
+        );
+
+        // Overlapping stretches share every point on them
+        let a: Polyline = "R5,U2,L1,D4".parse().unwrap();
+        let b: Polyline = "D1,R3,U1,R4".parse().unwrap();
+        assert_eq!(
+            vec![
+                Point::new(0, 0),
+                Point::new(3, 0),
+                Point::new(4, 0),
+                Point::new(5, 0),
+            ],
+            a.crossings(&b)
+        );
+    }
+}
@@ -0,0 +1,442 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Index of a file or directory in a `FileSystem`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    File,
+    Dir(BTreeMap<String, NodeId>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Node {
+    name: String,
+    parent: Option<NodeId>,
+    /// Size of a file, or the total size of everything below a directory.
+    size: u64,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FsError {
+    NotFound(String),
+    NotADirectory(String),
+    /// A file was created twice with different sizes, or as both a file and a directory.
+    Conflict(String),
+}
+
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsError::NotFound(path) => write!(f, "{}: no such file or directory", path),
+            FsError::NotADirectory(path) => write!(f, "{}: not a directory", path),
+            FsError::Conflict(path) => write!(f, "{}: conflicts with an existing entry", path),
+        }
+    }
+}
+
+impl std::error::Error for FsError {}
+
+/// An error on a (1-based) line of a shell transcript.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// An in-memory tree of files with sizes. Directory sizes are kept up to date as files are
+/// added.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSystem {
+    nodes: Vec<Node>,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        FileSystem::new()
+    }
+}
+
+impl FileSystem {
+    pub fn new() -> Self {
+        FileSystem {
+            nodes: vec![Node {
+                name: String::new(),
+                parent: None,
+                size: 0,
+                kind: Kind::Dir(BTreeMap::new()),
+            }],
+        }
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn name(&self, id: NodeId) -> &str {
+        &self.nodes[id.0].name
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    /// Size of a file, or total size of all files below a directory.
+    pub fn size(&self, id: NodeId) -> u64 {
+        self.nodes[id.0].size
+    }
+
+    pub fn is_dir(&self, id: NodeId) -> bool {
+        matches!(self.nodes[id.0].kind, Kind::Dir(_))
+    }
+
+    /// Entries of a directory in name order. Files have no entries.
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let children = match &self.nodes[id.0].kind {
+            Kind::Dir(children) => Some(children.values().copied()),
+            Kind::File => None,
+        };
+        children.into_iter().flatten()
+    }
+
+    /// Every directory, including the root, in creation order.
+    pub fn dirs(&self) -> impl Iterator<Item = NodeId> + '_ {
+        (0..self.nodes.len())
+            .map(NodeId)
+            .filter(move |&id| self.is_dir(id))
+    }
+
+    /// Absolute path of a node, e.g. `/a/e`.
+    pub fn path(&self, id: NodeId) -> String {
+        let mut names = vec![];
+        let mut node = Some(id);
+        while let Some(id) = node {
+            names.push(self.name(id));
+            node = self.parent(id);
+        }
+        if names.len() == 1 {
+            return "/".to_string();
+        }
+        names.reverse();
+        names.join("/")
+    }
+
+    /// Resolve a path relative to `cwd`. Paths starting with `/` are absolute; `.` and `..`
+    /// refer to the current and parent directory. The parent of the root is the root.
+    pub fn resolve(&self, cwd: NodeId, path: &str) -> Result<NodeId, FsError> {
+        let mut id = if path.starts_with('/') {
+            self.root()
+        } else {
+            cwd
+        };
+
+        for name in path.split('/').filter(|n| !n.is_empty()) {
+            id = match (name, &self.nodes[id.0].kind) {
+                (_, Kind::File) => return Err(FsError::NotADirectory(self.path(id))),
+                (".", _) => id,
+                ("..", _) => self.parent(id).unwrap_or(id),
+                (name, Kind::Dir(children)) => *children
+                    .get(name)
+                    .ok_or_else(|| FsError::NotFound(self.join(id, name)))?,
+            };
+        }
+
+        Ok(id)
+    }
+
+    fn join(&self, dir: NodeId, name: &str) -> String {
+        match self.parent(dir) {
+            None => format!("/{}", name),
+            Some(_) => format!("{}/{}", self.path(dir), name),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        dir: NodeId,
+        name: &str,
+        kind: Kind,
+        size: u64,
+    ) -> Result<NodeId, FsError> {
+        let id = NodeId(self.nodes.len());
+        match &mut self.nodes[dir.0].kind {
+            Kind::File => return Err(FsError::NotADirectory(self.path(dir))),
+            Kind::Dir(children) => {
+                children.insert(name.to_string(), id);
+            }
+        }
+        self.nodes.push(Node {
+            name: name.to_string(),
+            parent: Some(dir),
+            size,
+            kind,
+        });
+
+        let mut ancestor = Some(dir);
+        while let Some(a) = ancestor {
+            self.nodes[a.0].size += size;
+            ancestor = self.parent(a);
+        }
+
+        Ok(id)
+    }
+
+    /// Create a directory in `dir`, or return the existing one with that name.
+    pub fn mkdir(&mut self, dir: NodeId, name: &str) -> Result<NodeId, FsError> {
+        match self.resolve(dir, name) {
+            Ok(id) if self.is_dir(id) => Ok(id),
+            Ok(_) => Err(FsError::Conflict(self.join(dir, name))),
+            Err(FsError::NotFound(_)) => self.insert(dir, name, Kind::Dir(BTreeMap::new()), 0),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Create a file in `dir`. Creating the same file again with the same size is allowed, so
+    /// directories can be listed more than once.
+    pub fn create(&mut self, dir: NodeId, name: &str, size: u64) -> Result<NodeId, FsError> {
+        match self.resolve(dir, name) {
+            Ok(id) if !self.is_dir(id) && self.size(id) == size => Ok(id),
+            Ok(_) => Err(FsError::Conflict(self.join(dir, name))),
+            Err(FsError::NotFound(_)) => self.insert(dir, name, Kind::File, size),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Rebuild a file system from a transcript of `$ cd <path>` and `$ ls` commands and their
+    /// output.
+    pub fn replay(transcript: &str) -> Result<Self, ReplayError> {
+        let mut fs = FileSystem::new();
+        let mut cwd = None;
+        let mut listing = false;
+
+        for (i, line) in transcript.lines().enumerate() {
+            let error = |message: String| ReplayError {
+                line: i + 1,
+                message,
+            };
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            match words.as_slice() {
+                ["$", "cd", path] => {
+                    listing = false;
+                    let from = match cwd {
+                        Some(cwd) => cwd,
+                        None if path.starts_with('/') => fs.root(),
+                        None => {
+                            return Err(error("relative cd without a working directory".into()))
+                        }
+                    };
+                    let dir = fs.resolve(from, path).map_err(|e| error(e.to_string()))?;
+                    if !fs.is_dir(dir) {
+                        return Err(error(FsError::NotADirectory(fs.path(dir)).to_string()));
+                    }
+                    cwd = Some(dir);
+                }
+                ["$", "ls"] => {
+                    if cwd.is_none() {
+                        return Err(error("ls without a working directory".into()));
+                    }
+                    listing = true;
+                }
+                ["$", ..] => return Err(error(format!("unknown command: {}", line))),
+                _ if !listing => return Err(error(format!("output outside of ls: {}", line))),
+                ["dir", name] => {
+                    fs.mkdir(cwd.unwrap(), name)
+                        .map_err(|e| error(e.to_string()))?;
+                }
+                [size, name] => {
+                    let size = size
+                        .parse()
+                        .map_err(|_| error(format!("invalid file size: {}", size)))?;
+                    fs.create(cwd.unwrap(), name, size)
+                        .map_err(|e| error(e.to_string()))?;
+                }
+                _ => return Err(error(format!("malformed entry: {}", line))),
+            }
+        }
+
+        Ok(fs)
+    }
+
+    /// Directories with a total size of at most `limit`.
+    pub fn dirs_at_most(&self, limit: u64) -> impl Iterator<Item = (NodeId, u64)> + '_ {
+        self.dirs()
+            .map(move |id| (id, self.size(id)))
+            .filter(move |&(_, size)| size <= limit)
+    }
+
+    /// The smallest directory to delete so that at least `required` bytes of a disk of
+    /// `capacity` bytes are free.
+    pub fn smallest_dir_freeing(&self, capacity: u64, required: u64) -> Option<(NodeId, u64)> {
+        let free = capacity.checked_sub(self.size(self.root()))?;
+        let needed = required.saturating_sub(free);
+
+        self.dirs()
+            .map(|id| (id, self.size(id)))
+            .filter(|&(_, size)| size >= needed)
+            .min_by_key(|&(_, size)| size)
+    }
+
+    /// The size and path of every directory, children before their parents, like `du`.
+    pub fn du(&self) -> String {
+        let mut out = String::new();
+        self.du_from(self.root(), &mut out);
+        out
+    }
+
+    fn du_from(&self, id: NodeId, out: &mut String) {
+        for child in self.children(id).filter(|&c| self.is_dir(c)) {
+            self.du_from(child, out);
+        }
+        out.push_str(&format!("{}\t{}\n", self.size(id), self.path(id)));
+    }
+
+    /// The hierarchy as an indented list, in the style of the puzzle description.
+    pub fn tree(&self) -> String {
+        let mut out = String::new();
+        self.tree_from(self.root(), 0, &mut out);
+        out
+    }
+
+    fn tree_from(&self, id: NodeId, depth: usize, out: &mut String) {
+        let name = if id == self.root() {
+            "/"
+        } else {
+            self.name(id)
+        };
+        let indent = "  ".repeat(depth);
+        if self.is_dir(id) {
+            out.push_str(&format!("{}- {} (dir)\n", indent, name));
+            for child in self.children(id) {
+                self.tree_from(child, depth + 1, out);
+            }
+        } else {
+            out.push_str(&format!(
+                "{}- {} (file, size={})\n",
+                indent,
+                name,
+                self.size(id)
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TRANSCRIPT: &str = "\
+$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k";
+
+    #[test]
+    fn test_replay() {
+        let fs = FileSystem::replay(TRANSCRIPT).unwrap();
+        let size = |path| fs.size(fs.resolve(fs.root(), path).unwrap());
+        assert_eq!(584, size("/a/e"));
+        assert_eq!(94853, size("/a"));
+        assert_eq!(24933642, size("d"));
+        assert_eq!(48381165, size("/"));
+
+        let small: u64 = fs.dirs_at_most(100_000).map(|(_, size)| size).sum();
+        assert_eq!(95437, small);
+        let (dir, size) = fs.smallest_dir_freeing(70_000_000, 30_000_000).unwrap();
+        assert_eq!(("/d".to_string(), 24933642), (fs.path(dir), size));
+
+        // Listing a directory again does not count its files twice
+        let again = format!("{}\n$ cd /a/e\n$ ls\n584 i", TRANSCRIPT);
+        assert_eq!(fs, FileSystem::replay(&again).unwrap());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let fs = FileSystem::replay(TRANSCRIPT).unwrap();
+        let e = fs.resolve(fs.root(), "a/./e").unwrap();
+        assert_eq!("/a/e", fs.path(e));
+        assert_eq!("/d/k", fs.path(fs.resolve(e, "../../d/k").unwrap()));
+        assert_eq!(fs.root(), fs.resolve(e, "/").unwrap());
+        assert_eq!(fs.root(), fs.resolve(fs.root(), "..").unwrap());
+        assert_eq!(
+            Err(FsError::NotFound("/a/x".to_string())),
+            fs.resolve(e, "../x/y")
+        );
+        assert_eq!(
+            Err(FsError::NotADirectory("/b.txt".to_string())),
+            fs.resolve(e, "/b.txt/c")
+        );
+    }
+
+    #[test]
+    fn test_replay_errors() {
+        let error = |t: &str| FileSystem::replay(t).unwrap_err().to_string();
+        assert_eq!("line 1: ls without a working directory", error("$ ls\n1 a"));
+        assert_eq!("line 1: output outside of ls: 1 a", error("1 a"));
+        assert_eq!(
+            "line 2: /x: no such file or directory",
+            error("$ cd /\n$ cd x")
+        );
+        assert_eq!(
+            "line 4: /a: conflicts with an existing entry",
+            error("$ cd /\n$ ls\n1 a\ndir a")
+        );
+        assert_eq!("line 2: unknown command: $ rm a", error("$ cd /\n$ rm a"));
+        assert_eq!("line 3: invalid file size: x", error("$ cd /\n$ ls\nx a"));
+    }
+
+    #[test]
+    fn test_render() {
+        let fs = FileSystem::replay(TRANSCRIPT).unwrap();
+        assert_eq!(
+            "\
+- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+",
+            fs.tree()
+        );
+        assert_eq!("584\t/a/e\n94853\t/a\n24933642\t/d\n48381165\t/\n", fs.du());
+    }
+}
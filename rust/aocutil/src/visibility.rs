@@ -0,0 +1,202 @@
+use crate::grid::Point;
+use crate::math::gcd;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// A direction from some origin, reduced to lowest terms so that every point along the same ray
+/// has the same direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ray {
+    pub dx: i64,
+    pub dy: i64,
+}
+
+impl Ray {
+    pub const UP: Ray = Ray { dx: 0, dy: 1 };
+
+    /// Panics if both components are zero.
+    pub fn new(dx: i64, dy: i64) -> Self {
+        let g = gcd(dx, dy);
+        assert!(g != 0, "a ray needs a direction");
+        Ray {
+            dx: dx / g,
+            dy: dy / g,
+        }
+    }
+
+    /// The ray from `from` through `to`, unless they are the same point.
+    pub fn between(from: Point, to: Point) -> Option<Self> {
+        if from == to {
+            None
+        } else {
+            Some(Ray::new(to.x - from.x, to.y - from.y))
+        }
+    }
+
+    fn cross(&self, other: &Ray) -> i64 {
+        self.dx * other.dy - self.dy * other.dx
+    }
+
+    fn dot(&self, other: &Ray) -> i64 {
+        self.dx * other.dx + self.dy * other.dy
+    }
+
+    /// Whether the clockwise angle from `start` to this ray is at least half a turn.
+    fn second_half(&self, start: &Ray) -> bool {
+        let cross = start.cross(self);
+        cross > 0 || (cross == 0 && start.dot(self) < 0)
+    }
+
+    /// Compare the clockwise angles from `start` to each ray, with y pointing up. Comparisons
+    /// are exact, so rays are only equal if they are the same ray.
+    pub fn cmp_clockwise(&self, other: &Ray, start: &Ray) -> Ordering {
+        self.second_half(start)
+            .cmp(&other.second_half(start))
+            .then_with(|| self.cross(other).cmp(&0))
+    }
+}
+
+/// Targets grouped by their ray from an origin. Only the nearest target on each ray is visible
+/// from the origin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visibility {
+    origin: Point,
+    rays: HashMap<Ray, Vec<Point>>,
+}
+
+impl Visibility {
+    /// Group `targets` as seen from `origin`, which is ignored if it is one of the targets.
+    pub fn new<I: IntoIterator<Item = Point>>(origin: Point, targets: I) -> Self {
+        let mut rays: HashMap<Ray, Vec<Point>> = HashMap::new();
+        for p in targets {
+            if let Some(ray) = Ray::between(origin, p) {
+                rays.entry(ray).or_default().push(p);
+            }
+        }
+        // Along a ray, the Manhattan distance orders the points by distance
+        for points in rays.values_mut() {
+            points.sort_by_key(|p| (p.x - origin.x).abs() + (p.y - origin.y).abs());
+        }
+
+        Visibility { origin, rays }
+    }
+
+    pub fn origin(&self) -> Point {
+        self.origin
+    }
+
+    /// Number of targets visible from the origin.
+    pub fn visible(&self) -> usize {
+        self.rays.len()
+    }
+
+    /// Targets on `ray`, nearest first.
+    pub fn along(&self, ray: &Ray) -> &[Point] {
+        self.rays.get(ray).map_or(&[], |points| points)
+    }
+
+    /// Rays with targets in clockwise order, starting at `start`.
+    pub fn rays_clockwise(&self, start: &Ray) -> Vec<Ray> {
+        let mut rays: Vec<Ray> = self.rays.keys().copied().collect();
+        rays.sort_by(|a, b| a.cmp_clockwise(b, start));
+        rays
+    }
+
+    /// Order in which a laser turning clockwise from `start` hits every target, taking the
+    /// nearest target on a ray each time it passes.
+    pub fn sweep(&self, start: &Ray) -> Vec<Point> {
+        let rays: Vec<&[Point]> = self
+            .rays_clockwise(start)
+            .iter()
+            .map(|r| self.along(r))
+            .collect();
+        let turns = rays.iter().map(|points| points.len()).max().unwrap_or(0);
+
+        (0..turns)
+            .flat_map(|turn| rays.iter().filter_map(move |points| points.get(turn)))
+            .copied()
+            .collect()
+    }
+}
+
+/// The view from the point which can see most of the others.
+pub fn best_visibility(points: &[Point]) -> Option<Visibility> {
+    points
+        .iter()
+        .map(|&p| Visibility::new(p, points.iter().copied()))
+        .max_by_key(|v| v.visible())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ray() {
+        assert_eq!(Ray { dx: -2, dy: 1 }, Ray::new(-6, 3));
+        assert_eq!(Ray { dx: 0, dy: -1 }, Ray::new(0, -7));
+        assert_eq!(None, Ray::between(Point::new(1, 1), Point::new(1, 1)));
+    }
+
+    #[test]
+    fn test_clockwise() {
+        let rays = [
+            Ray::new(1, 0),
+            Ray::new(-1, 1),
+            Ray::new(0, -1),
+            Ray::new(0, 1),
+            Ray::new(-1, 0),
+            Ray::new(1, 1),
+            Ray::new(1, -1000),
+            Ray::new(-1, -1000),
+        ];
+
+        let mut sorted = rays.to_vec();
+        sorted.sort_by(|a, b| a.cmp_clockwise(b, &Ray::UP));
+        assert_eq!(
+            vec![
+                Ray::new(0, 1),
+                Ray::new(1, 1),
+                Ray::new(1, 0),
+                Ray::new(1, -1000),
+                Ray::new(0, -1),
+                Ray::new(-1, -1000),
+                Ray::new(-1, 0),
+                Ray::new(-1, 1),
+            ],
+            sorted
+        );
+
+        sorted.sort_by(|a, b| a.cmp_clockwise(b, &Ray::new(0, -1)));
+        assert_eq!(Ray::new(0, -1), sorted[0]);
+        assert_eq!(Ray::new(1, -1000), sorted[7]);
+    }
+
+    #[test]
+    fn test_sweep() {
+        let targets = vec![
+            Point::new(0, 2),
+            Point::new(0, 1),
+            Point::new(2, 0),
+            Point::new(0, 0),
+            Point::new(-3, -3),
+            Point::new(-1, -1),
+        ];
+        let v = Visibility::new(Point::new(0, 0), targets);
+        assert_eq!(3, v.visible());
+        assert_eq!(
+            &[Point::new(-1, -1), Point::new(-3, -3)],
+            v.along(&Ray::new(-1, -1))
+        );
+        assert_eq!(
+            vec![
+                Point::new(0, 1),
+                Point::new(2, 0),
+                Point::new(-1, -1),
+                Point::new(0, 2),
+                Point::new(-3, -3),
+            ],
+            v.sweep(&Ray::UP)
+        );
+    }
+}
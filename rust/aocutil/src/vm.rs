@@ -0,0 +1,573 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// An instruction argument.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Imm(i64),
+    Reg(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "==" => Some(Comparison::Eq),
+            "!=" => Some(Comparison::Ne),
+            "<" => Some(Comparison::Lt),
+            "<=" => Some(Comparison::Le),
+            ">" => Some(Comparison::Gt),
+            ">=" => Some(Comparison::Ge),
+            _ => None,
+        }
+    }
+
+    pub fn test(self, a: i64, b: i64) -> bool {
+        match self {
+            Comparison::Eq => a == b,
+            Comparison::Ne => a != b,
+            Comparison::Lt => a < b,
+            Comparison::Le => a <= b,
+            Comparison::Gt => a > b,
+            Comparison::Ge => a >= b,
+        }
+    }
+}
+
+/// A guard on an instruction, e.g. the `if a > 1` of `b inc 5 if a > 1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub lhs: Operand,
+    pub cmp: Comparison,
+    pub rhs: Operand,
+}
+
+impl Condition {
+    pub fn holds(&self, registers: &Registers) -> bool {
+        self.cmp
+            .test(registers.value(&self.lhs), registers.value(&self.rhs))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub op: String,
+    pub args: Vec<Operand>,
+    /// The instruction has no effect unless its condition holds.
+    pub condition: Option<Condition>,
+}
+
+/// Named registers. Registers which have not been written to read as zero.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Registers {
+    values: HashMap<String, i64>,
+}
+
+impl Registers {
+    pub fn get(&self, name: &str) -> i64 {
+        self.values.get(name).copied().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, name: &str, value: i64) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub fn add(&mut self, name: &str, delta: i64) {
+        *self.values.entry(name.to_string()).or_insert(0) += delta;
+    }
+
+    /// The value of an immediate, or the contents of a register.
+    pub fn value(&self, operand: &Operand) -> i64 {
+        match operand {
+            Operand::Imm(v) => *v,
+            Operand::Reg(name) => self.get(name),
+        }
+    }
+
+    /// Registers which have been written to, in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, i64)> {
+        let mut values: Vec<_> = self.values.iter().map(|(k, &v)| (k.as_str(), v)).collect();
+        values.sort_unstable();
+        values.into_iter()
+    }
+}
+
+/// What the program counter does after an instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Effect {
+    Next,
+    /// Move the program counter by an offset.
+    Jump(i64),
+    /// Move the program counter to an address.
+    Goto(i64),
+    Halt,
+}
+
+type Execute = fn(&mut Registers, &[Operand]) -> Effect;
+
+#[derive(Clone)]
+struct Op {
+    arity: usize,
+    cycles: u64,
+    execute: Execute,
+}
+
+/// An error on a (1-based) line of an assembly listing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// An instruction set: the mnemonic, number of arguments, cycle cost and behaviour of each
+/// operation.
+#[derive(Clone, Default)]
+pub struct InstructionSet {
+    ops: HashMap<String, Op>,
+}
+
+impl InstructionSet {
+    pub fn new() -> Self {
+        InstructionSet::default()
+    }
+
+    pub fn op(mut self, name: &str, arity: usize, cycles: u64, execute: Execute) -> Self {
+        self.ops.insert(
+            name.to_string(),
+            Op {
+                arity,
+                cycles,
+                execute,
+            },
+        );
+        self
+    }
+
+    /// Parse one instruction per line, e.g. `jmp +4` or `cpy a, b`. Arguments which are not
+    /// integers name registers. An instruction may name its first argument before the mnemonic
+    /// and end with a condition, so `b inc 5 if a > 1` is `inc b, 5` guarded by `a > 1`.
+    pub fn parse(&self, listing: &str) -> Result<Vec<Instruction>, AsmError> {
+        listing
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(i, l)| {
+                self.parse_line(l).map_err(|message| AsmError {
+                    line: i + 1,
+                    message,
+                })
+            })
+            .collect()
+    }
+
+    fn parse_line(&self, line: &str) -> Result<Instruction, String> {
+        let mut words: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        let condition = match words.iter().position(|&w| w == "if") {
+            Some(i) => {
+                let condition = match words[i + 1..] {
+                    [lhs, cmp, rhs] => Condition {
+                        lhs: operand(lhs),
+                        cmp: Comparison::parse(cmp)
+                            .ok_or_else(|| format!("unknown comparison {}", cmp))?,
+                        rhs: operand(rhs),
+                    },
+                    _ => return Err(format!("invalid condition {}", words[i + 1..].join(" "))),
+                };
+                words.truncate(i);
+                Some(condition)
+            }
+            None => None,
+        };
+        if words.len() > 1 && !self.ops.contains_key(words[0]) && self.ops.contains_key(words[1]) {
+            words.swap(0, 1);
+        }
+
+        let (op, args) = words
+            .split_first()
+            .ok_or_else(|| "missing instruction".to_string())?;
+        let instruction = Instruction {
+            op: op.to_string(),
+            args: args.iter().map(|w| operand(w)).collect(),
+            condition,
+        };
+        self.check(&instruction)?;
+
+        Ok(instruction)
+    }
+
+    /// Whether the instruction is in the set, with the right number of arguments.
+    fn check(&self, instruction: &Instruction) -> Result<(), String> {
+        let op = self
+            .ops
+            .get(&instruction.op)
+            .ok_or_else(|| format!("unknown instruction {}", instruction.op))?;
+        if instruction.args.len() != op.arity {
+            return Err(format!(
+                "{} takes {} arguments but got {}",
+                instruction.op,
+                op.arity,
+                instruction.args.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn operand(word: &str) -> Operand {
+    match word.parse() {
+        Ok(v) => Operand::Imm(v),
+        Err(_) => Operand::Reg(word.to_string()),
+    }
+}
+
+/// How a run ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    /// The program counter left the program, or an instruction halted.
+    Halted,
+    /// The instruction at `pc` was about to run a second time.
+    Looped { pc: i64 },
+}
+
+pub struct Machine<'a> {
+    set: &'a InstructionSet,
+    pub program: Vec<Instruction>,
+    pub pc: i64,
+    pub registers: Registers,
+    /// Number of cycles completed.
+    pub cycle: u64,
+    halted: bool,
+}
+
+impl<'a> Machine<'a> {
+    /// Fails if an instruction is missing from `set` or has the wrong number of arguments. The
+    /// error is on the line of the instruction's (0-based) address plus one.
+    pub fn new(set: &'a InstructionSet, program: Vec<Instruction>) -> Result<Self, AsmError> {
+        for (addr, instruction) in program.iter().enumerate() {
+            set.check(instruction).map_err(|message| AsmError {
+                line: addr + 1,
+                message,
+            })?;
+        }
+
+        Ok(Machine {
+            set,
+            program,
+            pc: 0,
+            registers: Registers::default(),
+            cycle: 0,
+            halted: false,
+        })
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted || self.pc < 0 || self.pc as usize >= self.program.len()
+    }
+
+    /// Execute one instruction, calling `hook` with the cycle number (starting at 1) and the
+    /// registers during each cycle the instruction takes. An instruction whose condition does
+    /// not hold still takes its cycles. Returns false if the machine has halted.
+    pub fn step<F: FnMut(u64, &Registers)>(&mut self, hook: &mut F) -> bool {
+        if self.is_halted() {
+            return false;
+        }
+
+        let instruction = &self.program[self.pc as usize];
+        let op = &self.set.ops[&instruction.op];
+        for _ in 0..op.cycles {
+            self.cycle += 1;
+            hook(self.cycle, &self.registers);
+        }
+
+        let effect = match &instruction.condition {
+            Some(condition) if !condition.holds(&self.registers) => Effect::Next,
+            _ => (op.execute)(&mut self.registers, &instruction.args),
+        };
+        match effect {
+            Effect::Next => self.pc += 1,
+            Effect::Jump(offset) => self.pc += offset,
+            Effect::Goto(addr) => self.pc = addr,
+            Effect::Halt => self.halted = true,
+        }
+
+        true
+    }
+
+    /// Run until the machine halts.
+    pub fn run(&mut self) {
+        self.run_with(|_, _| ());
+    }
+
+    pub fn run_with<F: FnMut(u64, &Registers)>(&mut self, mut hook: F) {
+        while self.step(&mut hook) {}
+    }
+
+    /// Run until the machine halts or an instruction is about to be executed a second time.
+    /// This only detects infinite loops in programs whose jumps do not depend on registers.
+    pub fn run_until_loop(&mut self) -> Outcome {
+        let mut seen = HashSet::new();
+        while !self.is_halted() {
+            if !seen.insert(self.pc) {
+                return Outcome::Looped { pc: self.pc };
+            }
+            self.step(&mut |_, _| ());
+        }
+
+        Outcome::Halted
+    }
+}
+
+/// Try replacing one instruction at a time with `patch` until the program halts. Returns the
+/// address of the patched instruction and the halted machine, or fails if a patched program
+/// does not fit `set`.
+pub fn patch_and_retry<'a, P>(
+    set: &'a InstructionSet,
+    program: &[Instruction],
+    patch: P,
+) -> Result<Option<(usize, Machine<'a>)>, AsmError>
+where
+    P: Fn(&Instruction) -> Option<Instruction>,
+{
+    for (addr, instruction) in program.iter().enumerate() {
+        let mut patched = program.to_vec();
+        patched[addr] = match patch(instruction) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+
+        let mut machine = Machine::new(set, patched)?;
+        if machine.run_until_loop() == Outcome::Halted {
+            return Ok(Some((addr, machine)));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn handheld() -> InstructionSet {
+        InstructionSet::new()
+            .op("acc", 1, 1, |r, a| {
+                r.add("acc", r.value(&a[0]));
+                Effect::Next
+            })
+            .op("jmp", 1, 1, |r, a| Effect::Jump(r.value(&a[0])))
+            .op("nop", 1, 1, |_, _| Effect::Next)
+    }
+
+    const BOOT: &str = "\
+nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6";
+
+    #[test]
+    fn test_loop_detection() {
+        let set = handheld();
+        let mut machine = Machine::new(&set, set.parse(BOOT).unwrap()).unwrap();
+        assert_eq!(Outcome::Looped { pc: 1 }, machine.run_until_loop());
+        assert_eq!(5, machine.registers.get("acc"));
+        assert_eq!(7, machine.cycle);
+    }
+
+    #[test]
+    fn test_patch_and_retry() {
+        let set = handheld();
+        let program = set.parse(BOOT).unwrap();
+        let (addr, machine) = patch_and_retry(&set, &program, |i| {
+            let op = match i.op.as_str() {
+                "jmp" => "nop",
+                "nop" => "jmp",
+                _ => return None,
+            };
+            Some(Instruction {
+                op: op.to_string(),
+                ..i.clone()
+            })
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(7, addr);
+        assert_eq!(8, machine.registers.get("acc"));
+    }
+
+    #[test]
+    fn test_cycles_and_hooks() {
+        let set = InstructionSet::new()
+            .op("noop", 0, 1, |_, _| Effect::Next)
+            .op("addx", 1, 2, |r, a| {
+                r.add("x", r.value(&a[0]));
+                Effect::Next
+            });
+        let mut machine = Machine::new(&set, set.parse("noop\naddx 3\naddx -5").unwrap()).unwrap();
+        machine.registers.set("x", 1);
+
+        let mut during = vec![];
+        machine.run_with(|cycle, r| during.push((cycle, r.get("x"))));
+        assert_eq!(vec![(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)], during);
+        assert_eq!(-1, machine.registers.get("x"));
+        assert_eq!(5, machine.cycle);
+    }
+
+    #[test]
+    fn test_registers_and_halt() {
+        let set = InstructionSet::new()
+            .op("cpy", 2, 1, |r, a| {
+                if let Operand::Reg(dst) = &a[1] {
+                    r.set(dst, r.value(&a[0]));
+                }
+                Effect::Next
+            })
+            .op("jnz", 2, 1, |r, a| match r.value(&a[0]) {
+                0 => Effect::Next,
+                _ => Effect::Jump(r.value(&a[1])),
+            })
+            .op("dec", 1, 1, |r, a| {
+                if let Operand::Reg(dst) = &a[0] {
+                    r.add(dst, -1);
+                }
+                Effect::Next
+            })
+            .op("inc", 1, 1, |r, a| {
+                if let Operand::Reg(dst) = &a[0] {
+                    r.add(dst, 1);
+                }
+                Effect::Next
+            })
+            .op("hlt", 0, 1, |_, _| Effect::Halt);
+        let program = set
+            .parse("cpy 3, a\ninc b\ndec a\njnz a, -2\nhlt\ninc b")
+            .unwrap();
+        let mut machine = Machine::new(&set, program).unwrap();
+        machine.run();
+        assert_eq!(
+            vec![("a", 0), ("b", 3)],
+            machine.registers.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(4, machine.pc);
+
+        assert_eq!(
+            Err(AsmError {
+                line: 2,
+                message: "inc takes 1 arguments but got 2".to_string()
+            }),
+            set.parse("hlt\ninc a b")
+        );
+        assert_eq!(
+            "line 1: unknown instruction mul",
+            set.parse("mul a").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_conditions() {
+        let set = InstructionSet::new()
+            .op("inc", 2, 1, |r, a| {
+                if let Operand::Reg(dst) = &a[0] {
+                    r.add(dst, r.value(&a[1]));
+                }
+                Effect::Next
+            })
+            .op("dec", 2, 1, |r, a| {
+                if let Operand::Reg(dst) = &a[0] {
+                    r.add(dst, -r.value(&a[1]));
+                }
+                Effect::Next
+            });
+        let program = set
+            .parse("b inc 5 if a > 1\na inc 1 if b < 5\nc dec -10 if a >= 1\nc inc -20 if c == 10")
+            .unwrap();
+        assert_eq!(
+            Instruction {
+                op: "inc".to_string(),
+                args: vec![Operand::Reg("b".to_string()), Operand::Imm(5)],
+                condition: Some(Condition {
+                    lhs: Operand::Reg("a".to_string()),
+                    cmp: Comparison::Gt,
+                    rhs: Operand::Imm(1),
+                }),
+            },
+            program[0]
+        );
+
+        let mut machine = Machine::new(&set, program).unwrap();
+        machine.run();
+        assert_eq!(
+            vec![("a", 1), ("c", -10)],
+            machine.registers.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(4, machine.cycle);
+
+        assert_eq!(
+            "line 1: unknown comparison =>",
+            set.parse("a inc 1 if b => 2").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "line 2: invalid condition b >",
+            set.parse("inc a 1\ninc a 1 if b >")
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "line 1: missing instruction",
+            set.parse("if a > 1").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_invalid_program() {
+        let set = handheld();
+        let instruction = |op: &str, args| Instruction {
+            op: op.to_string(),
+            args,
+            condition: None,
+        };
+        let program = vec![
+            instruction("nop", vec![Operand::Imm(0)]),
+            instruction("mul", vec![]),
+        ];
+        assert_eq!(
+            "line 2: unknown instruction mul",
+            Machine::new(&set, program).err().unwrap().to_string()
+        );
+        let program = vec![instruction("jmp", vec![])];
+        assert_eq!(
+            "line 1: jmp takes 1 arguments but got 0",
+            Machine::new(&set, program).err().unwrap().to_string()
+        );
+
+        let program = set.parse(BOOT).unwrap();
+        let result = patch_and_retry(&set, &program, |i| Some(instruction("mul", i.args.clone())));
+        assert_eq!(1, result.err().unwrap().line);
+    }
+}